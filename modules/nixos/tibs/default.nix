@@ -12,6 +12,7 @@ with lib; let
     name = "graphics-drivers";
     paths = [ config.hardware.graphics.package ] ++ config.hardware.graphics.extraPackages;
   };
+  deviceSettings = settings: concatStringsSep "," (mapAttrsToList (name: value: "${name}=${if isBool value then (if value then "1" else "0") else toString value}") settings);
 in {
   options.tibs = {
     enable = mkOption {
@@ -34,6 +35,90 @@ in {
       default = "${pkgs.catppuccin-cursors.frappeMauve}/share";
       description = "Path to the cursors";
     };
+    background = mkOption {
+      type = types.nullOr types.path;
+      default = null;
+      description = "Image, animated image or folder of images (slideshow) to use instead of the mesh gradient background";
+    };
+    multiMonitor = mkOption {
+      type = types.enum [ "mirror" "primary" "follow-cursor" ];
+      default = "primary";
+      description = "What to show when more than one monitor is connected: the UI on every monitor, only on the primary one, or on the monitor the cursor is on";
+    };
+    drmDevice = mkOption {
+      type = types.nullOr types.str;
+      default = null;
+      example = "/dev/dri/card1";
+      description = "GPU to display tibs on, defaults to the one picked by aquamarine";
+    };
+    primaryOutput = mkOption {
+      type = types.nullOr types.str;
+      default = null;
+      example = "DP-1";
+      description = "Name of the primary monitor, defaults to the first one";
+    };
+    uiScale = mkOption {
+      type = types.either (types.enum [ "auto" ]) types.float;
+      default = "auto";
+      description = "Scale of the UI, can be fractional. \"auto\" guesses it from the DPI of each monitor";
+    };
+    outputs = mkOption {
+      type = types.listOf types.str;
+      default = [];
+      example = [ "eDP-1,mode=2560x1600@60,scale=1.5" "DP-1,transform=90" ];
+      description = "Mode, transform (normal, 90, 180, 270, flipped, flipped-90, ...) and scale of each monitor";
+    };
+    fontFamily = mkOption {
+      type = types.listOf types.str;
+      default = [ "UbuntuSans NF" "Ubuntu Sans" "sans-serif" ];
+      example = [ "Inter" "asset:fonts.Inter" "/usr/share/fonts/Inter.ttf" ];
+      description = "Fallback chain of fonts: installed families, font files or fonts bundled in the assets folder";
+    };
+    fonts = mkOption {
+      type = types.attrsOf types.str;
+      default = {};
+      example = { bold = "Inter:800"; clock = "asset:fonts.Clock"; };
+      description = "Named fonts, as a fallback chain and an optional weight. regular, bold and medium replace the built-in ones";
+    };
+    keyboardLayouts = mkOption {
+      type = types.listOf types.str;
+      default = [];
+      example = [ "us" "pt" "us(intl)" ];
+      description = "Keyboard layouts that can be picked on the login screen, the first one is the default. The layout of each user is remembered";
+    };
+    keybindings = mkOption {
+      type = types.attrsOf (types.listOf types.str);
+      default = {};
+      example = { delete-word-backward = [ "Ctrl+BackSpace" "Alt+BackSpace" ]; select-all = []; };
+      description = "Replaces the shortcuts of an action (select-all, delete-word-backward, delete-word-forward, delete-to-start, word-left, word-right, next-keyboard-layout, toggle-devtools), an empty list disables it";
+    };
+    numlock = mkOption {
+      type = types.bool;
+      default = false;
+      description = "Turn Num Lock on when tibs starts";
+    };
+    mouse = mkOption {
+      type = types.attrsOf (types.oneOf [ types.bool types.float types.str ]);
+      default = { };
+      example = { accel-profile = "flat"; accel-speed = 0.2; left-handed = false; };
+      description = "libinput settings of mice (tap, natural-scroll, left-handed, disable-while-typing, accel-profile, accel-speed)";
+    };
+    touchpad = mkOption {
+      type = types.attrsOf (types.oneOf [ types.bool types.float types.str ]);
+      default = { };
+      example = { tap = true; natural-scroll = true; disable-while-typing = true; };
+      description = "libinput settings of touchpads, same as mouse";
+    };
+    userInputSettings = mkOption {
+      type = types.bool;
+      default = false;
+      description = "Use the mouse= and touchpad= settings the user who logged in last wrote in ~/.config/tibs/input";
+    };
+    hideUserList = mkOption {
+      type = types.bool;
+      default = false;
+      description = "Don't list the users on the login screen, the username has to be typed";
+    };
     cursorName = mkOption {
       type = types.string;
       default = "catppuccin-frappe-mauve-cursors";
@@ -59,6 +144,7 @@ in {
       serviceConfig = {
         Type = "simple";
         TTYPath="/dev/tty1";
+        StateDirectory = "tibs";
         StandardInput = "tty";
         StandardOutput = "tty";
         ExecStart = pkgs.writeShellScript "tibs-service" ''
@@ -69,7 +155,7 @@ in {
           }
           export OPENGL_DRIVER_PATH=${driversEnv}
           ln -sfn $OPENGL_DRIVER_PATH /run/opengl-driver
-          HOME="/root" HYPRCURSOR_THEME="${config.tibs.cursorName}" XDG_DATA_DIRS="${config.tibs.cursorThemesPath}" TIBS_ASSETS_FOLDER="${config.tibs.assetsDir}" LD_LIBRARY_PATH="${lib.getLib pkgs.libGL}/lib" ${config.tibs.tibsPath}
+          HOME="/root" HYPRCURSOR_THEME="${config.tibs.cursorName}" XDG_DATA_DIRS="${config.tibs.cursorThemesPath}" TIBS_ASSETS_FOLDER="${config.tibs.assetsDir}" ${optionalString (config.tibs.background != null) ''TIBS_BACKGROUND="${config.tibs.background}"''} TIBS_MULTI_MONITOR="${config.tibs.multiMonitor}" TIBS_UI_SCALE="${toString config.tibs.uiScale}" TIBS_FONT_FAMILY="${concatStringsSep "," config.tibs.fontFamily}" TIBS_FONTS="${concatStringsSep ";" (mapAttrsToList (name: font: "${name}=${font}") config.tibs.fonts)}" TIBS_OUTPUTS="${concatStringsSep ";" config.tibs.outputs}" TIBS_KEYBOARD_LAYOUTS="${concatStringsSep "," config.tibs.keyboardLayouts}" TIBS_KEYBINDINGS="${concatStringsSep ";" (mapAttrsToList (action: shortcuts: "${action}=${concatStringsSep "," shortcuts}") config.tibs.keybindings)}" TIBS_NUMLOCK="${if config.tibs.numlock then "1" else "0"}" TIBS_MOUSE="${deviceSettings config.tibs.mouse}" TIBS_TOUCHPAD="${deviceSettings config.tibs.touchpad}" TIBS_USER_INPUT_SETTINGS="${if config.tibs.userInputSettings then "1" else "0"}" TIBS_HIDE_USER_LIST="${if config.tibs.hideUserList then "1" else "0"}" ${optionalString (config.tibs.primaryOutput != null) ''TIBS_PRIMARY_OUTPUT="${config.tibs.primaryOutput}"''} ${optionalString (config.tibs.drmDevice != null) ''TIBS_DRM_DEVICE="${config.tibs.drmDevice}"''} LD_LIBRARY_PATH="${lib.getLib pkgs.libGL}/lib" ${config.tibs.tibsPath}
           exit_code=$?
 
           if [ $exit_code -eq 139 ]; then
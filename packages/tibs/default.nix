@@ -21,6 +21,7 @@ in
       glibc
       libinput
       libxkbcommon
+      udev
       cairo
       hyprcursor
       inputs.hyprutils.packages.${system}.default
@@ -23,6 +23,8 @@ fn main() {
 		"gbm",
 		"libunwind",
 		"xkbcommon",
+		"libudev",
+		"libinput",
 	] {
 		let libs = pkg_config::probe_library(lib).expect(&format!("Failed to find {}", lib));
 		for path in libs.link_paths {
@@ -0,0 +1,102 @@
+#include <cstdlib>
+#include <cstring>
+#include <fcntl.h>
+#include <libudev.h>
+#include <print>
+#include <rustamarine/internal/drm-monitor.hpp>
+#include <rustamarine/internal/rustamarine.hpp>
+#include <unistd.h>
+#include <xf86drm.h>
+
+using namespace rustamarine;
+
+DrmMonitor::DrmMonitor() {
+	udevContext = udev_new();
+	if (!udevContext) {
+		std::println("ERROR: Failed to create the udev context, GPU hotplug won't be detected");
+		return;
+	}
+	monitor = udev_monitor_new_from_netlink(udevContext, "udev");
+	if (!monitor) {
+		std::println("ERROR: Failed to create the udev monitor, GPU hotplug won't be detected");
+		return;
+	}
+	// Only the devices themselves, not their connectors
+	udev_monitor_filter_add_match_subsystem_devtype(monitor, "drm", "drm_minor");
+	udev_monitor_enable_receiving(monitor);
+}
+DrmMonitor::~DrmMonitor() {
+	if (monitor)
+		udev_monitor_unref(monitor);
+	if (udevContext)
+		udev_unref(udevContext);
+}
+int DrmMonitor::fd() {
+	return monitor ? udev_monitor_get_fd(monitor) : -1;
+}
+void DrmMonitor::dispatch() {
+	if (!monitor)
+		return;
+	while (auto device = udev_monitor_receive_device(monitor)) {
+		const char *action = udev_device_get_action(device);
+		const char *sysname = udev_device_get_sysname(device);
+		const char *devnode = udev_device_get_devnode(device);
+		// Render nodes (renderD128) can't display anything
+		if (action && devnode && sysname && !strncmp(sysname, "card", 4)) {
+			if (!strcmp(action, "add"))
+				events.push_back({RMAR_DRM_DEVICE_ADDED, devnode});
+			else if (!strcmp(action, "remove"))
+				events.push_back({RMAR_DRM_DEVICE_REMOVED, devnode});
+		}
+		udev_device_unref(device);
+	}
+}
+
+struct RustamarineDrmEvents rmarTakeDrmEvents(struct Rustamarine *self) {
+	struct RustamarineDrmEvents result;
+	auto &events = self->drmMonitor->events;
+	result.count = events.size();
+	result.events = nullptr;
+	if (result.count > 0) {
+		result.events = (struct RustamarineDrmEvent *)malloc(
+				sizeof(struct RustamarineDrmEvent) * result.count);
+		for (size_t i = 0; i < result.count; ++i) {
+			result.events[i].type = events[i].type;
+			result.events[i].path = strdup(events[i].path.c_str());
+		}
+	}
+	events.clear();
+	return result;
+}
+void rmarFreeDrmEvents(struct RustamarineDrmEvents events) {
+	for (size_t i = 0; i < events.count; ++i)
+		free((void *)events.events[i].path);
+	free(events.events);
+}
+const char *rmarGetDrmDevice(struct Rustamarine *self) {
+	if (self->drmDevice.empty()) {
+		int fd = self->backend->drmFD();
+		if (fd < 0)
+			return nullptr;
+		char *name = drmGetDeviceNameFromFd2(fd);
+		if (!name)
+			return nullptr;
+		self->drmDevice = name;
+		free(name);
+	}
+	return self->drmDevice.c_str();
+}
+bool rmarIsBootFramebuffer(const char *path) {
+	int fd = open(path, O_RDWR | O_CLOEXEC);
+	if (fd < 0)
+		return false;
+	auto version = drmGetVersion(fd);
+	close(fd);
+	if (!version)
+		return false;
+	std::string driver(version->name, version->name_len);
+	drmFreeVersion(version);
+	// Framebuffers set up by the firmware, they're replaced once the driver of the GPU is loaded
+	return driver == "simpledrm" || driver == "efidrm" || driver == "vesadrm" ||
+				 driver == "ofdrm";
+}
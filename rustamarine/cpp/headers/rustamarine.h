@@ -12,7 +12,47 @@ extern "C" {
 struct RustamarineScreen;
 struct Rustamarine;
 
-struct Rustamarine *rmarInitialize();
+enum RustamarineBackendRequest {
+	RMAR_BACKEND_DISABLED,
+	// Used if it can be started
+	RMAR_BACKEND_OPTIONAL,
+	// Initialization fails if it can't be started
+	RMAR_BACKEND_REQUIRED,
+	// Only used if none of the other backends could be started
+	RMAR_BACKEND_FALLBACK,
+};
+struct RustamarineOptions {
+	enum RustamarineBackendRequest drm, wayland, headless;
+	// Path of the DRM device to use (e.g. /dev/dri/card1), NULL to let aquamarine pick one
+	const char *drmDevice;
+};
+
+// Returns NULL if the backends or OpenGL couldn't be initialized, with the reason in `error`
+// (which must be freed with rmarFreeError)
+struct Rustamarine *rmarInitialize(struct RustamarineOptions options, char **error);
+void rmarFreeError(char *error);
+
+enum RustamarineDrmEventType {
+	RMAR_DRM_DEVICE_ADDED,
+	RMAR_DRM_DEVICE_REMOVED,
+};
+struct RustamarineDrmEvent {
+	enum RustamarineDrmEventType type;
+	// e.g. /dev/dri/card1
+	const char *path;
+};
+struct RustamarineDrmEvents {
+	struct RustamarineDrmEvent *events;
+	size_t count;
+};
+// DRM devices that were added or removed since the last call, as reported by udev. Must be freed
+// with rmarFreeDrmEvents
+struct RustamarineDrmEvents rmarTakeDrmEvents(struct Rustamarine *self);
+void rmarFreeDrmEvents(struct RustamarineDrmEvents events);
+// Path of the DRM device the backend is using, NULL if it isn't using DRM
+const char *rmarGetDrmDevice(struct Rustamarine *self);
+// Whether the DRM device at `path` is a framebuffer set up by the firmware (simpledrm and friends)
+bool rmarIsBootFramebuffer(const char *path);
 void *rmarGetProcAddress(struct Rustamarine *self, const char *procName);
 void rmarPollEvents(struct Rustamarine *self);
 void rmarTearDown(struct Rustamarine *self);
@@ -34,9 +74,44 @@ void rmarScreenSetOnRender(struct RustamarineScreen *screen,
 unsigned int rmarScreenGetWidth(const struct RustamarineScreen *screen);
 unsigned int rmarScreenGetHeight(const struct RustamarineScreen *screen);
 float rmarScreenGetRefreshRate(const struct RustamarineScreen *screen);
+// Physical size in millimeters, 0 if unknown (e.g. projectors and nested backends)
+unsigned int rmarScreenGetPhysicalWidth(const struct RustamarineScreen *screen);
+unsigned int rmarScreenGetPhysicalHeight(const struct RustamarineScreen *screen);
 const char *rmarScreenGetName(const struct RustamarineScreen *screen);
 bool rmarScreenIsEnabled(const struct RustamarineScreen *screen);
 void rmarScreenSetEnabled(const struct RustamarineScreen *screen, bool isEnabled);
+uint64_t rmarScreenGetId(const struct RustamarineScreen *screen);
+
+struct RustamarineMode {
+	unsigned int width, height;
+	// In Hz
+	float refreshRate;
+	bool preferred;
+};
+struct RustamarineModes {
+	struct RustamarineMode *modes;
+	size_t count;
+};
+struct RustamarineModes rmarScreenGetModes(const struct RustamarineScreen *screen);
+void rmarFreeModes(struct RustamarineModes modes);
+// Uses one of the modes supported by the screen, or a custom mode if there's none with that size and
+// refresh rate. Returns false (and keeps the current mode) if the mode is rejected.
+bool rmarScreenSetMode(struct RustamarineScreen *screen, struct RustamarineMode mode);
+// The transform and the scale aren't applied by rustamarine, they're only stored so the renderer
+// can apply them. The transform uses the values of wl_output_transform.
+void rmarScreenSetTransform(struct RustamarineScreen *screen, uint32_t transform);
+uint32_t rmarScreenGetTransform(const struct RustamarineScreen *screen);
+void rmarScreenSetScale(struct RustamarineScreen *screen, float scale);
+float rmarScreenGetScale(const struct RustamarineScreen *screen);
+// Returns NULL if the screen was disconnected
+struct RustamarineScreen *rmarGetScreenById(struct Rustamarine *self, uint64_t id);
+// Called with the id and name of the screen, removed screens are only freed after the callback returns
+void rmarSetOnScreenAdded(struct Rustamarine *self,
+													void (*callback)(void *, uint64_t, const char *),
+													void *context);
+void rmarSetOnScreenRemoved(struct Rustamarine *self,
+														void (*callback)(void *, uint64_t, const char *),
+														void *context);
 
 bool rmarIsKeyDown(struct Rustamarine* rmar, uint32_t key);
 bool rmarIsKeyPressed(struct Rustamarine* rmar, uint32_t key);
@@ -59,6 +134,133 @@ double rmarGetMouseScrollY(struct Rustamarine* rmar);
 
 const char* rmarGetTypedCharacters(struct Rustamarine* rmar);
 
+enum RustamarineEventType {
+	RMAR_EVENT_KEY,
+	RMAR_EVENT_TEXT,
+	RMAR_EVENT_POINTER_MOTION,
+	RMAR_EVENT_POINTER_BUTTON,
+	RMAR_EVENT_POINTER_AXIS,
+	RMAR_EVENT_DEVICE_ADDED,
+	RMAR_EVENT_DEVICE_REMOVED,
+	RMAR_EVENT_TOUCH_DOWN,
+	RMAR_EVENT_TOUCH_MOTION,
+	RMAR_EVENT_TOUCH_UP,
+	// The touch was taken over by something else (e.g. a palm was detected), it's not a tap
+	RMAR_EVENT_TOUCH_CANCEL,
+	RMAR_EVENT_TABLET_TOOL_AXIS,
+	RMAR_EVENT_TABLET_TOOL_PROXIMITY,
+	RMAR_EVENT_TABLET_TOOL_TIP,
+	RMAR_EVENT_TABLET_TOOL_BUTTON,
+};
+enum RustamarineDeviceType {
+	RMAR_DEVICE_KEYBOARD,
+	RMAR_DEVICE_POINTER,
+	RMAR_DEVICE_TOUCH,
+	RMAR_DEVICE_TABLET,
+	// A pointer that can tap, its events are pointer events
+	RMAR_DEVICE_TOUCHPAD,
+};
+// Bit mask of the modifiers that are active
+enum RustamarineModifier {
+	RMAR_MODIFIER_SHIFT = 1 << 0,
+	RMAR_MODIFIER_CTRL = 1 << 1,
+	RMAR_MODIFIER_ALT = 1 << 2,
+	RMAR_MODIFIER_SUPER = 1 << 3,
+	RMAR_MODIFIER_CAPS_LOCK = 1 << 4,
+	RMAR_MODIFIER_NUM_LOCK = 1 << 5,
+};
+enum RustamarineModifierState {
+	// Modifiers whose key is held down
+	RMAR_MODIFIERS_DEPRESSED,
+	// Sticky modifiers that apply to the next key only
+	RMAR_MODIFIERS_LATCHED,
+	RMAR_MODIFIERS_LOCKED,
+	// What applies to the next key, Caps Lock and Num Lock are only set while locked
+	RMAR_MODIFIERS_EFFECTIVE,
+};
+// RMAR_MODIFIER_* bit mask of all the keyboards
+uint32_t rmarGetModifiers(struct Rustamarine *rmar, enum RustamarineModifierState state);
+// Locks or unlocks RMAR_MODIFIER_CAPS_LOCK and RMAR_MODIFIER_NUM_LOCK in `modifiers` on every
+// keyboard. Keyboards that are plugged in later start with the locks that were set last
+void rmarSetLocks(struct Rustamarine *rmar, uint32_t modifiers, bool locked);
+// Keyboard LEDs, the same bits as libinput uses
+enum RustamarineLed {
+	RMAR_LED_NUM_LOCK = 1 << 0,
+	RMAR_LED_CAPS_LOCK = 1 << 1,
+	RMAR_LED_SCROLL_LOCK = 1 << 2,
+};
+// RMAR_LED_* bit mask of the LEDs that are on, on any keyboard
+uint32_t rmarGetLeds(struct Rustamarine *rmar);
+// XKB_KEY_NoSymbol (0) if there's no keysym with that name, the case is ignored
+uint32_t rmarKeysymFromName(const char *name);
+uint32_t rmarKeysymToLower(uint32_t keysym);
+uint32_t rmarKeysymToUpper(uint32_t keysym);
+
+// Comma separated xkb layouts and variants (e.g. "us,pt" and "intl,"), the active layout goes back
+// to the first one. Returns false and keeps the current keymap if it can't be compiled. By default
+// the layout is read from RUSTAMARINE_KB_LAYOUT
+bool rmarSetKeyboardLayouts(struct Rustamarine *rmar, const char *layouts, const char *variants);
+size_t rmarGetKeyboardLayoutCount(struct Rustamarine *rmar);
+// Description of the layout (e.g. "English (US)"), NULL if there's no keyboard or no such layout.
+// Valid until the layouts change
+const char *rmarGetKeyboardLayoutName(struct Rustamarine *rmar, size_t index);
+size_t rmarGetActiveKeyboardLayout(struct Rustamarine *rmar);
+void rmarSetActiveKeyboardLayout(struct Rustamarine *rmar, size_t index);
+// Keysym of the key with the evdev `keycode` in the active layout, at shift `level` (0 without
+// Shift, 1 with it). Works without a keyboard plugged in, XKB_KEY_NoSymbol if there's none
+uint32_t rmarGetKeyKeysym(struct Rustamarine *rmar, uint32_t keycode, uint32_t level);
+// Writes the UTF-8 text typed by `keysym` and a NUL to `buffer`, returns its length without the
+// NUL, 0 if it doesn't type anything or doesn't fit
+size_t rmarKeysymToUtf8(uint32_t keysym, char *buffer, size_t size);
+enum RustamarineAccelProfile {
+	RMAR_ACCEL_PROFILE_DEFAULT,
+	RMAR_ACCEL_PROFILE_FLAT,
+	RMAR_ACCEL_PROFILE_ADAPTIVE,
+};
+// libinput settings, the ones a device doesn't support are skipped
+struct RustamarineDeviceConfig {
+	// -1 keeps the libinput default, 0 disables and 1 enables
+	int tapToClick, naturalScroll, leftHanded, disableWhileTyping;
+	enum RustamarineAccelProfile accelProfile;
+	// From -1 (slowest) to 1 (fastest), NAN keeps the default
+	double accelSpeed;
+};
+// Applies `config` to the devices of `type` that are plugged in and the ones plugged in later
+void rmarSetDeviceConfig(struct Rustamarine *rmar, enum RustamarineDeviceType type,
+												 struct RustamarineDeviceConfig config);
+// Only the fields of the type of event are set
+struct RustamarineEvent {
+	enum RustamarineEventType type;
+	// RMAR_EVENT_KEY: evdev keycode, keysym and the modifiers after the key was handled
+	uint32_t keycode, keysym, modifiers;
+	// RMAR_EVENT_POINTER_BUTTON: 0 is the left button, RMAR_EVENT_TABLET_TOOL_BUTTON: evdev code
+	uint32_t button;
+	// RMAR_EVENT_KEY, RMAR_EVENT_*_BUTTON, RMAR_EVENT_TABLET_TOOL_TIP: touching the tablet,
+	// RMAR_EVENT_TABLET_TOOL_PROXIMITY: the tool came close to the tablet
+	bool pressed;
+	// RMAR_EVENT_TOUCH_*: finger, unique while it touches the screen
+	int32_t touchId;
+	// RMAR_EVENT_TEXT: UTF-8 text, RMAR_EVENT_DEVICE_*: name of the device
+	const char *text;
+	// RMAR_EVENT_POINTER_MOTION: new position of the mouse
+	// RMAR_EVENT_TOUCH_DOWN, RMAR_EVENT_TOUCH_MOTION, RMAR_EVENT_TABLET_TOOL_*: position from 0 to 1
+	// on the device, in the orientation of the screen it's mapped to before its transform
+	double x, y;
+	// RMAR_EVENT_POINTER_MOTION: how much the mouse moved, RMAR_EVENT_POINTER_AXIS: scroll
+	double dx, dy;
+	// RMAR_EVENT_TABLET_TOOL_*: from 0 to 1
+	double pressure;
+	// RMAR_EVENT_DEVICE_*
+	enum RustamarineDeviceType deviceType;
+};
+struct RustamarineEvents {
+	struct RustamarineEvent *events;
+	size_t count;
+};
+// Input events since the last call, in the order they happened. Must be freed with rmarFreeEvents
+struct RustamarineEvents rmarDrainEvents(struct Rustamarine *rmar);
+void rmarFreeEvents(struct RustamarineEvents events);
+
 bool rmarIsDRM(struct Rustamarine* rmar);
 void rmarGoToTTY(struct Rustamarine* rmar, uint16_t tty);
 bool rmarIsOnOriginalTTY(struct Rustamarine* rmar);
@@ -0,0 +1,32 @@
+#pragma once
+#include <rustamarine.h>
+#include <string>
+#include <vector>
+
+struct udev;
+struct udev_monitor;
+
+namespace rustamarine {
+// Watches udev for DRM devices (/dev/dri/card*) being added or removed, e.g. when the driver of the
+// GPU replaces the boot framebuffer (simpledrm)
+class DrmMonitor {
+public:
+	struct Event {
+		RustamarineDrmEventType type;
+		std::string path;
+	};
+	DrmMonitor();
+	~DrmMonitor();
+	DrmMonitor(const DrmMonitor &) = delete;
+	DrmMonitor &operator=(const DrmMonitor &) = delete;
+	// -1 if udev isn't available
+	int fd();
+	// Reads the pending udev events into `events`
+	void dispatch();
+	std::vector<Event> events;
+
+private:
+	struct udev *udevContext = nullptr;
+	struct udev_monitor *monitor = nullptr;
+};
+} // namespace rustamarine
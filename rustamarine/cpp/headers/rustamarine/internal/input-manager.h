@@ -3,7 +3,9 @@
 #include <hyprutils/signal/Listener.hpp>
 #include <rustamarine.h>
 #include "rustamarine/internal/utils.hpp"
+#include <deque>
 #include <map>
+#include <libinput.h>
 #include <sys/types.h>
 #include <string>
 #include <vector>
@@ -18,6 +20,8 @@ namespace rustamarine {
 	struct MouseButtonState {
 		bool down = false;        // true if the key is currently pressed
 		bool justChanged = false; // true if the key state just changed (pressed or released this frame)
+		// Set even if the button was pressed and released in the same frame
+		bool pressedThisFrame = false, releasedThisFrame = false;
 	};
 	struct KeyState {
 			bool down = false;        // true if the key is currently pressed
@@ -26,7 +30,20 @@ namespace rustamarine {
 			bool shouldTypeChar = false;
 			uint64_t lastTypedCharTimestamp;
 			uint64_t stateChangedTimestamp;
+			// Set even if the key was pressed and released in the same frame
+			bool pressedThisFrame = false, releasedThisFrame = false;
 		};
+	// Queued for rmarDrainEvents, the polling state (key states, mouse position, typed characters...)
+	// is updated from the same events
+	struct InputEvent {
+		RustamarineEventType type;
+		uint32_t keycode = 0, keysym = 0, modifiers = 0, button = 0;
+		bool pressed = false;
+		int32_t touchId = 0;
+		std::string text;
+		double x = 0, y = 0, dx = 0, dy = 0, pressure = 0;
+		RustamarineDeviceType deviceType = RMAR_DEVICE_KEYBOARD;
+	};
 
 	class InputManager;
 	class Mouse : public std::enable_shared_from_this<Mouse> {
@@ -35,12 +52,32 @@ namespace rustamarine {
 		explicit Mouse(SP<Aquamarine::IPointer> pointer, InputManager* inputManager);
 		InputManager* inputManager;
 		SP<Aquamarine::IPointer> pointer;
+		// RMAR_DEVICE_TOUCHPAD or RMAR_DEVICE_POINTER
+		RustamarineDeviceType type;
 		CHyprSignalListener onRelativeMoveListenerListener, onWarpListener, onScrollListener, onButtonChangeListener, onDisconnectListener;
 		std::map<uint32_t, MouseButtonState> mouseButtonStates;
 		void registerListeners();
 		friend InputManager;
 
 	};
+	class Touch {
+		public:
+		explicit Touch(SP<Aquamarine::ITouch> touch, InputManager* inputManager);
+		InputManager* inputManager;
+		SP<Aquamarine::ITouch> touch;
+		CHyprSignalListener onDownListener, onMoveListener, onUpListener, onCancelListener, onDisconnectListener;
+		void registerListeners();
+	};
+	class Tablet {
+		public:
+		explicit Tablet(SP<Aquamarine::ITablet> tablet, InputManager* inputManager);
+		InputManager* inputManager;
+		SP<Aquamarine::ITablet> tablet;
+		CHyprSignalListener onAxisListener, onProximityListener, onTipListener, onButtonListener, onDisconnectListener;
+		// Axis events only have the axes that changed
+		double x = 0, y = 0, pressure = 0;
+		void registerListeners();
+	};
 	class Keyboard : public std::enable_shared_from_this<Keyboard> {
 		public:
 		static SP<Keyboard> create(SP<Aquamarine::IKeyboard> keyboard, InputManager* inputManager);
@@ -58,6 +95,16 @@ namespace rustamarine {
 
 		// Handle key event with xkbcommon
 		void handleKeyEvent(xkb_keysym_t keysym);
+		// RMAR_MODIFIER_* bit mask of the current xkb state
+		uint32_t modifiers(RustamarineModifierState state);
+		// Locks the layout (xkb group) at `index`
+		void setActiveLayout(uint32_t index);
+		// Locks or unlocks the RMAR_MODIFIER_CAPS_LOCK and RMAR_MODIFIER_NUM_LOCK bits of `modifiers`
+		void setLocks(uint32_t modifiers, bool locked);
+		// Turns the LEDs of the keyboard on and off to match the xkb state
+		void updateLeds(bool force = false);
+		// RMAR_LED_* bit mask of the LEDs that are on
+		uint32_t leds = 0;
 
 		// xkbcommon state
 		struct xkb_context* xkbContext = nullptr;
@@ -80,11 +127,19 @@ namespace rustamarine {
 
 		// Get UTF-8 character string for the current frame
 		void onFrameEnd();
+		// Queues `event` and updates the mouse and typed characters of the current frame with it
+		void handleEvent(InputEvent event);
+		// Events that weren't drained yet, the oldest ones are dropped if nobody drains them
+		std::deque<InputEvent> events;
 		CHyprSignalListener
 			onNewKeyboardListener,
-			onNewMouseListener;
+			onNewMouseListener,
+			onNewTouchListener,
+			onNewTabletListener;
 		SP<Rustamarine> rmar;
 		std::vector<SP<Mouse>> mouses;
+		std::vector<SP<Touch>> touches;
+		std::vector<SP<Tablet>> tablets;
 		std::vector<SP<Keyboard>> keyboards;
 		double mouseDeltaX = 0;
 		double mouseDeltaY = 0;
@@ -96,7 +151,22 @@ namespace rustamarine {
 		// Character input tracking
 		std::string currentFrameUtf8Input;
 
+		// Layouts of the keymap of every keyboard, RUSTAMARINE_KB_LAYOUT is used if they're empty
+		std::string layouts, variants;
+		uint32_t activeLayout = 0;
+		// Compiled on demand from the same layouts as the keyboards, for rmarGetKeyKeysym to work
+		// without a keyboard
+		std::shared_ptr<xkb_keymap> labelsKeymap;
+		// RMAR_MODIFIER_* locks new keyboards start with
+		uint32_t locks = 0;
+		// Set with rmarSetDeviceConfig, applied to new devices of the type
+		std::map<RustamarineDeviceType, RustamarineDeviceConfig> deviceConfigs;
+		// Applies the config of `type` to the device, if it's managed by libinput
+		void configureDevice(libinput_device *device, RustamarineDeviceType type);
+
 		friend Mouse;
 		friend Keyboard;
+		friend Touch;
+		friend Tablet;
 	};
 }
@@ -1,5 +1,6 @@
 #include "rustamarine/internal/rustamarine.hpp"
-void initializeOpenGL(SP<Rustamarine> rmar);
+// Returns an empty string on success, or the reason it failed
+std::string initializeOpenGL(SP<Rustamarine> rmar);
 void tearDownOpenGL(RustamarineOpenGLContext *opengl);
 
 static const char *eglErrorToString(EGLint error) {
@@ -7,6 +7,7 @@
 #include <hyprutils/signal/Listener.hpp>
 #include <rustamarine.h>
 #include <rustamarine/internal/screen.hpp>
+#include <rustamarine/internal/drm-monitor.hpp>
 #include <rustamarine/internal/input-manager.h>
 #include <vector>
 
@@ -14,6 +15,12 @@ struct RustamarineOpenGLContext {
 	void *gbmDevice = nullptr, *eglDevice = nullptr, *eglDisplay = nullptr, *eglContext = nullptr;
 	Hyprutils::OS::CFileDescriptor gbmFd;
 };
+struct RustamarineScreenCallback {
+	void *context = nullptr;
+	void (*callback)(void *, uint64_t, const char *) = nullptr;
+	void call(RustamarineScreen *screen);
+	void set(void (*callback)(void *, uint64_t, const char *), void *context);
+};
 struct Rustamarine {
 	SP<Aquamarine::CBackend> backend;
 	std::vector<SP<RustamarineScreen>> screens;
@@ -22,9 +29,17 @@ struct Rustamarine {
 	} listeners;
 	RustamarineOpenGLContext openGLContext;
 	rustamarine::InputManager inputManager;
+	// Ids are never reused, so a stale id can't point to a different screen
+	uint64_t nextScreenId = 1;
+	RustamarineScreenCallback onScreenAdded, onScreenRemoved;
+	SP<rustamarine::DrmMonitor> drmMonitor;
+	// Cached by rmarGetDrmDevice
+	std::string drmDevice;
 };
 #undef Listener
 
 SP<RustamarineScreen> createScreenFromOutput(SP<Rustamarine> rustamarine,
 																						 SP<Aquamarine::IOutput> output);
+void removeDestroyedScreens(Rustamarine *rustamarine);
+void releaseScreen(RustamarineScreen *screen);
 void setup_segfault_handler();
@@ -48,12 +48,17 @@ struct RustamarineScreen {
 	bool updateSwapchain();
 	bool isVBlank = false;
 	Hyprutils::Signal::CHyprSignalListener needsFrameListener, frameListener,
-			onStateListener, presentListener;
+			onStateListener, presentListener, destroyListener;
 	SP<rustamarine::RenderBuffer> currentBuffer;
 	bool ensureCurrentBufferIsSet();
 	// onRender callback fields
 	void *onRenderContext = nullptr;
 	void (*onRenderCFunc)(void *, RustamarineScreen *) = nullptr;
+	uint64_t id = 0;
+	uint32_t transform = 0;
+	float scale = 1.0;
+	// Set when the output is unplugged, the screen is removed at the end of rmarPollEvents
+	bool destroyed = false;
 
 	~RustamarineScreen();
 };
@@ -1,7 +1,10 @@
 #include <aquamarine/input/Input.hpp>
 #include <chrono>
 #include <cstdio>
+#include <cstdlib>
 #include <cstring>
+#include <algorithm>
+#include <cmath>
 #include <hyprutils/memory/SharedPtr.hpp>
 #include <print>
 #include <rustamarine/internal/rustamarine.hpp>
@@ -11,10 +14,15 @@
 
 using namespace rustamarine;
 using namespace Aquamarine;
+// Events are dropped (oldest first) past this, if the app never drains them
+static constexpr size_t MAX_QUEUED_EVENTS = 1024;
 // Implementation of Mouse
 Mouse::Mouse(SP<Aquamarine::IPointer> pointer, InputManager *inputManager)
 		: pointer(pointer), inputManager(inputManager) {
-	std::println("New mouse: {}", pointer->getName());
+	auto handle = pointer->getLibinputHandle();
+	type = handle && libinput_device_config_tap_get_finger_count(handle) > 0 ? RMAR_DEVICE_TOUCHPAD
+																																					: RMAR_DEVICE_POINTER;
+	std::println("New {}: {}", type == RMAR_DEVICE_TOUCHPAD ? "touchpad" : "mouse", pointer->getName());
 }
 void Mouse::registerListeners() {
 	auto inputManager = this->inputManager;
@@ -22,10 +30,13 @@ void Mouse::registerListeners() {
 	onRelativeMoveListenerListener =
 			pointer->events.move.registerListener([inputManager](std::any event) {
 				auto relEvent = std::any_cast<Aquamarine::IPointer::SMoveEvent>(event);
-				inputManager->mouseDeltaX += relEvent.delta.x;
-				inputManager->mouseDeltaY += relEvent.delta.y;
-				inputManager->mouseAbsoluteX += relEvent.delta.x;
-				inputManager->mouseAbsoluteY += relEvent.delta.y;
+				inputManager->handleEvent({
+					.type = RMAR_EVENT_POINTER_MOTION,
+					.x = inputManager->mouseAbsoluteX + relEvent.delta.x,
+					.y = inputManager->mouseAbsoluteY + relEvent.delta.y,
+					.dx = relEvent.delta.x,
+					.dy = relEvent.delta.y,
+				});
 			});
 	// Listen for absolute mouse movement (warp)
 	onWarpListener =
@@ -35,44 +46,171 @@ void Mouse::registerListeners() {
 					auto screen = inputManager->rmar->screens[0];
 					auto width = static_cast<double>(rmarScreenGetWidth(screen.get()));
 					auto height = static_cast<double>(rmarScreenGetHeight(screen.get()));
-					auto oldAbsolutePositionX = inputManager->mouseAbsoluteX;
-					auto oldAbsolutePositionY = inputManager->mouseAbsoluteY;
-					inputManager->mouseAbsoluteX = warpEvent.absolute.x * width;
-					inputManager->mouseAbsoluteY = warpEvent.absolute.y * height;
-					inputManager->mouseDeltaX +=
-							warpEvent.absolute.x - inputManager->mouseAbsoluteX;
-					inputManager->mouseDeltaY +=
-							warpEvent.absolute.y - inputManager->mouseAbsoluteY;
+					auto x = warpEvent.absolute.x * width;
+					auto y = warpEvent.absolute.y * height;
+					inputManager->handleEvent({
+						.type = RMAR_EVENT_POINTER_MOTION,
+						.x = x,
+						.y = y,
+						.dx = x - inputManager->mouseAbsoluteX,
+						.dy = y - inputManager->mouseAbsoluteY,
+					});
 				}
 			});
 	onScrollListener = pointer->events.axis.registerListener([inputManager](std::any d) {
     auto scrollEvent = std::any_cast<Aquamarine::IPointer::SAxisEvent>(d);
+    InputEvent axisEvent = {.type = RMAR_EVENT_POINTER_AXIS};
     switch (scrollEvent.axis) {
 	   	case IPointer::AQ_POINTER_AXIS_VERTICAL:
-					inputManager->mouseScrollY += scrollEvent.delta;
+					axisEvent.dy = scrollEvent.delta;
 					break;
 	    case IPointer::AQ_POINTER_AXIS_HORIZONTAL:
-					inputManager->mouseScrollX += scrollEvent.delta;
+					axisEvent.dx = scrollEvent.delta;
 					break;
     }
+    inputManager->handleEvent(axisEvent);
 	});
 	onButtonChangeListener = pointer->events.button.registerListener([this](std::any d) {
 	  auto e = std::any_cast<Aquamarine::IPointer::SButtonEvent>(d);
-		this->mouseButtonStates[e.button-272] = {
- 			e.pressed,
- 			true
-		};
+		auto &state = this->mouseButtonStates[e.button-272];
+		state.down = e.pressed;
+		state.justChanged = true;
+		if (e.pressed)
+			state.pressedThisFrame = true;
+		else
+			state.releasedThisFrame = true;
+		inputManager->handleEvent({
+			.type = RMAR_EVENT_POINTER_BUTTON,
+			.button = e.button - 272,
+			.pressed = e.pressed,
+		});
 	});
 	// Listen for pointer disconnect
 	onDisconnectListener =
 			pointer->events.destroy.registerListener([this](std::any) {
 				auto pointerPtr = this->pointer.get();
+				this->inputManager->handleEvent({
+					.type = RMAR_EVENT_DEVICE_REMOVED,
+					.text = this->pointer->getName(),
+					.deviceType = this->type,
+				});
 				std::erase_if(this->inputManager->mouses,
 											[pointerPtr](const auto &mouse) {
 												return mouse->pointer.get() == pointerPtr;
 											});
 			});
 }
+// Implementation of Touch
+Touch::Touch(SP<Aquamarine::ITouch> touch, InputManager *inputManager)
+		: touch(touch), inputManager(inputManager) {
+	std::println("New touch device: {}", touch->getName());
+}
+void Touch::registerListeners() {
+	auto inputManager = this->inputManager;
+	onDownListener = touch->events.down.registerListener([inputManager](std::any event) {
+		auto e = std::any_cast<Aquamarine::ITouch::SDownEvent>(event);
+		inputManager->handleEvent({
+			.type = RMAR_EVENT_TOUCH_DOWN,
+			.touchId = e.touchID,
+			.x = e.pos.x,
+			.y = e.pos.y,
+		});
+	});
+	onMoveListener = touch->events.move.registerListener([inputManager](std::any event) {
+		auto e = std::any_cast<Aquamarine::ITouch::SMotionEvent>(event);
+		inputManager->handleEvent({
+			.type = RMAR_EVENT_TOUCH_MOTION,
+			.touchId = e.touchID,
+			.x = e.pos.x,
+			.y = e.pos.y,
+		});
+	});
+	onUpListener = touch->events.up.registerListener([inputManager](std::any event) {
+		auto e = std::any_cast<Aquamarine::ITouch::SUpEvent>(event);
+		inputManager->handleEvent({.type = RMAR_EVENT_TOUCH_UP, .touchId = e.touchID});
+	});
+	onCancelListener = touch->events.cancel.registerListener([inputManager](std::any event) {
+		auto e = std::any_cast<Aquamarine::ITouch::SCancelEvent>(event);
+		inputManager->handleEvent({.type = RMAR_EVENT_TOUCH_CANCEL, .touchId = e.touchID});
+	});
+	onDisconnectListener = touch->events.destroy.registerListener([this](std::any) {
+		auto touchPtr = this->touch.get();
+		this->inputManager->handleEvent({
+			.type = RMAR_EVENT_DEVICE_REMOVED,
+			.text = this->touch->getName(),
+			.deviceType = RMAR_DEVICE_TOUCH,
+		});
+		std::erase_if(this->inputManager->touches,
+									[touchPtr](const auto &touch) { return touch->touch.get() == touchPtr; });
+	});
+}
+// Implementation of Tablet
+Tablet::Tablet(SP<Aquamarine::ITablet> tablet, InputManager *inputManager)
+		: tablet(tablet), inputManager(inputManager) {
+	std::println("New tablet: {}", tablet->getName());
+}
+void Tablet::registerListeners() {
+	onAxisListener = tablet->events.axis.registerListener([this](std::any event) {
+		auto e = std::any_cast<Aquamarine::ITablet::SAxisEvent>(event);
+		if (e.updatedAxes & AQ_TABLET_TOOL_AXIS_X)
+			x = e.absolute.x;
+		if (e.updatedAxes & AQ_TABLET_TOOL_AXIS_Y)
+			y = e.absolute.y;
+		if (e.updatedAxes & AQ_TABLET_TOOL_AXIS_PRESSURE)
+			pressure = e.pressure;
+		inputManager->handleEvent({
+			.type = RMAR_EVENT_TABLET_TOOL_AXIS,
+			.x = x,
+			.y = y,
+			.pressure = pressure,
+		});
+	});
+	onProximityListener = tablet->events.proximity.registerListener([this](std::any event) {
+		auto e = std::any_cast<Aquamarine::ITablet::SProximityEvent>(event);
+		x = e.absolute.x;
+		y = e.absolute.y;
+		inputManager->handleEvent({
+			.type = RMAR_EVENT_TABLET_TOOL_PROXIMITY,
+			.pressed = e.in,
+			.x = x,
+			.y = y,
+			.pressure = pressure,
+		});
+	});
+	onTipListener = tablet->events.tip.registerListener([this](std::any event) {
+		auto e = std::any_cast<Aquamarine::ITablet::STipEvent>(event);
+		x = e.absolute.x;
+		y = e.absolute.y;
+		inputManager->handleEvent({
+			.type = RMAR_EVENT_TABLET_TOOL_TIP,
+			.pressed = e.down,
+			.x = x,
+			.y = y,
+			.pressure = pressure,
+		});
+	});
+	onButtonListener = tablet->events.button.registerListener([this](std::any event) {
+		auto e = std::any_cast<Aquamarine::ITablet::SButtonEvent>(event);
+		inputManager->handleEvent({
+			.type = RMAR_EVENT_TABLET_TOOL_BUTTON,
+			.button = e.button,
+			.pressed = e.down,
+			.x = x,
+			.y = y,
+			.pressure = pressure,
+		});
+	});
+	onDisconnectListener = tablet->events.destroy.registerListener([this](std::any) {
+		auto tabletPtr = this->tablet.get();
+		this->inputManager->handleEvent({
+			.type = RMAR_EVENT_DEVICE_REMOVED,
+			.text = this->tablet->getName(),
+			.deviceType = RMAR_DEVICE_TABLET,
+		});
+		std::erase_if(this->inputManager->tablets,
+									[tabletPtr](const auto &tablet) { return tablet->tablet.get() == tabletPtr; });
+	});
+}
 // Implementation of Keyboard
 Keyboard::Keyboard(SP<Aquamarine::IKeyboard> keyboard,
 									 InputManager *inputManager)
@@ -102,6 +240,7 @@ void Keyboard::registerListeners() {
 				// Update xkb state for modifiers and other state tracking
 				xkb_state_update_key(xkbState, evdev_keycode,
 														 pressed ? XKB_KEY_DOWN : XKB_KEY_UP);
+				updateLeds();
 
 				// Get proper keysym from xkb state
 				xkb_keysym_t keysym =
@@ -114,6 +253,17 @@ void Keyboard::registerListeners() {
 				state.justChanged = (state.down != prevDown);
 				state.repeating = false;
 				state.stateChangedTimestamp = timeMs;
+				if (pressed)
+					state.pressedThisFrame = true;
+				else
+					state.releasedThisFrame = true;
+				inputManager->handleEvent({
+					.type = RMAR_EVENT_KEY,
+					.keycode = keycode,
+					.keysym = keysym,
+					.modifiers = modifiers(RMAR_MODIFIERS_EFFECTIVE),
+					.pressed = pressed,
+				});
 
 				// Handle key event with xkbcommon for character conversion
 				if (pressed)
@@ -125,6 +275,11 @@ void Keyboard::registerListeners() {
 			keyboard->events.destroy.registerListener([this](std::any) {
 				// Remove this keyboard from inputManager->keyboards
 				auto kbdPtr = this->keyboard.get();
+				this->inputManager->handleEvent({
+					.type = RMAR_EVENT_DEVICE_REMOVED,
+					.text = this->keyboard->getName(),
+					.deviceType = RMAR_DEVICE_KEYBOARD,
+				});
 				std::erase_if(this->inputManager->keyboards, [kbdPtr](const auto &kbd) {
 					return kbd->keyboard.get() == kbdPtr;
 				});
@@ -138,7 +293,13 @@ bool Keyboard::initXkb() {
 	if (!xkbContext) {
 		return false;
 	}
-	struct xkb_rule_names rules = {.rules = "evdev", .model = "", .layout = getenv("RUSTAMARINE_KB_LAYOUT")};
+	struct xkb_rule_names rules = {.rules = "evdev", .model = ""};
+	if (inputManager->layouts.empty()) {
+		rules.layout = getenv("RUSTAMARINE_KB_LAYOUT");
+	} else {
+		rules.layout = inputManager->layouts.c_str();
+		rules.variant = inputManager->variants.c_str();
+	}
 
 	xkbKeymap = xkb_keymap_new_from_names(xkbContext, &rules,
 																				XKB_KEYMAP_COMPILE_NO_FLAGS);
@@ -153,6 +314,9 @@ bool Keyboard::initXkb() {
 		cleanupXkb();
 		return false;
 	}
+	setActiveLayout(inputManager->activeLayout);
+	setLocks(inputManager->locks, true);
+	updateLeds(true);
 
 	const char *locale = setlocale(LC_CTYPE, nullptr);
 	xkbComposeTable = xkb_compose_table_new_from_locale(
@@ -233,9 +397,11 @@ void Keyboard::handleKeyEvent(xkb_keysym_t keysym) {
 																	 XKB_STATE_MODS_EFFECTIVE) == 1;
 	bool alt_active = xkb_state_mod_name_is_active(xkbState, XKB_MOD_NAME_ALT,
 																								 XKB_STATE_MODS_EFFECTIVE) == 1;
+	bool logo_active = xkb_state_mod_name_is_active(xkbState, XKB_MOD_NAME_LOGO,
+																									XKB_STATE_MODS_EFFECTIVE) == 1;
 
 	// Skip key combinations with modifiers that shouldn't produce text
-	if (ctrl_active || alt_active) {
+	if (ctrl_active || alt_active || logo_active) {
 		return;
 	}
   char buffer[128] = {0};
@@ -261,7 +427,94 @@ void Keyboard::handleKeyEvent(xkb_keysym_t keysym) {
 	  bufferLen = xkb_keysym_to_utf8(keysym, buffer, sizeof(buffer));
 	}
 	buffer[bufferLen] = 0;
-	inputManager->currentFrameUtf8Input = inputManager->currentFrameUtf8Input + buffer;
+	inputManager->handleEvent({.type = RMAR_EVENT_TEXT, .text = buffer});
+}
+
+void Keyboard::setActiveLayout(uint32_t index) {
+	if (!xkbState || index >= xkb_keymap_num_layouts(xkbKeymap))
+		return;
+	xkb_state_update_mask(xkbState,
+												xkb_state_serialize_mods(xkbState, XKB_STATE_MODS_DEPRESSED),
+												xkb_state_serialize_mods(xkbState, XKB_STATE_MODS_LATCHED),
+												xkb_state_serialize_mods(xkbState, XKB_STATE_MODS_LOCKED), 0, 0,
+												index);
+	// Some layouts have their own LED
+	updateLeds();
+}
+
+void Keyboard::setLocks(uint32_t modifiers, bool locked) {
+	if (!xkbState)
+		return;
+	xkb_mod_mask_t mask = 0;
+	auto addMod = [&](const char *name) {
+		auto index = xkb_keymap_mod_get_index(xkbKeymap, name);
+		if (index != XKB_MOD_INVALID)
+			mask |= 1 << index;
+	};
+	if (modifiers & RMAR_MODIFIER_CAPS_LOCK)
+		addMod(XKB_MOD_NAME_CAPS);
+	if (modifiers & RMAR_MODIFIER_NUM_LOCK)
+		addMod(XKB_MOD_NAME_NUM);
+	auto lockedMods = xkb_state_serialize_mods(xkbState, XKB_STATE_MODS_LOCKED);
+	lockedMods = locked ? lockedMods | mask : lockedMods & ~mask;
+	xkb_state_update_mask(xkbState,
+												xkb_state_serialize_mods(xkbState, XKB_STATE_MODS_DEPRESSED),
+												xkb_state_serialize_mods(xkbState, XKB_STATE_MODS_LATCHED),
+												lockedMods, 0, 0,
+												xkb_state_serialize_layout(xkbState, XKB_STATE_LAYOUT_LOCKED));
+	updateLeds();
+}
+
+void Keyboard::updateLeds(bool force) {
+	if (!xkbState)
+		return;
+	const char *names[] = {XKB_LED_NAME_NUM, XKB_LED_NAME_CAPS, XKB_LED_NAME_SCROLL};
+	uint32_t newLeds = 0;
+	for (size_t i = 0; i < std::size(names); i++)
+		if (xkb_state_led_name_is_active(xkbState, names[i]) == 1)
+			newLeds |= 1 << i;
+	if (newLeds == leds && !force)
+		return;
+	leds = newLeds;
+	keyboard->updateLEDs(leds);
+}
+
+uint32_t Keyboard::modifiers(RustamarineModifierState state) {
+	if (!xkbState)
+		return 0;
+	xkb_state_component component = XKB_STATE_MODS_EFFECTIVE;
+	// Caps Lock and Num Lock are depressed while their key is held, even if it's unlocking them
+	xkb_state_component locksComponent = XKB_STATE_MODS_LOCKED;
+	switch (state) {
+		case RMAR_MODIFIERS_DEPRESSED:
+			component = locksComponent = XKB_STATE_MODS_DEPRESSED;
+			break;
+		case RMAR_MODIFIERS_LATCHED:
+			component = locksComponent = XKB_STATE_MODS_LATCHED;
+			break;
+		case RMAR_MODIFIERS_LOCKED:
+			component = locksComponent = XKB_STATE_MODS_LOCKED;
+			break;
+		case RMAR_MODIFIERS_EFFECTIVE:
+			break;
+	}
+	auto isActive = [this](const char *name, xkb_state_component component) {
+		return xkb_state_mod_name_is_active(xkbState, name, component) == 1;
+	};
+	uint32_t modifiers = 0;
+	if (isActive(XKB_MOD_NAME_SHIFT, component))
+		modifiers |= RMAR_MODIFIER_SHIFT;
+	if (isActive(XKB_MOD_NAME_CTRL, component))
+		modifiers |= RMAR_MODIFIER_CTRL;
+	if (isActive(XKB_MOD_NAME_ALT, component))
+		modifiers |= RMAR_MODIFIER_ALT;
+	if (isActive(XKB_MOD_NAME_LOGO, component))
+		modifiers |= RMAR_MODIFIER_SUPER;
+	if (isActive(XKB_MOD_NAME_CAPS, locksComponent))
+		modifiers |= RMAR_MODIFIER_CAPS_LOCK;
+	if (isActive(XKB_MOD_NAME_NUM, locksComponent))
+		modifiers |= RMAR_MODIFIER_NUM_LOCK;
+	return modifiers;
 }
 
 // Implementation of InputManager
@@ -273,7 +526,13 @@ InputManager::InputManager(SP<Rustamarine> rmar) : rmar(rmar) {
 				auto kb = Hyprutils::Memory::makeShared<Keyboard>(keyboard,
 																								&rmar->inputManager);
 				kb->registerListeners();
+				rmar->inputManager.configureDevice(keyboard->getLibinputHandle(), RMAR_DEVICE_KEYBOARD);
 				rmar->inputManager.keyboards.emplace_back(kb);
+				rmar->inputManager.handleEvent({
+					.type = RMAR_EVENT_DEVICE_ADDED,
+					.text = keyboard->getName(),
+					.deviceType = RMAR_DEVICE_KEYBOARD,
+				});
 			});
 	// Listen for new mice
 	onNewMouseListener =
@@ -281,7 +540,39 @@ InputManager::InputManager(SP<Rustamarine> rmar) : rmar(rmar) {
 				auto pointer = std::any_cast<SP<Aquamarine::IPointer>>(event);
 				auto mouse = Hyprutils::Memory::makeShared<Mouse>(pointer, &rmar->inputManager);
 				mouse->registerListeners();
+				rmar->inputManager.configureDevice(pointer->getLibinputHandle(), mouse->type);
 				rmar->inputManager.mouses.emplace_back(mouse);
+				rmar->inputManager.handleEvent({
+					.type = RMAR_EVENT_DEVICE_ADDED,
+					.text = pointer->getName(),
+					.deviceType = mouse->type,
+				});
+			});
+	onNewTouchListener =
+			rmar->backend->events.newTouch.registerListener([rmar](std::any event) {
+				auto touch = std::any_cast<SP<Aquamarine::ITouch>>(event);
+				auto device = Hyprutils::Memory::makeShared<Touch>(touch, &rmar->inputManager);
+				device->registerListeners();
+				rmar->inputManager.configureDevice(touch->getLibinputHandle(), RMAR_DEVICE_TOUCH);
+				rmar->inputManager.touches.emplace_back(device);
+				rmar->inputManager.handleEvent({
+					.type = RMAR_EVENT_DEVICE_ADDED,
+					.text = touch->getName(),
+					.deviceType = RMAR_DEVICE_TOUCH,
+				});
+			});
+	onNewTabletListener =
+			rmar->backend->events.newTablet.registerListener([rmar](std::any event) {
+				auto tablet = std::any_cast<SP<Aquamarine::ITablet>>(event);
+				auto device = Hyprutils::Memory::makeShared<Tablet>(tablet, &rmar->inputManager);
+				device->registerListeners();
+				rmar->inputManager.configureDevice(tablet->getLibinputHandle(), RMAR_DEVICE_TABLET);
+				rmar->inputManager.tablets.emplace_back(device);
+				rmar->inputManager.handleEvent({
+					.type = RMAR_EVENT_DEVICE_ADDED,
+					.text = tablet->getName(),
+					.deviceType = RMAR_DEVICE_TABLET,
+				});
 			});
 }
 
@@ -301,6 +592,65 @@ static inline uint32_t getCurrentTimeMs() {
 			duration_cast<milliseconds>(steady_clock::now().time_since_epoch())
 					.count());
 }
+void InputManager::handleEvent(InputEvent event) {
+	switch (event.type) {
+		case RMAR_EVENT_TEXT:
+			currentFrameUtf8Input += event.text;
+			break;
+		case RMAR_EVENT_POINTER_MOTION:
+			mouseAbsoluteX = event.x;
+			mouseAbsoluteY = event.y;
+			mouseDeltaX += event.dx;
+			mouseDeltaY += event.dy;
+			break;
+		case RMAR_EVENT_POINTER_AXIS:
+			mouseScrollX += event.dx;
+			mouseScrollY += event.dy;
+			break;
+		default:
+			break;
+	}
+	if (events.size() >= MAX_QUEUED_EVENTS)
+		events.pop_front();
+	events.push_back(std::move(event));
+}
+
+void InputManager::configureDevice(libinput_device *device, RustamarineDeviceType type) {
+	auto it = deviceConfigs.find(type);
+	if (!device || it == deviceConfigs.end())
+		return;
+	auto &config = it->second;
+	if (config.tapToClick >= 0 && libinput_device_config_tap_get_finger_count(device) > 0)
+		libinput_device_config_tap_set_enabled(
+				device, config.tapToClick ? LIBINPUT_CONFIG_TAP_ENABLED : LIBINPUT_CONFIG_TAP_DISABLED);
+	if (config.naturalScroll >= 0 && libinput_device_config_scroll_has_natural_scroll(device))
+		libinput_device_config_scroll_set_natural_scroll_enabled(device, config.naturalScroll);
+	if (config.leftHanded >= 0 && libinput_device_config_left_handed_is_available(device))
+		libinput_device_config_left_handed_set(device, config.leftHanded);
+	if (config.disableWhileTyping >= 0 && libinput_device_config_dwt_is_available(device))
+		libinput_device_config_dwt_set_enabled(device, config.disableWhileTyping
+																									 ? LIBINPUT_CONFIG_DWT_ENABLED
+																									 : LIBINPUT_CONFIG_DWT_DISABLED);
+	if (!libinput_device_config_accel_is_available(device))
+		return;
+	if (!std::isnan(config.accelSpeed))
+		libinput_device_config_accel_set_speed(device, std::clamp(config.accelSpeed, -1.0, 1.0));
+	auto profile = LIBINPUT_CONFIG_ACCEL_PROFILE_NONE;
+	switch (config.accelProfile) {
+		case RMAR_ACCEL_PROFILE_FLAT:
+			profile = LIBINPUT_CONFIG_ACCEL_PROFILE_FLAT;
+			break;
+		case RMAR_ACCEL_PROFILE_ADAPTIVE:
+			profile = LIBINPUT_CONFIG_ACCEL_PROFILE_ADAPTIVE;
+			break;
+		case RMAR_ACCEL_PROFILE_DEFAULT:
+			break;
+	}
+	if (profile != LIBINPUT_CONFIG_ACCEL_PROFILE_NONE &&
+			(libinput_device_config_accel_get_profiles(device) & profile))
+		libinput_device_config_accel_set_profile(device, profile);
+}
+
 void InputManager::onFrameEnd() {
 
 	// Reset mouse delta for the new frame
@@ -313,6 +663,8 @@ void InputManager::onFrameEnd() {
 	for (auto &kb : this->keyboards) {
 		// Reset justChanged for all keys and mouse buttons
 		for (auto &[_, state] : kb->keystates) {
+			state.pressedThisFrame = false;
+			state.releasedThisFrame = false;
 			// Handle repeating and shouldTypeChar logic
 			uint32_t now = getCurrentTimeMs();
 			if (!state.down) {
@@ -357,6 +709,8 @@ void InputManager::onFrameEnd() {
 	for (auto &mouse : this->mouses)
 		for (auto &[_, state] : mouse->mouseButtonStates) {
 			state.justChanged = false;
+			state.pressedThisFrame = false;
+			state.releasedThisFrame = false;
 		}
 }
 bool rmarIsKeyDown(Rustamarine *rmar, uint32_t key) {
@@ -376,7 +730,7 @@ bool rmarIsKeyPressed(Rustamarine *rmar, uint32_t key) {
 	for (auto &kb : rmar->inputManager.keyboards) {
 	if(!kb->keystates.contains(key)) continue;
 		auto it = kb->keystates[key];
-		if (it.down && it.justChanged) return true;
+		if (it.pressedThisFrame) return true;
 	}
 	return false;
 }
@@ -387,7 +741,7 @@ bool rmarIsKeyReleased(Rustamarine *rmar, uint32_t key) {
 	for (auto &kb : rmar->inputManager.keyboards) {
 	if(!kb->keystates.contains(key)) continue;
 		auto it = kb->keystates[key];
-		if (!it.down && it.justChanged) return true;
+		if (it.releasedThisFrame) return true;
 	}
 	return false;
 }
@@ -423,7 +777,7 @@ bool rmarIsMouseButtonPressed(Rustamarine *rmar, uint32_t button) {
  if(!mouse->mouseButtonStates.contains(button)) continue;
 
 		auto it = mouse->mouseButtonStates[button];
-		if(it.down && it.justChanged) return true;
+		if (it.pressedThisFrame) return true;
 	}
 	return false;
 }
@@ -435,7 +789,7 @@ bool rmarIsMouseButtonReleased(Rustamarine *rmar, uint32_t button) {
  if(!mouse->mouseButtonStates.contains(button)) continue;
 
 		auto it = mouse->mouseButtonStates[button];
-		if (!it.down && it.justChanged) return true;
+		if (it.releasedThisFrame) return true;
 	}
 	return false;
 }
@@ -491,3 +845,211 @@ void rmarSetMouseY(Rustamarine *rmar, int y) {
 	rmar->inputManager.mouseAbsoluteY = static_cast<double>(y);
 	rmar->inputManager.mouseDeltaY += rmar->inputManager.mouseAbsoluteY - prev;
 }
+
+RustamarineEvents rmarDrainEvents(Rustamarine *rmar) {
+	if (!rmar || rmar->inputManager.events.empty())
+		return {nullptr, 0};
+	auto &queue = rmar->inputManager.events;
+	auto events = static_cast<RustamarineEvent *>(calloc(queue.size(), sizeof(RustamarineEvent)));
+	size_t i = 0;
+	for (auto &event : queue) {
+		events[i++] = {
+			.type = event.type,
+			.keycode = event.keycode,
+			.keysym = event.keysym,
+			.modifiers = event.modifiers,
+			.button = event.button,
+			.pressed = event.pressed,
+			.touchId = event.touchId,
+			.text = event.text.empty() ? nullptr : strdup(event.text.c_str()),
+			.x = event.x,
+			.y = event.y,
+			.dx = event.dx,
+			.dy = event.dy,
+			.pressure = event.pressure,
+			.deviceType = event.deviceType,
+		};
+	}
+	queue.clear();
+	return {events, i};
+}
+
+void rmarFreeEvents(RustamarineEvents events) {
+	for (size_t i = 0; i < events.count; i++)
+		free(const_cast<char *>(events.events[i].text));
+	free(events.events);
+}
+
+uint32_t rmarGetModifiers(Rustamarine *rmar, RustamarineModifierState state) {
+	if (!rmar)
+		return 0;
+	uint32_t modifiers = 0;
+	for (auto &kb : rmar->inputManager.keyboards)
+		modifiers |= kb->modifiers(state);
+	return modifiers;
+}
+
+uint32_t rmarKeysymFromName(const char *name) {
+	return xkb_keysym_from_name(name, XKB_KEYSYM_CASE_INSENSITIVE);
+}
+
+uint32_t rmarKeysymToLower(uint32_t keysym) { return xkb_keysym_to_lower(keysym); }
+
+uint32_t rmarKeysymToUpper(uint32_t keysym) { return xkb_keysym_to_upper(keysym); }
+
+bool rmarSetKeyboardLayouts(Rustamarine *rmar, const char *layouts, const char *variants) {
+	if (!rmar)
+		return false;
+	// Check that the keymap compiles before breaking the keyboards with it
+	auto context = xkb_context_new(XKB_CONTEXT_NO_FLAGS);
+	if (!context)
+		return false;
+	struct xkb_rule_names rules = {
+			.rules = "evdev", .model = "", .layout = layouts, .variant = variants};
+	auto keymap = xkb_keymap_new_from_names(context, &rules, XKB_KEYMAP_COMPILE_NO_FLAGS);
+	xkb_context_unref(context);
+	if (!keymap) {
+		std::println(stderr, "Failed to compile the keymap for layouts {} ({})", layouts, variants);
+		return false;
+	}
+	xkb_keymap_unref(keymap);
+
+	auto &inputManager = rmar->inputManager;
+	inputManager.layouts = layouts;
+	inputManager.variants = variants;
+	inputManager.activeLayout = 0;
+	inputManager.labelsKeymap = nullptr;
+	for (auto &kb : inputManager.keyboards) {
+		kb->cleanupXkb();
+		if (!kb->initXkb())
+			std::println(stderr, "Failed to initialize XKB for keyboard {}", kb->keyboard->getName());
+	}
+	return true;
+}
+
+// Keymaps are built from the same layouts, so any keyboard can describe them
+static Keyboard *keymapKeyboard(Rustamarine *rmar) {
+	for (auto &kb : rmar->inputManager.keyboards)
+		if (kb->xkbKeymap)
+			return kb.get();
+	return nullptr;
+}
+
+size_t rmarGetKeyboardLayoutCount(Rustamarine *rmar) {
+	if (!rmar)
+		return 0;
+	auto kb = keymapKeyboard(rmar);
+	return kb ? xkb_keymap_num_layouts(kb->xkbKeymap) : 0;
+}
+
+const char *rmarGetKeyboardLayoutName(Rustamarine *rmar, size_t index) {
+	if (!rmar)
+		return nullptr;
+	auto kb = keymapKeyboard(rmar);
+	if (!kb || index >= xkb_keymap_num_layouts(kb->xkbKeymap))
+		return nullptr;
+	return xkb_keymap_layout_get_name(kb->xkbKeymap, index);
+}
+
+size_t rmarGetActiveKeyboardLayout(Rustamarine *rmar) {
+	if (!rmar)
+		return 0;
+	// The layout can also be changed with the keyboard, e.g. with the grp:alt_shift_toggle option
+	auto kb = keymapKeyboard(rmar);
+	if (kb && kb->xkbState)
+		return xkb_state_serialize_layout(kb->xkbState, XKB_STATE_LAYOUT_EFFECTIVE);
+	return rmar->inputManager.activeLayout;
+}
+
+void rmarSetActiveKeyboardLayout(Rustamarine *rmar, size_t index) {
+	if (!rmar)
+		return;
+	rmar->inputManager.activeLayout = index;
+	for (auto &kb : rmar->inputManager.keyboards)
+		kb->setActiveLayout(index);
+}
+
+static xkb_keymap *labelsKeymap(InputManager &inputManager) {
+	if (inputManager.labelsKeymap)
+		return inputManager.labelsKeymap.get();
+	auto context = xkb_context_new(XKB_CONTEXT_NO_FLAGS);
+	if (!context)
+		return nullptr;
+	struct xkb_rule_names rules = {.rules = "evdev", .model = ""};
+	if (inputManager.layouts.empty()) {
+		rules.layout = getenv("RUSTAMARINE_KB_LAYOUT");
+	} else {
+		rules.layout = inputManager.layouts.c_str();
+		rules.variant = inputManager.variants.c_str();
+	}
+	auto keymap = xkb_keymap_new_from_names(context, &rules, XKB_KEYMAP_COMPILE_NO_FLAGS);
+	xkb_context_unref(context);
+	if (!keymap)
+		return nullptr;
+	inputManager.labelsKeymap = std::shared_ptr<xkb_keymap>(keymap, xkb_keymap_unref);
+	return keymap;
+}
+
+uint32_t rmarGetKeyKeysym(Rustamarine *rmar, uint32_t keycode, uint32_t level) {
+	if (!rmar)
+		return XKB_KEY_NoSymbol;
+	auto keymap = labelsKeymap(rmar->inputManager);
+	if (!keymap)
+		return XKB_KEY_NoSymbol;
+	const xkb_keysym_t *keysyms;
+	auto count = xkb_keymap_key_get_syms_by_level(keymap, keycode + 8,
+																								 rmarGetActiveKeyboardLayout(rmar), level, &keysyms);
+	return count > 0 ? keysyms[0] : XKB_KEY_NoSymbol;
+}
+
+size_t rmarKeysymToUtf8(uint32_t keysym, char *buffer, size_t size) {
+	// Includes the NUL, -1 if it doesn't fit
+	int length = xkb_keysym_to_utf8(keysym, buffer, size);
+	return length > 0 ? length - 1 : 0;
+}
+
+void rmarSetDeviceConfig(Rustamarine *rmar, RustamarineDeviceType type,
+												 RustamarineDeviceConfig config) {
+	if (!rmar)
+		return;
+	auto &inputManager = rmar->inputManager;
+	inputManager.deviceConfigs[type] = config;
+	switch (type) {
+		case RMAR_DEVICE_KEYBOARD:
+			for (auto &kb : inputManager.keyboards)
+				inputManager.configureDevice(kb->keyboard->getLibinputHandle(), type);
+			break;
+		case RMAR_DEVICE_POINTER:
+		case RMAR_DEVICE_TOUCHPAD:
+			for (auto &mouse : inputManager.mouses)
+				if (mouse->type == type)
+					inputManager.configureDevice(mouse->pointer->getLibinputHandle(), type);
+			break;
+		case RMAR_DEVICE_TOUCH:
+			for (auto &touch : inputManager.touches)
+				inputManager.configureDevice(touch->touch->getLibinputHandle(), type);
+			break;
+		case RMAR_DEVICE_TABLET:
+			for (auto &tablet : inputManager.tablets)
+				inputManager.configureDevice(tablet->tablet->getLibinputHandle(), type);
+			break;
+	}
+}
+
+void rmarSetLocks(Rustamarine *rmar, uint32_t modifiers, bool locked) {
+	if (!rmar)
+		return;
+	auto &locks = rmar->inputManager.locks;
+	locks = locked ? locks | modifiers : locks & ~modifiers;
+	for (auto &kb : rmar->inputManager.keyboards)
+		kb->setLocks(modifiers, locked);
+}
+
+uint32_t rmarGetLeds(Rustamarine *rmar) {
+	if (!rmar)
+		return 0;
+	uint32_t leds = 0;
+	for (auto &kb : rmar->inputManager.keyboards)
+		leds |= kb->leds;
+	return leds;
+}
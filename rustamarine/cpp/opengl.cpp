@@ -98,17 +98,18 @@ static int openRenderNode(int drmFd) {
 	return renderFD;
 }
 
-void initEGL(SP<Rustamarine> rmar, bool gbm = false) {
+// Returns an empty string on success, or the reason it failed
+std::string initEGL(SP<Rustamarine> rmar, bool gbm = false) {
 	if (gbm) {
 		rmar->openGLContext.gbmFd =
 				Hyprutils::OS::CFileDescriptor{openRenderNode(rmar->backend->drmFD())};
 		if (!rmar->openGLContext.gbmFd.isValid())
-			RASSERT(false, "Couldn't open a gbm fd");
+			return "Couldn't open a gbm fd";
 
 		rmar->openGLContext.gbmDevice =
 				gbm_create_device(rmar->openGLContext.gbmFd.get());
 		if (!rmar->openGLContext.gbmDevice)
-			RASSERT(false, "Couldn't open a gbm device");
+			return "Couldn't open a gbm device";
 
 	} else {
 		rmar->openGLContext.eglDevice = eglDeviceFromDRMFD(rmar->backend->drmFD());
@@ -118,21 +119,16 @@ void initEGL(SP<Rustamarine> rmar, bool gbm = false) {
 			gbm ? rmar->openGLContext.gbmDevice : rmar->openGLContext.eglDevice,
 			nullptr);
 	if (!eglDisplay) {
-		if (gbm) {
-			panic("Failed to initialize EGL Display (eglGetPlatformDisplayEXT)")
-		} else {
-			initEGL(rmar, true);
-			return;
-		}
+		if (gbm)
+			return "Failed to initialize EGL Display (eglGetPlatformDisplayEXT)";
+		return initEGL(rmar, true);
 	}
 	EGLint version[2] = {0, 0};
 	if (eglInitialize(eglDisplay, &version[0], &version[1]) == EGL_FALSE) {
-		if (gbm) {
-			panic("Failed to initialize EGL Display (eglInitialize)")
-		} else {
-			initEGL(rmar, true);
-			return;
-		}
+		if (gbm)
+			return std::format("Failed to initialize EGL Display (eglInitialize: {})",
+												 eglErrorToString(eglGetError()));
+		return initEGL(rmar, true);
 	}
 	printf("[LOG] EGL version: %d.%d\n", version[0], version[1]);
 	auto eglContext =
@@ -140,14 +136,15 @@ void initEGL(SP<Rustamarine> rmar, bool gbm = false) {
 											 (int[]){EGL_CONTEXT_MAJOR_VERSION, 3,
 															 EGL_CONTEXT_MINOR_VERSION, 2, EGL_NONE});
 	if (eglContext == EGL_NO_CONTEXT)
-		panic("Failed to create EGL Context") {
-			EGLint priority = EGL_CONTEXT_PRIORITY_MEDIUM_IMG;
-			eglQueryContext(eglDisplay, eglContext, EGL_CONTEXT_PRIORITY_LEVEL_IMG,
-											&priority);
-		}
+		return std::format("Failed to create EGL Context ({})",
+											 eglErrorToString(eglGetError()));
+	EGLint priority = EGL_CONTEXT_PRIORITY_MEDIUM_IMG;
+	eglQueryContext(eglDisplay, eglContext, EGL_CONTEXT_PRIORITY_LEVEL_IMG,
+									&priority);
 	eglMakeCurrent(eglDisplay, EGL_NO_SURFACE, EGL_NO_SURFACE, eglContext);
 	rmar->openGLContext.eglDisplay = eglDisplay;
 	rmar->openGLContext.eglContext = eglContext;
+	return "";
 }
 
 static void EGLAPIENTRY eglLog(EGLenum error, const char *command,
@@ -175,7 +172,7 @@ static void EGLAPIENTRY eglLog(EGLenum error, const char *command,
 					eglErrorToString(error), command ? command : "(null)",
 					message ? message : "(null)");
 }
-void initializeOpenGL(SP<Rustamarine> rmar) {
+std::string initializeOpenGL(SP<Rustamarine> rmar) {
 	gladLoadEGL();
 	static const EGLAttrib debugAttrs[] = {
 			EGL_DEBUG_MSG_CRITICAL_KHR,
@@ -193,16 +190,18 @@ void initializeOpenGL(SP<Rustamarine> rmar) {
 		rmar->openGLContext.eglDisplay = eglGetCurrentDisplay();
 		rmar->openGLContext.eglContext = eglGetCurrentContext();
 		rmar->openGLContext.eglDevice = eglDeviceFromDRMFD(rmar->backend->drmFD());
-		return;
+		return "";
 	}
 	eglBindAPI(EGL_OPENGL_ES_API);
-	initEGL(rmar);
+	if (auto error = initEGL(rmar); !error.empty())
+		return error;
 	if (!gladLoadGLES2Loader((GLADloadproc)eglGetProcAddress))
-		panic("Failed to load OpenGL functions with glad");
+		return "Failed to load OpenGL functions with glad";
 	printf("[LOG] Initialized OpenGL Context!\n");
 	printf("[LOG] Using: %s\n", (char *)glGetString(GL_VERSION));
 	printf("[LOG] Vendor: %s\n", (char *)glGetString(GL_VENDOR));
 	printf("[LOG] Renderer: %s\n", (char *)glGetString(GL_RENDERER));
+	return "";
 }
 void tearDownOpenGL(RustamarineOpenGLContext *opengl) {
 	if (opengl->eglDisplay != nullptr && opengl->eglContext != nullptr) {
@@ -216,6 +215,7 @@ void *rmarGetProcAddress(struct Rustamarine *, const char *procName) {
 }
 void ensureOpenGLInitialized(SP<Rustamarine> rmar) {
  if (rmar->openGLContext.eglContext == EGL_NO_CONTEXT) {
-  initializeOpenGL(rmar);
+  if (auto error = initializeOpenGL(rmar); !error.empty())
+   panic("{}", error);
  }
 }
@@ -5,6 +5,8 @@
 #include <cstring>
 #include <hyprutils/memory/SharedPtr.hpp>
 #include <iostream>
+#include <optional>
+#include <string>
 #include <assert.h>
 #include <poll.h>
 #include <rustamarine/internal/opengl.hpp>
@@ -19,21 +21,33 @@ void aqLog(Aquamarine::eBackendLogLevel level, std::string msg) {
 		return;
 	std::cout << "[AQ] [" << aqLevelToString(level) << "] " << msg << "\n";
 }
-std::vector<Aquamarine::SBackendImplementationOptions> getBackendsList() {
+std::vector<Aquamarine::SBackendImplementationOptions>
+getBackendsList(const RustamarineOptions &options) {
 	std::vector<Aquamarine::SBackendImplementationOptions> implementations;
-	Aquamarine::SBackendImplementationOptions backendOptions;
-	backendOptions.backendType = Aquamarine::eBackendType::AQ_BACKEND_WAYLAND;
-	backendOptions.backendRequestMode =
-			Aquamarine::eBackendRequestMode::AQ_BACKEND_REQUEST_FALLBACK;
-	implementations.emplace_back(backendOptions);
-	backendOptions.backendType = Aquamarine::eBackendType::AQ_BACKEND_HEADLESS;
-	backendOptions.backendRequestMode =
-			Aquamarine::eBackendRequestMode::AQ_BACKEND_REQUEST_MANDATORY;
-	implementations.emplace_back(backendOptions);
-	backendOptions.backendType = Aquamarine::eBackendType::AQ_BACKEND_DRM;
-	backendOptions.backendRequestMode =
-			Aquamarine::eBackendRequestMode::AQ_BACKEND_REQUEST_IF_AVAILABLE;
-	implementations.emplace_back(backendOptions);
+	auto add = [&](Aquamarine::eBackendType type, RustamarineBackendRequest request) {
+		Aquamarine::SBackendImplementationOptions backendOptions;
+		backendOptions.backendType = type;
+		switch (request) {
+		case RMAR_BACKEND_DISABLED:
+			return;
+		case RMAR_BACKEND_OPTIONAL:
+			backendOptions.backendRequestMode =
+					Aquamarine::eBackendRequestMode::AQ_BACKEND_REQUEST_IF_AVAILABLE;
+			break;
+		case RMAR_BACKEND_REQUIRED:
+			backendOptions.backendRequestMode =
+					Aquamarine::eBackendRequestMode::AQ_BACKEND_REQUEST_MANDATORY;
+			break;
+		case RMAR_BACKEND_FALLBACK:
+			backendOptions.backendRequestMode =
+					Aquamarine::eBackendRequestMode::AQ_BACKEND_REQUEST_FALLBACK;
+			break;
+		}
+		implementations.emplace_back(backendOptions);
+	};
+	add(Aquamarine::eBackendType::AQ_BACKEND_WAYLAND, options.wayland);
+	add(Aquamarine::eBackendType::AQ_BACKEND_HEADLESS, options.headless);
+	add(Aquamarine::eBackendType::AQ_BACKEND_DRM, options.drm);
 	return implementations;
 }
 
@@ -44,29 +58,98 @@ void setupEventListeners(SP<Rustamarine> rmar) {
 						auto output =
 								std::any_cast<SP<Aquamarine::IOutput>>(uncastedOutput);
 
-						rmar->screens.push_back(createScreenFromOutput(rmar, output));
+						auto screen = createScreenFromOutput(rmar, output);
+						rmar->screens.push_back(screen);
+						rmar->onScreenAdded.call(screen.get());
 					});
 
 }
 
-Rustamarine *rmarInitialize() {
+// Breaks the reference cycles of a rustamarine that failed to initialize, so it gets freed
+static void releaseRustamarine(SP<Rustamarine> rmar) {
+	for (auto &screen : rmar->screens)
+		releaseScreen(screen.get());
+	rmar->screens.clear();
+	rmar->listeners.newOutputListener.reset();
+	rmar->inputManager = {};
+	tearDownOpenGL(&rmar->openGLContext);
+}
+
+static Rustamarine *initializationError(char **error, std::string message) {
+	std::cout << "[Rustamarine] " << message << "\n";
+	if (error)
+		*error = strdup(message.c_str());
+	return nullptr;
+}
+
+// Aquamarine only takes the DRM device from AQ_DRM_DEVICES, which would be inherited by
+// everything started afterwards, so it's only set until the backend started
+class DrmDevicesEnv {
+	std::optional<std::string> previous;
+	bool changed = false;
+
+public:
+	explicit DrmDevicesEnv(const char *device) {
+		if (!device)
+			return;
+		if (auto value = std::getenv("AQ_DRM_DEVICES"))
+			previous = value;
+		setenv("AQ_DRM_DEVICES", device, true);
+		changed = true;
+	}
+	~DrmDevicesEnv() {
+		if (!changed)
+			return;
+		if (previous)
+			setenv("AQ_DRM_DEVICES", previous->c_str(), true);
+		else
+			unsetenv("AQ_DRM_DEVICES");
+	}
+	DrmDevicesEnv(const DrmDevicesEnv &) = delete;
+	DrmDevicesEnv &operator=(const DrmDevicesEnv &) = delete;
+};
+
+Rustamarine *rmarInitialize(struct RustamarineOptions rmarOptions, char **error) {
 	setup_segfault_handler();
+	if (error)
+		*error = nullptr;
+	DrmDevicesEnv drmDevicesEnv(rmarOptions.drmDevice);
 	Aquamarine::SBackendOptions options;
 	options.logFunction = aqLog;
-	auto implementations = getBackendsList();
+	auto implementations = getBackendsList(rmarOptions);
+	if (implementations.empty())
+		return initializationError(error, "All the backends are disabled");
 	auto aqBackend = Aquamarine::CBackend::create(implementations, options);
+	if (!aqBackend)
+		return initializationError(error, "Failed to create the aquamarine backend");
 	SP<Rustamarine> rmar(
 			new Rustamarine{.backend = aqBackend, .screens = {}, .listeners = {}});
 	setupEventListeners(rmar);
 	rmar->inputManager = rustamarine::InputManager(rmar);
-	if (!rmar->backend->start())
-		panic("Failed to start aquamarine backend");
-	initializeOpenGL(rmar);
+	rmar->drmMonitor = Hyprutils::Memory::makeShared<rustamarine::DrmMonitor>();
+	if (!rmar->backend->start()) {
+		releaseRustamarine(rmar);
+		return initializationError(
+				error, rmarOptions.drmDevice
+									 ? std::format("Failed to start the aquamarine backend with {}",
+																 rmarOptions.drmDevice)
+									 : "Failed to start the aquamarine backend");
+	}
+	if (auto message = initializeOpenGL(rmar); !message.empty()) {
+		releaseRustamarine(rmar);
+		return initializationError(error, "Failed to initialize OpenGL: " + message);
+	}
+	// Cached while the device is still there, so it's known after it's unplugged
+	rmarGetDrmDevice(rmar.get());
 
 	rmar.impl_->inc();
 	return rmar.get();
 }
 
+void rmarFreeError(char *error) {
+	free(error);
+}
+
 void rmarPollEvents(struct Rustamarine *self) {
 	for (auto &screen : self->screens) {
 		screen->isVBlank = false;
@@ -78,11 +161,18 @@ void rmarPollEvents(struct Rustamarine *self) {
 	for (const auto &pfd : pollFDs) {
 		fds.push_back({pfd->fd, POLLIN, 0});
 	}
+	int drmMonitorFD = self->drmMonitor->fd();
+	if (drmMonitorFD >= 0)
+		fds.push_back({drmMonitorFD, POLLIN, 0});
 	int ret = poll(fds.data(), fds.size(), -1);
 	if (ret > 0) {
 		for (size_t i = 0; i < fds.size(); ++i) {
 			if (fds[i].revents & POLLIN) {
 				auto fd = fds[i].fd;
+				if (fd == drmMonitorFD) {
+					self->drmMonitor->dispatch();
+					continue;
+				}
 				auto it = std::find_if(pollFDs.begin(), pollFDs.end(),
 															 [fd](const auto &pfd) { return pfd->fd == fd; });
 				if (it == pollFDs.end())
@@ -91,8 +181,11 @@ void rmarPollEvents(struct Rustamarine *self) {
 			}
 		}
 	}
+	removeDestroyedScreens(self);
 }
 void rmarTearDown(struct Rustamarine *self) {
+	self->onScreenAdded.set(nullptr, nullptr);
+	self->onScreenRemoved.set(nullptr, nullptr);
 	tearDownOpenGL(&self->openGLContext);
 	delete self;
 }
@@ -112,6 +205,33 @@ struct RustamarineScreens rmarGetScreens(struct Rustamarine *self) {
 void rmarFreeScreens(struct RustamarineScreens screens) {
 	free(screens.screens);
 }
+struct RustamarineScreen *rmarGetScreenById(struct Rustamarine *self, uint64_t id) {
+	auto it = std::find_if(self->screens.begin(), self->screens.end(),
+												 [id](const auto &screen) { return screen->id == id && !screen->destroyed; });
+	if (it == self->screens.end())
+		return nullptr;
+	return it->get();
+}
+void RustamarineScreenCallback::call(RustamarineScreen *screen) {
+	if (this->callback)
+		this->callback(this->context, screen->id, screen->output->name.c_str());
+}
+void RustamarineScreenCallback::set(void (*callback)(void *, uint64_t, const char *), void *context) {
+	if (this->context)
+		rmarFreeRustClosure(this->context);
+	this->callback = callback;
+	this->context = context;
+}
+void rmarSetOnScreenAdded(struct Rustamarine *self,
+													void (*callback)(void *, uint64_t, const char *),
+													void *context) {
+	self->onScreenAdded.set(callback, context);
+}
+void rmarSetOnScreenRemoved(struct Rustamarine *self,
+														void (*callback)(void *, uint64_t, const char *),
+														void *context) {
+	self->onScreenRemoved.set(callback, context);
+}
 struct Rustamarine *rmarFromScreen(struct RustamarineScreen *screen) {
 	return screen->rustamarine.get();
 }
@@ -1,6 +1,7 @@
 #include "glad/glad.h"
 #include "rustamarine.h"
 #include <algorithm>
+#include <cmath>
 #include <aquamarine/output/Output.hpp>
 #include <cstdint>
 #include <cstdio>
@@ -33,14 +34,17 @@ bool RustamarineScreen::test() {
 SP<RustamarineScreen> createScreenFromOutput(SP<Rustamarine> rustamarine,
                                              SP<Aquamarine::IOutput> output) {
     SP screen(new RustamarineScreen{output, rustamarine, {}});
+    screen->id = rustamarine->nextScreenId++;
     screen->needsFrameListener =
         output->events.needsFrame.registerListener([screen](std::any _) {
-            if (screenIsInactive(&*screen))
+            if (screen->destroyed || screenIsInactive(&*screen))
                 return;
               screen->output->scheduleFrame(Aquamarine::IOutput::AQ_SCHEDULE_NEEDS_FRAME);
         });
     screen->frameListener =
         output->events.frame.registerListener([screen](std::any _) {
+            if (screen->destroyed)
+                return;
             screen->isVBlank = true;
             if (screen->onRenderCFunc) {
                 screen->onRenderCFunc(screen->onRenderContext, screen.get());
@@ -48,6 +52,8 @@ SP<RustamarineScreen> createScreenFromOutput(SP<Rustamarine> rustamarine,
         });
     screen->onStateListener =
         output->events.state.registerListener([screen](std::any data) {
+            if (screen->destroyed)
+                return;
             auto event = std::any_cast<Aquamarine::IOutput::SStateEvent>(data);
             screen->renderBuffers.clear();
             screen->output->state->setEnabled(true);
@@ -55,6 +61,12 @@ SP<RustamarineScreen> createScreenFromOutput(SP<Rustamarine> rustamarine,
             screen->output->state->setFormat(DRM_FORMAT_XRGB8888);
 
         });
+    // The screen can't be removed right away because we might be in the middle of iterating the
+    // screens (or rendering one of them), removeDestroyedScreens takes care of it later
+    screen->destroyListener =
+        output->events.destroy.registerListener([screen](std::any _) {
+            screen->destroyed = true;
+        });
 
     screen->output->state->setEnabled(true);
     screen->output->state->setFormat(DRM_FORMAT_XRGB8888);
@@ -201,6 +213,31 @@ void rmarSwapBuffers(struct RustamarineScreen *self) {
 	self->currentBuffer.reset();
 	self->rustamarine->inputManager.onFrameEnd();
 }
+void removeDestroyedScreens(Rustamarine *rustamarine) {
+	std::vector<SP<RustamarineScreen>> destroyed;
+	std::erase_if(rustamarine->screens, [&](const SP<RustamarineScreen> &screen) {
+		if (!screen->destroyed)
+			return false;
+		destroyed.push_back(screen);
+		return true;
+	});
+	for (auto &screen : destroyed) {
+		printf("LOG: Monitor %s: disconnected\n", screen->output->name.c_str());
+		rustamarine->onScreenRemoved.call(screen.get());
+		releaseScreen(screen.get());
+	}
+}
+void releaseScreen(RustamarineScreen *screen) {
+	// The listeners hold a reference to the screen, dropping them breaks the cycle so the screen
+	// (and the rust closure of its render callback) gets freed
+	screen->needsFrameListener.reset();
+	screen->frameListener.reset();
+	screen->onStateListener.reset();
+	screen->presentListener.reset();
+	screen->destroyListener.reset();
+	screen->currentBuffer.reset();
+	screen->renderBuffers.clear();
+}
 bool RustamarineScreen::updateSwapchain() {
 	auto options = this->output->swapchain->currentOptions();
 	const auto &STATE = this->output->state->state();
@@ -269,6 +306,18 @@ float rmarScreenGetRefreshRate(const struct RustamarineScreen *screen) {
 	return 0.0f;
 }
 
+unsigned int rmarScreenGetPhysicalWidth(const struct RustamarineScreen *screen) {
+	if (!screen || !screen->output)
+		return 0;
+	return static_cast<unsigned int>(screen->output->physicalSize.x);
+}
+
+unsigned int rmarScreenGetPhysicalHeight(const struct RustamarineScreen *screen) {
+	if (!screen || !screen->output)
+		return 0;
+	return static_cast<unsigned int>(screen->output->physicalSize.y);
+}
+
 const char *rmarScreenGetName(const struct RustamarineScreen *screen) {
 	if (!screen || !screen->output)
 		return "";
@@ -279,6 +328,77 @@ const char *rmarScreenGetName(const struct RustamarineScreen *screen) {
 bool rmarScreenIsEnabled(const struct RustamarineScreen *screen) {
 	return screen->output->state->state().enabled;
 }
+uint64_t rmarScreenGetId(const struct RustamarineScreen *screen) {
+	return screen->id;
+}
+struct RustamarineModes rmarScreenGetModes(const struct RustamarineScreen *screen) {
+	struct RustamarineModes result;
+	const auto &modes = screen->output->modes;
+	result.count = modes.size();
+	result.modes = nullptr;
+	if (result.count > 0) {
+		result.modes = (struct RustamarineMode *)malloc(sizeof(struct RustamarineMode) * result.count);
+		for (size_t i = 0; i < result.count; ++i) {
+			result.modes[i] = RustamarineMode{
+					.width = static_cast<unsigned int>(modes[i]->pixelSize.x),
+					.height = static_cast<unsigned int>(modes[i]->pixelSize.y),
+					.refreshRate = static_cast<float>(modes[i]->refreshRate) / 1000.0f,
+					.preferred = modes[i]->preferred,
+			};
+		}
+	}
+	return result;
+}
+void rmarFreeModes(struct RustamarineModes modes) {
+	free(modes.modes);
+}
+bool rmarScreenSetMode(struct RustamarineScreen *screen, struct RustamarineMode mode) {
+	auto output = screen->output;
+	const auto &state = output->state->state();
+	auto previousMode = state.mode;
+	auto previousCustomMode = state.customMode;
+
+	auto it = std::find_if(output->modes.begin(), output->modes.end(), [&](const auto &m) {
+		return m->pixelSize.x == mode.width && m->pixelSize.y == mode.height &&
+					 (mode.refreshRate <= 0 ||
+						std::abs(m->refreshRate / 1000.f - mode.refreshRate) < 0.5f);
+	});
+	if (it != output->modes.end()) {
+		output->state->setMode(*it);
+	} else {
+		output->state->setCustomMode(makeShared<Aquamarine::SOutputMode>(Aquamarine::SOutputMode{
+				.pixelSize = Vector2D(mode.width, mode.height),
+				.refreshRate = static_cast<unsigned int>(std::max(mode.refreshRate, 0.f) * 1000),
+		}));
+	}
+	std::string modeStr = std::format("{}x{}@{:.2f}Hz", mode.width, mode.height, mode.refreshRate);
+	if (!screen->test() || !output->commit()) {
+		printf("ERROR: Monitor %s: REJECTED mode %s!\n", output->name.c_str(), modeStr.c_str());
+		if (previousMode)
+			output->state->setMode(previousMode);
+		else
+			output->state->setCustomMode(previousCustomMode);
+		screen->updateSwapchain();
+		return false;
+	}
+	printf("LOG: Monitor %s: switched to mode %s\n", output->name.c_str(), modeStr.c_str());
+	screen->renderBuffers.clear();
+	screen->currentBuffer.reset();
+	output->scheduleFrame(Aquamarine::IOutput::AQ_SCHEDULE_NEEDS_FRAME);
+	return true;
+}
+void rmarScreenSetTransform(struct RustamarineScreen *screen, uint32_t transform) {
+	screen->transform = transform;
+}
+uint32_t rmarScreenGetTransform(const struct RustamarineScreen *screen) {
+	return screen->transform;
+}
+void rmarScreenSetScale(struct RustamarineScreen *screen, float scale) {
+	screen->scale = scale > 0 ? scale : 1.0f;
+}
+float rmarScreenGetScale(const struct RustamarineScreen *screen) {
+	return screen->scale;
+}
 void rmarScreenSetEnabled(const struct RustamarineScreen *screen, bool isEnabled) {
 	if(isEnabled == screen->output->state->state().enabled) return;
 	screen->output->state->setEnabled(isEnabled);
@@ -1,7 +1,8 @@
 use rustamarine::{keys::KEY_Escape, opengl};
 
 fn main() {
-	let mut rustamarine = rustamarine::Rustamarine::new();
+	let mut rustamarine =
+		rustamarine::Rustamarine::new(Default::default()).expect("Failed to initialize rustamarine");
 	rustamarine::opengl::load_with(|s| rustamarine.get_opengl_proc_address(s) as *const _);
 	loop {
 		if rustamarine.is_key_pressed(KEY_Escape) {
@@ -3,7 +3,8 @@ use std::ffi::CStr;
 use rustamarine::{keys::KEY_Escape, opengl};
 
 fn main() {
-	let mut rustamarine = rustamarine::Rustamarine::new();
+	let mut rustamarine =
+		rustamarine::Rustamarine::new(Default::default()).expect("Failed to initialize rustamarine");
 	rustamarine::opengl::load_with(|s| rustamarine.get_opengl_proc_address(s) as *const _);
 	// Vertex and fragment shader sources for drawing a point
 	const VERT_SRC: &CStr = cr#"
@@ -0,0 +1,70 @@
+//! libinput settings of the input devices, like tap-to-click on touchpads.
+//!
+//! Settings are set per [`DeviceKind`] and apply to the devices of that kind that are plugged in
+//! and the ones that are plugged in later. Settings a device doesn't support are skipped.
+use super::{events::DeviceKind, *};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelProfile {
+	/// Constant speed, the pointer moves as much as the device
+	Flat,
+	/// Faster movements move the pointer further
+	Adaptive,
+}
+
+/// `None` keeps the libinput default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DeviceConfig {
+	pub tap_to_click: Option<bool>,
+	pub natural_scroll: Option<bool>,
+	pub left_handed: Option<bool>,
+	pub disable_while_typing: Option<bool>,
+	pub accel_profile: Option<AccelProfile>,
+	/// From -1 (slowest) to 1 (fastest)
+	pub accel_speed: Option<f64>,
+}
+
+impl DeviceConfig {
+	/// The settings of `self`, with the ones it doesn't set taken from `fallback`.
+	pub fn or(self, fallback: Self) -> Self {
+		Self {
+			tap_to_click: self.tap_to_click.or(fallback.tap_to_click),
+			natural_scroll: self.natural_scroll.or(fallback.natural_scroll),
+			left_handed: self.left_handed.or(fallback.left_handed),
+			disable_while_typing: self.disable_while_typing.or(fallback.disable_while_typing),
+			accel_profile: self.accel_profile.or(fallback.accel_profile),
+			accel_speed: self.accel_speed.or(fallback.accel_speed),
+		}
+	}
+
+	fn to_sys(self) -> sys::RustamarineDeviceConfig {
+		let flag = |value: Option<bool>| value.map_or(-1, i32::from);
+		sys::RustamarineDeviceConfig {
+			tapToClick: flag(self.tap_to_click),
+			naturalScroll: flag(self.natural_scroll),
+			leftHanded: flag(self.left_handed),
+			disableWhileTyping: flag(self.disable_while_typing),
+			accelProfile: match self.accel_profile {
+				None => sys::RustamarineAccelProfile_RMAR_ACCEL_PROFILE_DEFAULT,
+				Some(AccelProfile::Flat) => sys::RustamarineAccelProfile_RMAR_ACCEL_PROFILE_FLAT,
+				Some(AccelProfile::Adaptive) => sys::RustamarineAccelProfile_RMAR_ACCEL_PROFILE_ADAPTIVE,
+			},
+			accelSpeed: self.accel_speed.unwrap_or(f64::NAN),
+		}
+	}
+}
+
+impl Rustamarine {
+	/// Replaces the settings of the devices of `kind`. Mice and touchpads are configured separately,
+	/// a touchpad is a [`DeviceKind::Touchpad`] even though its events are pointer events.
+	pub fn set_device_config(&mut self, kind: DeviceKind, config: DeviceConfig) {
+		let kind = match kind {
+			DeviceKind::Keyboard => sys::RustamarineDeviceType_RMAR_DEVICE_KEYBOARD,
+			DeviceKind::Pointer => sys::RustamarineDeviceType_RMAR_DEVICE_POINTER,
+			DeviceKind::Touchpad => sys::RustamarineDeviceType_RMAR_DEVICE_TOUCHPAD,
+			DeviceKind::Touch => sys::RustamarineDeviceType_RMAR_DEVICE_TOUCH,
+			DeviceKind::Tablet => sys::RustamarineDeviceType_RMAR_DEVICE_TABLET,
+		};
+		unsafe { sys::rmarSetDeviceConfig(self.inner, kind, config.to_sys()) }
+	}
+}
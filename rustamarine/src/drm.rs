@@ -0,0 +1,95 @@
+//! DRM devices and their hotplug events.
+//!
+//! At boot the only DRM device is often the framebuffer set up by the firmware (simpledrm), which
+//! gets replaced by a new device once the driver of the GPU is loaded. Rustamarine keeps rendering
+//! on the device it was started with, so it's up to the caller to recreate it on the new device.
+use std::{
+	ffi::{CStr, CString, OsStr},
+	os::unix::ffi::OsStrExt,
+	path::{Path, PathBuf},
+};
+
+use super::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrmEvent {
+	Added(PathBuf),
+	Removed(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrmDevice {
+	/// e.g. `/dev/dri/card1`
+	pub path: PathBuf,
+	/// Framebuffer set up by the firmware (simpledrm, efidrm...), it can't do much and goes away once
+	/// the driver of the GPU is loaded
+	pub boot_framebuffer: bool,
+}
+
+impl DrmDevice {
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		let path = path.into();
+		let boot_framebuffer = CString::new(path.as_os_str().as_bytes())
+			.is_ok_and(|c_path| unsafe { sys::rmarIsBootFramebuffer(c_path.as_ptr()) });
+		Self {
+			path,
+			boot_framebuffer,
+		}
+	}
+}
+
+/// DRM devices that can display something (`/dev/dri/card*`), sorted by path.
+pub fn devices() -> Vec<DrmDevice> {
+	let mut paths = std::fs::read_dir("/dev/dri")
+		.into_iter()
+		.flatten()
+		.filter_map(|entry| entry.ok())
+		.map(|entry| entry.path())
+		.filter(|path| {
+			path
+				.file_name()
+				.and_then(|name| name.to_str())
+				.is_some_and(|name| name.starts_with("card"))
+		})
+		.collect::<Vec<_>>();
+	paths.sort();
+	paths.into_iter().map(DrmDevice::new).collect()
+}
+
+impl Rustamarine {
+	/// DRM devices that were added or removed since the last call.
+	///
+	/// udev is watched by [`Rustamarine::poll_events`], so it also returns when a device is plugged
+	/// in or out.
+	pub fn take_drm_events(&mut self) -> Vec<DrmEvent> {
+		let events = unsafe { sys::rmarTakeDrmEvents(self.inner) };
+		if events.count == 0 {
+			return vec![];
+		}
+		let slice = unsafe { std::slice::from_raw_parts(events.events, events.count) };
+		let events_vec = slice
+			.iter()
+			.map(|event| {
+				let path = unsafe { c_path(event.path) }.to_path_buf();
+				if event.type_ == sys::RustamarineDrmEventType_RMAR_DRM_DEVICE_ADDED {
+					DrmEvent::Added(path)
+				} else {
+					DrmEvent::Removed(path)
+				}
+			})
+			.collect();
+		unsafe { sys::rmarFreeDrmEvents(events) };
+		events_vec
+	}
+
+	/// DRM device the backend is rendering on, `None` if it isn't using DRM (e.g. running nested).
+	pub fn drm_device(&self) -> Option<&Path> {
+		let path = unsafe { sys::rmarGetDrmDevice(self.inner) };
+		(!path.is_null()).then(|| unsafe { c_path(path) })
+	}
+}
+
+/// The string must outlive `'a`
+unsafe fn c_path<'a>(path: *const std::ffi::c_char) -> &'a Path {
+	Path::new(OsStr::from_bytes(CStr::from_ptr(path).to_bytes()))
+}
@@ -0,0 +1,287 @@
+//! Input events, in the order they happened.
+//!
+//! The polling methods ([`Rustamarine::is_key_pressed`], [`Rustamarine::get_typed_characters`]...)
+//! only describe the state of the current frame, so they're enough for most UIs. The events are
+//! for when the order or every intermediate step matters (e.g. a key pressed and released twice in
+//! the same frame). Events are queued until [`Rustamarine::drain_events`] is called, the oldest
+//! ones are dropped if it never is.
+use std::ops::BitOr;
+
+use super::*;
+
+/// A set of modifiers, e.g. the ones that were active when a key event happened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(pub u32);
+
+impl Modifiers {
+	pub const NONE: Self = Self(0);
+	pub const SHIFT: Self = Self(sys::RustamarineModifier_RMAR_MODIFIER_SHIFT);
+	pub const CTRL: Self = Self(sys::RustamarineModifier_RMAR_MODIFIER_CTRL);
+	pub const ALT: Self = Self(sys::RustamarineModifier_RMAR_MODIFIER_ALT);
+	pub const SUPER: Self = Self(sys::RustamarineModifier_RMAR_MODIFIER_SUPER);
+	/// Only set while Caps Lock is locked
+	pub const CAPS_LOCK: Self = Self(sys::RustamarineModifier_RMAR_MODIFIER_CAPS_LOCK);
+	/// Only set while Num Lock is locked
+	pub const NUM_LOCK: Self = Self(sys::RustamarineModifier_RMAR_MODIFIER_NUM_LOCK);
+	pub const LOCKS: Self = Self(Self::CAPS_LOCK.0 | Self::NUM_LOCK.0);
+
+	/// `true` if all the modifiers of `other` are active.
+	pub fn contains(self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	/// Shift, Ctrl, Alt and Super only, the modifiers that are part of a key combination.
+	pub fn without_locks(self) -> Self {
+		Self(self.0 & !Self::LOCKS.0)
+	}
+}
+
+impl BitOr for Modifiers {
+	type Output = Self;
+
+	fn bitor(self, rhs: Self) -> Self {
+		Self(self.0 | rhs.0)
+	}
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Leds {
+	pub num_lock: bool,
+	pub caps_lock: bool,
+	pub scroll_lock: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+	Keyboard,
+	Pointer,
+	/// A pointer that can tap, its events are pointer events
+	Touchpad,
+	Touch,
+	Tablet,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+	Key {
+		/// evdev keycode of the physical key
+		keycode: u32,
+		/// Keysym in the current layout, can be compared with the constants of the [`keys`] module
+		keysym: u32,
+		pressed: bool,
+		/// Modifiers after the key was handled
+		modifiers: Modifiers,
+	},
+	/// Text typed with the keyboard, after compose sequences
+	Text(String),
+	PointerMotion {
+		/// New position of the mouse
+		x: f64,
+		y: f64,
+		dx: f64,
+		dy: f64,
+	},
+	PointerButton {
+		/// 0 is the left button, 1 the right one and 2 the middle one
+		button: u32,
+		pressed: bool,
+	},
+	PointerAxis {
+		dx: f64,
+		dy: f64,
+	},
+	/// A finger touched the screen.
+	///
+	/// Touch and tablet positions go from 0 to 1 on the device, in the orientation of the screen it's
+	/// mapped to before its transform is applied.
+	TouchDown {
+		/// Unique while the finger touches the screen
+		id: i32,
+		x: f64,
+		y: f64,
+	},
+	TouchMotion {
+		id: i32,
+		x: f64,
+		y: f64,
+	},
+	TouchUp {
+		id: i32,
+	},
+	/// The touch was taken over by something else (e.g. a palm was detected), it's not a tap
+	TouchCancel {
+		id: i32,
+	},
+	/// A tablet tool (e.g. a pen) moved or its pressure changed
+	TabletToolMotion {
+		x: f64,
+		y: f64,
+		/// From 0 to 1
+		pressure: f64,
+	},
+	/// A tablet tool came close to the tablet (`in_proximity`) or went away from it
+	TabletToolProximity {
+		x: f64,
+		y: f64,
+		in_proximity: bool,
+	},
+	/// The tip of a tablet tool touched the tablet (`down`) or was lifted
+	TabletToolTip {
+		x: f64,
+		y: f64,
+		down: bool,
+	},
+	TabletToolButton {
+		/// evdev code of the button, e.g. `BTN_STYLUS`
+		button: u32,
+		pressed: bool,
+	},
+	DeviceAdded {
+		kind: DeviceKind,
+		name: String,
+	},
+	DeviceRemoved {
+		kind: DeviceKind,
+		name: String,
+	},
+}
+
+impl Event {
+	fn from_sys(event: &sys::RustamarineEvent) -> Self {
+		let text = || {
+			if event.text.is_null() {
+				String::new()
+			} else {
+				unsafe { CStr::from_ptr(event.text) }
+					.to_string_lossy()
+					.into_owned()
+			}
+		};
+		let kind = match event.deviceType {
+			sys::RustamarineDeviceType_RMAR_DEVICE_KEYBOARD => DeviceKind::Keyboard,
+			sys::RustamarineDeviceType_RMAR_DEVICE_TOUCHPAD => DeviceKind::Touchpad,
+			sys::RustamarineDeviceType_RMAR_DEVICE_TOUCH => DeviceKind::Touch,
+			sys::RustamarineDeviceType_RMAR_DEVICE_TABLET => DeviceKind::Tablet,
+			_ => DeviceKind::Pointer,
+		};
+		match event.type_ {
+			sys::RustamarineEventType_RMAR_EVENT_KEY => Self::Key {
+				keycode: event.keycode,
+				keysym: event.keysym,
+				pressed: event.pressed,
+				modifiers: Modifiers(event.modifiers),
+			},
+			sys::RustamarineEventType_RMAR_EVENT_TEXT => Self::Text(text()),
+			sys::RustamarineEventType_RMAR_EVENT_POINTER_MOTION => Self::PointerMotion {
+				x: event.x,
+				y: event.y,
+				dx: event.dx,
+				dy: event.dy,
+			},
+			sys::RustamarineEventType_RMAR_EVENT_POINTER_BUTTON => Self::PointerButton {
+				button: event.button,
+				pressed: event.pressed,
+			},
+			sys::RustamarineEventType_RMAR_EVENT_POINTER_AXIS => Self::PointerAxis {
+				dx: event.dx,
+				dy: event.dy,
+			},
+			sys::RustamarineEventType_RMAR_EVENT_TOUCH_DOWN => Self::TouchDown {
+				id: event.touchId,
+				x: event.x,
+				y: event.y,
+			},
+			sys::RustamarineEventType_RMAR_EVENT_TOUCH_MOTION => Self::TouchMotion {
+				id: event.touchId,
+				x: event.x,
+				y: event.y,
+			},
+			sys::RustamarineEventType_RMAR_EVENT_TOUCH_UP => Self::TouchUp { id: event.touchId },
+			sys::RustamarineEventType_RMAR_EVENT_TOUCH_CANCEL => Self::TouchCancel { id: event.touchId },
+			sys::RustamarineEventType_RMAR_EVENT_TABLET_TOOL_AXIS => Self::TabletToolMotion {
+				x: event.x,
+				y: event.y,
+				pressure: event.pressure,
+			},
+			sys::RustamarineEventType_RMAR_EVENT_TABLET_TOOL_PROXIMITY => Self::TabletToolProximity {
+				x: event.x,
+				y: event.y,
+				in_proximity: event.pressed,
+			},
+			sys::RustamarineEventType_RMAR_EVENT_TABLET_TOOL_TIP => Self::TabletToolTip {
+				x: event.x,
+				y: event.y,
+				down: event.pressed,
+			},
+			sys::RustamarineEventType_RMAR_EVENT_TABLET_TOOL_BUTTON => Self::TabletToolButton {
+				button: event.button,
+				pressed: event.pressed,
+			},
+			sys::RustamarineEventType_RMAR_EVENT_DEVICE_ADDED => Self::DeviceAdded { kind, name: text() },
+			_ => Self::DeviceRemoved { kind, name: text() },
+		}
+	}
+}
+
+impl Rustamarine {
+	/// Modifiers that apply to the next key press, on any keyboard.
+	///
+	/// Caps Lock and Num Lock are only set while they're locked, not while their key is held to
+	/// unlock them.
+	pub fn modifiers(&self) -> Modifiers {
+		self.get_modifiers(sys::RustamarineModifierState_RMAR_MODIFIERS_EFFECTIVE)
+	}
+
+	/// Modifiers whose key is held down.
+	pub fn depressed_modifiers(&self) -> Modifiers {
+		self.get_modifiers(sys::RustamarineModifierState_RMAR_MODIFIERS_DEPRESSED)
+	}
+
+	/// Sticky modifiers that only apply to the next key press (e.g. with the sticky keys
+	/// accessibility option).
+	pub fn latched_modifiers(&self) -> Modifiers {
+		self.get_modifiers(sys::RustamarineModifierState_RMAR_MODIFIERS_LATCHED)
+	}
+
+	/// Modifiers that stay active until their key is pressed again, like Caps Lock.
+	pub fn locked_modifiers(&self) -> Modifiers {
+		self.get_modifiers(sys::RustamarineModifierState_RMAR_MODIFIERS_LOCKED)
+	}
+
+	fn get_modifiers(&self, state: sys::RustamarineModifierState) -> Modifiers {
+		Modifiers(unsafe { sys::rmarGetModifiers(self.inner, state) })
+	}
+
+	/// Locks (`locked = true`) or unlocks Caps Lock and/or Num Lock, e.g. to turn on Num Lock at
+	/// startup. Only [`Modifiers::CAPS_LOCK`] and [`Modifiers::NUM_LOCK`] are used from `locks`.
+	///
+	/// Keyboards that are plugged in later start with the locks that were set last.
+	pub fn set_locked(&mut self, locks: Modifiers, locked: bool) {
+		unsafe { sys::rmarSetLocks(self.inner, locks.0, locked) }
+	}
+
+	/// Keyboard LEDs that are on, on any keyboard. They're kept in sync with the locks.
+	pub fn leds(&self) -> Leds {
+		let leds = unsafe { sys::rmarGetLeds(self.inner) };
+		let on = |led: sys::RustamarineLed| leds & led != 0;
+		Leds {
+			num_lock: on(sys::RustamarineLed_RMAR_LED_NUM_LOCK),
+			caps_lock: on(sys::RustamarineLed_RMAR_LED_CAPS_LOCK),
+			scroll_lock: on(sys::RustamarineLed_RMAR_LED_SCROLL_LOCK),
+		}
+	}
+
+	/// Input events since the last call, oldest first.
+	///
+	/// Draining the events doesn't affect the polling methods, both can be used at the same time.
+	pub fn drain_events(&mut self) -> Vec<Event> {
+		let events = unsafe { sys::rmarDrainEvents(self.inner) };
+		if events.count == 0 {
+			return vec![];
+		}
+		let slice = unsafe { std::slice::from_raw_parts(events.events, events.count) };
+		let events_vec = slice.iter().map(Event::from_sys).collect();
+		unsafe { sys::rmarFreeEvents(events) };
+		events_vec
+	}
+}
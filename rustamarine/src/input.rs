@@ -16,7 +16,8 @@ impl Rustamarine {
 
 	/// Check if a key was just pressed this frame.
 	///
-	/// This function only returns `true` for one frame when the key is first pressed.
+	/// This function only returns `true` for one frame when the key is first pressed, even if it
+	/// was released again in the same frame.
 	///
 	/// # Arguments
 	///
@@ -74,7 +75,8 @@ impl Rustamarine {
 
 	/// Check if a mouse button was just pressed this frame.
 	///
-	/// This function only returns `true` for one frame when the button is first pressed.
+	/// This function only returns `true` for one frame when the button is first pressed, even if it
+	/// was released again in the same frame (a quick click).
 	///
 	/// # Arguments
 	///
@@ -186,7 +188,7 @@ impl Rustamarine {
 	/// # Example
 	///
 	/// ```
-	/// let rmar = Rustamarine::new();
+	/// let rmar = Rustamarine::new(Default::default()).unwrap();
 	/// // In your main loop:
 	/// rmar.poll_events();
 	/// let text_input = rmar.get_char();
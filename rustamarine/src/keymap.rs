@@ -0,0 +1,115 @@
+//! Keyboard layouts.
+//!
+//! Every keyboard uses the same xkb keymap, made of up to 4 layouts (xkb groups). Only one of them
+//! is active at a time, it can be changed with [`Rustamarine::set_active_keyboard_layout`] or with
+//! the keyboard if the keymap has an option to switch groups.
+use std::{fmt, str::FromStr};
+
+use super::*;
+
+/// An xkb layout and its variant, written as `us` or `us(intl)`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyboardLayout {
+	pub layout: String,
+	/// Empty for the default variant
+	pub variant: String,
+}
+
+impl FromStr for KeyboardLayout {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		let (layout, variant) = match s.strip_suffix(')').and_then(|s| s.split_once('(')) {
+			Some((layout, variant)) => (layout.trim(), variant.trim()),
+			None => (s, ""),
+		};
+		let valid = |name: &str| {
+			name
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+		};
+		if layout.is_empty() || !valid(layout) || !valid(variant) {
+			return Err(());
+		}
+		Ok(Self {
+			layout: layout.to_string(),
+			variant: variant.to_string(),
+		})
+	}
+}
+
+impl fmt::Display for KeyboardLayout {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.variant.is_empty() {
+			write!(f, "{}", self.layout)
+		} else {
+			write!(f, "{}({})", self.layout, self.variant)
+		}
+	}
+}
+
+impl Rustamarine {
+	/// Replaces the layouts of the keymap, the first one becomes the active one.
+	///
+	/// Returns `false` and keeps the current keymap if xkb can't compile it (e.g. an unknown layout).
+	pub fn set_keyboard_layouts(&mut self, layouts: &[KeyboardLayout]) -> bool {
+		let join = |f: fn(&KeyboardLayout) -> &str| {
+			CString::new(layouts.iter().map(f).collect::<Vec<_>>().join(","))
+		};
+		let (Ok(names), Ok(variants)) = (join(|l| l.layout.as_str()), join(|l| l.variant.as_str()))
+		else {
+			return false;
+		};
+		unsafe { sys::rmarSetKeyboardLayouts(self.inner, names.as_ptr(), variants.as_ptr()) }
+	}
+
+	/// Descriptions of the layouts of the keymap (e.g. `English (US)`), empty if there's no
+	/// keyboard.
+	pub fn keyboard_layout_names(&self) -> Vec<String> {
+		let count = unsafe { sys::rmarGetKeyboardLayoutCount(self.inner) };
+		(0..count)
+			.map(|i| {
+				let name = unsafe { sys::rmarGetKeyboardLayoutName(self.inner, i) };
+				if name.is_null() {
+					String::new()
+				} else {
+					unsafe { CStr::from_ptr(name) }
+						.to_string_lossy()
+						.into_owned()
+				}
+			})
+			.collect()
+	}
+
+	/// Index of the active layout.
+	pub fn active_keyboard_layout(&self) -> usize {
+		unsafe { sys::rmarGetActiveKeyboardLayout(self.inner) }
+	}
+
+	/// Description of the active layout, `None` if there's no keyboard.
+	pub fn active_keyboard_layout_name(&self) -> Option<String> {
+		self
+			.keyboard_layout_names()
+			.into_iter()
+			.nth(self.active_keyboard_layout())
+	}
+
+	pub fn set_active_keyboard_layout(&mut self, index: usize) {
+		unsafe { sys::rmarSetActiveKeyboardLayout(self.inner, index) }
+	}
+
+	/// Keysym of the key with the evdev `keycode` in the active layout, as if Shift was held if
+	/// `shift` is `true`. Works without a keyboard, e.g. to label the keys of an on-screen keyboard.
+	pub fn key_keysym(&self, keycode: u32, shift: bool) -> Option<u32> {
+		let keysym = unsafe { sys::rmarGetKeyKeysym(self.inner, keycode, shift as u32) };
+		(keysym != 0).then_some(keysym)
+	}
+}
+
+/// Text typed by `keysym`, `None` for keys that don't type anything like Shift or dead keys.
+pub fn keysym_to_text(keysym: u32) -> Option<String> {
+	let mut buffer = [0u8; 16];
+	let length = unsafe { sys::rmarKeysymToUtf8(keysym, buffer.as_mut_ptr().cast(), buffer.len()) };
+	(length > 0).then(|| String::from_utf8_lossy(&buffer[..length]).into_owned())
+}
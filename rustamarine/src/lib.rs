@@ -1,25 +1,118 @@
 use std::{
-	ffi::{c_void, CString},
+	ffi::{c_void, CStr, CString},
+	fmt,
 	marker::PhantomData,
 	mem::ManuallyDrop,
+	os::unix::ffi::OsStrExt,
+	path::PathBuf,
 	str::FromStr,
 };
 
 mod sys;
 #[cfg(feature = "opengl_loader")]
 pub use sys::opengl;
+pub mod device_config;
+pub mod drm;
+pub mod events;
 mod input;
+pub mod keymap;
 pub use input::keys;
 pub mod screen;
+pub mod shortcut;
 pub struct Rustamarine {
 	inner: *mut sys::Rustamarine,
 }
 
-impl Rustamarine {
-	pub fn new() -> Self {
+/// How a backend is used when initializing rustamarine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendRequest {
+	Disabled,
+	/// Used if it can be started
+	Optional,
+	/// Initialization fails if it can't be started
+	Required,
+	/// Only used if none of the other backends could be started
+	Fallback,
+}
+
+impl BackendRequest {
+	fn to_sys(self) -> sys::RustamarineBackendRequest {
+		match self {
+			Self::Disabled => sys::RustamarineBackendRequest_RMAR_BACKEND_DISABLED,
+			Self::Optional => sys::RustamarineBackendRequest_RMAR_BACKEND_OPTIONAL,
+			Self::Required => sys::RustamarineBackendRequest_RMAR_BACKEND_REQUIRED,
+			Self::Fallback => sys::RustamarineBackendRequest_RMAR_BACKEND_FALLBACK,
+		}
+	}
+}
+
+/// Backends that [`Rustamarine::new`] is allowed to use.
+///
+/// The default uses DRM when available, falls back to a Wayland window when running nested, and
+/// always adds a headless backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+	pub drm: BackendRequest,
+	pub wayland: BackendRequest,
+	pub headless: BackendRequest,
+	/// DRM device to use (e.g. `/dev/dri/card1`), aquamarine picks one if it's `None`
+	pub drm_device: Option<PathBuf>,
+}
+
+impl Default for Options {
+	fn default() -> Self {
 		Self {
-			inner: unsafe { sys::rmarInitialize() },
+			drm: BackendRequest::Optional,
+			wayland: BackendRequest::Fallback,
+			headless: BackendRequest::Required,
+			drm_device: None,
+		}
+	}
+}
+
+/// Why [`Rustamarine::new`] failed, e.g. there's no DRM device or EGL couldn't be initialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InitError(pub String);
+
+impl fmt::Display for InitError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Failed to initialize rustamarine: {}", self.0)
+	}
+}
+
+impl std::error::Error for InitError {}
+
+impl Rustamarine {
+	pub fn new(options: Options) -> Result<Self, InitError> {
+		let drm_device = options
+			.drm_device
+			.as_ref()
+			.map(|path| CString::new(path.as_os_str().as_bytes()))
+			.transpose()
+			.map_err(|_| InitError("The DRM device path has a NUL byte".into()))?;
+		let sys_options = sys::RustamarineOptions {
+			drm: options.drm.to_sys(),
+			wayland: options.wayland.to_sys(),
+			headless: options.headless.to_sys(),
+			drmDevice: drm_device
+				.as_ref()
+				.map_or(std::ptr::null(), |device| device.as_ptr()),
+		};
+		let mut error = std::ptr::null_mut();
+		let inner = unsafe { sys::rmarInitialize(sys_options, &mut error) };
+		if inner.is_null() {
+			let message = if error.is_null() {
+				"Unknown error".to_string()
+			} else {
+				let message = unsafe { CStr::from_ptr(error) }
+					.to_string_lossy()
+					.into_owned();
+				unsafe { sys::rmarFreeError(error) };
+				message
+			};
+			return Err(InitError(message));
 		}
+		Ok(Self { inner })
 	}
 	pub fn poll_events(&mut self) {
 		unsafe { sys::rmarPollEvents(self.inner) };
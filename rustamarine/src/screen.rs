@@ -1,8 +1,87 @@
-use std::{marker::PhantomData, mem::ManuallyDrop};
+use std::{marker::PhantomData, mem::ManuallyDrop, str::FromStr};
 
 use crate::{Rustamarine, RustamarineRef};
 
 use super::sys;
+
+/// Stable handle to a screen.
+///
+/// Ids are never reused, so unlike a [`Screen`] they can be stored and looked up later with
+/// [`Rustamarine::screen`], which returns `None` once the screen is unplugged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScreenId(pub(crate) u64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mode {
+	pub width: u32,
+	pub height: u32,
+	/// In Hz, `0` lets the backend pick one when used in [`Screen::set_mode`]
+	pub refresh_rate: f32,
+	pub preferred: bool,
+}
+
+/// Rotation and flip of a screen, with the same meaning as `wl_output_transform`: the content is
+/// rotated counter-clockwise by the given angle, and flipped around the vertical axis before that
+/// for the `Flipped` variants.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Transform {
+	#[default]
+	Normal = 0,
+	Rotate90 = 1,
+	Rotate180 = 2,
+	Rotate270 = 3,
+	Flipped = 4,
+	Flipped90 = 5,
+	Flipped180 = 6,
+	Flipped270 = 7,
+}
+
+impl Transform {
+	fn from_raw(raw: u32) -> Self {
+		match raw {
+			1 => Self::Rotate90,
+			2 => Self::Rotate180,
+			3 => Self::Rotate270,
+			4 => Self::Flipped,
+			5 => Self::Flipped90,
+			6 => Self::Flipped180,
+			7 => Self::Flipped270,
+			_ => Self::Normal,
+		}
+	}
+	/// Rotation in degrees, counter-clockwise
+	pub fn rotation(self) -> u32 {
+		(self as u32 % 4) * 90
+	}
+	pub fn is_flipped(self) -> bool {
+		self as u32 >= 4
+	}
+	/// Whether width and height are swapped
+	pub fn is_sideways(self) -> bool {
+		self as u32 % 2 == 1
+	}
+}
+
+impl FromStr for Transform {
+	type Err = ();
+
+	/// Parses `normal`, `90`, `180`, `270`, `flipped`, `flipped-90`, `flipped-180` and `flipped-270`
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"normal" | "0" => Ok(Self::Normal),
+			"90" => Ok(Self::Rotate90),
+			"180" => Ok(Self::Rotate180),
+			"270" => Ok(Self::Rotate270),
+			"flipped" => Ok(Self::Flipped),
+			"flipped-90" => Ok(Self::Flipped90),
+			"flipped-180" => Ok(Self::Flipped180),
+			"flipped-270" => Ok(Self::Flipped270),
+			_ => Err(()),
+		}
+	}
+}
+
 pub struct Screen<'a> {
 	inner: &'a mut sys::RustamarineScreen,
 }
@@ -56,6 +135,74 @@ impl<'a> Screen<'a> {
 		unsafe { sys::rmarScreenGetHeight(self.inner) }
 	}
 
+	/// Physical size of the screen in millimeters, `(0, 0)` if it's unknown
+	pub fn get_physical_size(&self) -> (u32, u32) {
+		unsafe {
+			(
+				sys::rmarScreenGetPhysicalWidth(self.inner),
+				sys::rmarScreenGetPhysicalHeight(self.inner),
+			)
+		}
+	}
+
+	/// Modes supported by the screen
+	pub fn modes(&self) -> Vec<Mode> {
+		let modes = unsafe { sys::rmarScreenGetModes(self.inner) };
+		if modes.count == 0 {
+			return vec![];
+		}
+		let slice = unsafe { std::slice::from_raw_parts(modes.modes, modes.count as usize) };
+		let modes_vec = slice
+			.iter()
+			.map(|mode| Mode {
+				width: mode.width,
+				height: mode.height,
+				refresh_rate: mode.refreshRate,
+				preferred: mode.preferred,
+			})
+			.collect();
+		unsafe { sys::rmarFreeModes(modes) };
+		modes_vec
+	}
+
+	/// Changes the mode of the screen, it doesn't need to be one of [`Screen::modes`].
+	///
+	/// Returns `false` if the mode was rejected, the current mode is kept in that case.
+	pub fn set_mode(&mut self, mode: &Mode) -> bool {
+		unsafe {
+			sys::rmarScreenSetMode(
+				self.inner,
+				sys::RustamarineMode {
+					width: mode.width,
+					height: mode.height,
+					refreshRate: mode.refresh_rate,
+					preferred: mode.preferred,
+				},
+			)
+		}
+	}
+
+	/// Sets the transform of the screen.
+	///
+	/// Rustamarine doesn't rotate anything by itself, it's up to the renderer to apply the transform
+	/// returned by [`Screen::get_transform`].
+	pub fn set_transform(&mut self, transform: Transform) {
+		unsafe { sys::rmarScreenSetTransform(self.inner, transform as u32) }
+	}
+
+	pub fn get_transform(&self) -> Transform {
+		Transform::from_raw(unsafe { sys::rmarScreenGetTransform(self.inner) })
+	}
+
+	/// Sets the scale of the screen, like the transform it's up to the renderer to apply it.
+	pub fn set_scale(&mut self, scale: f32) {
+		unsafe { sys::rmarScreenSetScale(self.inner, scale) }
+	}
+
+	pub fn get_scale(&self) -> f32 {
+		unsafe { sys::rmarScreenGetScale(self.inner) }
+	}
+
 	pub fn get_refresh_rate(&self) -> f32 {
 		unsafe { sys::rmarScreenGetRefreshRate(self.inner) }
 	}
@@ -67,6 +214,10 @@ impl<'a> Screen<'a> {
 		}
 	}
 
+	pub fn id(&self) -> ScreenId {
+		ScreenId(unsafe { sys::rmarScreenGetId(self.inner) })
+	}
+
 	pub fn is_enabled(&self) -> bool {
 		unsafe { sys::rmarScreenIsEnabled(self.inner) }
 	}
@@ -90,4 +241,58 @@ impl super::Rustamarine {
 		unsafe { sys::rmarFreeScreens(screens) };
 		screens_vec
 	}
+
+	/// Looks up a screen by its id, returns `None` if it was disconnected.
+	pub fn screen<'a>(&'a mut self, id: ScreenId) -> Option<Screen<'a>> {
+		let inner = unsafe { sys::rmarGetScreenById(self.inner, id.0).as_mut() }?;
+		Some(Screen { inner })
+	}
+
+	/// Sets a callback that's called from [`Rustamarine::poll_events`] whenever a screen is plugged
+	/// in, with its id and name.
+	pub fn set_on_screen_added<F>(&mut self, callback: F)
+	where
+		F: FnMut(ScreenId, &str) + 'static,
+	{
+		let (callback, context) = screen_callback(callback);
+		unsafe { sys::rmarSetOnScreenAdded(self.inner, callback, context) }
+	}
+
+	/// Sets a callback that's called from [`Rustamarine::poll_events`] whenever a screen is unplugged,
+	/// with its id and name.
+	///
+	/// [`Screen`]s that were obtained before can't be used anymore after this, and the render
+	/// callback of the screen is dropped.
+	pub fn set_on_screen_removed<F>(&mut self, callback: F)
+	where
+		F: FnMut(ScreenId, &str) + 'static,
+	{
+		let (callback, context) = screen_callback(callback);
+		unsafe { sys::rmarSetOnScreenRemoved(self.inner, callback, context) }
+	}
+}
+
+type ScreenCallback =
+	unsafe extern "C" fn(*mut std::os::raw::c_void, u64, *const std::os::raw::c_char);
+
+fn screen_callback<F>(callback: F) -> (Option<ScreenCallback>, *mut std::os::raw::c_void)
+where
+	F: FnMut(ScreenId, &str) + 'static,
+{
+	use std::os::raw::{c_char, c_void};
+	unsafe extern "C" fn rust_screen_callback_trampoline(
+		ctx: *mut c_void,
+		id: u64,
+		name: *const c_char,
+	) {
+		let closure = &mut *(ctx as *mut Box<dyn FnMut(ScreenId, &str)>);
+		let name = std::ffi::CStr::from_ptr(name).to_str().unwrap_or_default();
+		closure(ScreenId(id), name);
+	}
+
+	let boxed: Box<Box<dyn FnMut(ScreenId, &str)>> = Box::new(Box::new(callback));
+	(
+		Some(rust_screen_callback_trampoline),
+		Box::into_raw(boxed) as *mut c_void,
+	)
 }
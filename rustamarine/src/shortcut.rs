@@ -0,0 +1,89 @@
+//! Key combinations like `Ctrl+Backspace`.
+//!
+//! Shortcuts only care about Shift, Ctrl, Alt and Super: Caps Lock and Num Lock being on doesn't
+//! stop `Ctrl+A` from matching. Letters match regardless of their case, so `Ctrl+Shift+A` matches
+//! even though Shift turns the keysym of the key into an uppercase `A`.
+use std::{fmt, str::FromStr};
+
+use super::*;
+use crate::events::Modifiers;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+	pub modifiers: Modifiers,
+	/// Lowercase keysym of the key
+	pub keysym: u32,
+}
+
+/// Why a shortcut couldn't be parsed, e.g. an unknown modifier or key name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseShortcutError(pub String);
+
+impl fmt::Display for ParseShortcutError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Invalid shortcut: {}", self.0)
+	}
+}
+
+impl std::error::Error for ParseShortcutError {}
+
+impl Shortcut {
+	pub fn new(modifiers: Modifiers, keysym: u32) -> Self {
+		Self {
+			modifiers: modifiers.without_locks(),
+			keysym: unsafe { sys::rmarKeysymToLower(keysym) },
+		}
+	}
+
+	/// `true` if pressing `keysym` while `modifiers` are active triggers the shortcut.
+	pub fn matches(&self, modifiers: Modifiers, keysym: u32) -> bool {
+		self.modifiers == modifiers.without_locks()
+			&& self.keysym == unsafe { sys::rmarKeysymToLower(keysym) }
+	}
+
+	/// Keysyms the key can have: the lowercase and uppercase ones for letters, just one for the
+	/// other keys.
+	pub fn keysyms(&self) -> impl Iterator<Item = u32> {
+		let upper = unsafe { sys::rmarKeysymToUpper(self.keysym) };
+		std::iter::once(self.keysym).chain((upper != self.keysym).then_some(upper))
+	}
+}
+
+impl FromStr for Shortcut {
+	type Err = ParseShortcutError;
+
+	/// Parses `Ctrl+Shift+BackSpace`, the key is an xkb keysym name and the case is ignored.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let error = |reason: &str| ParseShortcutError(format!("{s:?}: {reason}"));
+		let mut parts = s.split('+').map(str::trim).collect::<Vec<_>>();
+		let key = parts
+			.pop()
+			.filter(|key| !key.is_empty())
+			.ok_or_else(|| error("missing key"))?;
+		let mut modifiers = Modifiers::NONE;
+		for modifier in parts {
+			modifiers = modifiers
+				| match modifier.to_ascii_lowercase().as_str() {
+					"shift" => Modifiers::SHIFT,
+					"ctrl" | "control" => Modifiers::CTRL,
+					"alt" | "mod1" => Modifiers::ALT,
+					"super" | "logo" | "meta" | "mod4" => Modifiers::SUPER,
+					_ => return Err(error(&format!("unknown modifier {modifier:?}"))),
+				};
+		}
+		let c_key = CString::new(key).map_err(|_| error("invalid key name"))?;
+		let keysym = unsafe { sys::rmarKeysymFromName(c_key.as_ptr()) };
+		if keysym == 0 {
+			return Err(error(&format!("unknown key {key:?}")));
+		}
+		Ok(Self::new(modifiers, keysym))
+	}
+}
+
+impl Rustamarine {
+	/// `true` if the key of `shortcut` was just pressed with exactly its modifiers held.
+	pub fn is_shortcut_pressed(&self, shortcut: &Shortcut) -> bool {
+		shortcut.modifiers == self.modifiers().without_locks()
+			&& shortcut.keysyms().any(|keysym| self.is_key_pressed(keysym))
+	}
+}
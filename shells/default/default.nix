@@ -31,6 +31,7 @@ mkShell rec {
     libdrm.dev
     libgbm
     libunwind.dev
+    udev
     libbacktrace
     pkgs.llvmPackages_16.libclang.lib
     pam
@@ -1,25 +1,43 @@
 use super::FONTS;
 
-use crate::animation::{Animation, AnimationStateTracker, BasicAnimation};
+use crate::animation::{
+	easing::{ease_in_out_circ, ease_in_quad},
+	Animation, AnimationStateTracker, BasicAnimation, DelayAnimation,
+};
 use crate::background::Background;
+use crate::config::CONFIG;
 use crate::cursor::Cursor;
 use crate::custom_elements::CustomElements;
 use crate::fps_counter::FPSCounter;
 use crate::frame_pool::FramePool;
+use crate::input::Input;
+use crate::keybindings::Action;
 use crate::loading_screen::LoadingScreen;
 use crate::login::LoginManager;
 use crate::login::LoginScreen;
+use crate::outputs::{MultiMonitorPolicy, Output, OutputLayout};
 use crate::progress_watcher::ProgressWatcher;
 use crate::session_manager::{self, SessionManager};
 use crate::skia::SkiaContext;
+use crate::touch::{TouchInput, TouchPointer};
 use crate::{gl, skia};
 use assets_manager::AssetCache;
 use clay_layout::{fixed, grow, Declaration};
+use rustamarine::events::Modifiers;
 use rustamarine::screen::Screen;
-use skia_safe::Rect;
+use skia_safe::{Canvas, Picture, PictureRecorder, Rect};
 use std::rc::Rc;
 use std::thread::sleep;
 use std::time::Duration;
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum OutputRole {
+	/// The output with the interactive UI
+	Ui,
+	/// Shows a scaled copy of the UI output
+	Mirror,
+	/// Only shows the background
+	Background,
+}
 #[derive(Default, Copy, Clone)]
 pub enum LoginAnimationDirection {
 	FadeOut,
@@ -40,6 +58,11 @@ pub struct AppState<'a> {
 	pub show_login_screen: bool,
 	pub screen_slide_animation_progress: f32,
 	pub devtools: bool,
+	/// Hidden in headless mode, the cursor theme depends on the machine
+	pub show_cursor: bool,
+	/// Enables the debugging shortcuts: Escape quits, F12 (the `toggle-devtools` keybinding) toggles
+	/// the devtools and P plays the login animation
+	pub dev_mode: bool,
 	pub background: Background,
 	pub should_exit: bool,
 	pub login_manager: LoginManager,
@@ -47,62 +70,169 @@ pub struct AppState<'a> {
 	pub login_animation: AnimationStateTracker,
 	pub login_animation_direction: LoginAnimationDirection,
 	pub scroll_velocity: (f32, f32),
+	pub touch: TouchPointer,
 	pub frame_pool: FramePool<'a>,
+	pub outputs: OutputLayout,
+	/// Last frame of the UI along with its size, replayed on the other outputs in mirror mode
+	pub ui_picture: Option<(Picture, (f32, f32))>,
+	/// Set if skia couldn't be initialized, the text-mode greeter is shown instead
+	pub graphics_error: Option<String>,
+	/// The mouse starts in the middle of the primary output, but keeps its position when moving to
+	/// another GPU
+	center_mouse: bool,
 }
 
 impl AppState<'_> {
-	pub fn update(&mut self, rmar: &mut rustamarine::Rustamarine, screen: &mut Screen) {
+	/// `scale` is the biggest scale of the outputs, used to pick the resolution of the images.
+	pub fn new(
+		clay: clay_layout::Clay,
+		assets: Rc<AssetCache>,
+		scale: f32,
+		boot_progress: ProgressWatcher,
+		login_manager: LoginManager,
+		session_manager: SessionManager,
+		login_screen: LoginScreen,
+	) -> Self {
+		Self {
+			boot_progress,
+			fps_counter: FPSCounter::new(),
+			last_time: std::time::Instant::now(),
+			scroll_velocity: (0., 0.),
+			touch: TouchPointer::default(),
+			clay,
+			skia: None,
+			loading_screen: LoadingScreen::new(&assets, scale),
+			login_screen,
+			cursor: Cursor::new(None, scale),
+			screen_slide_animation: BasicAnimation::new("screen_slide", 1.5, ease_in_out_circ),
+			show_login_screen: false,
+			screen_slide_animation_progress: 0.0,
+			devtools: false,
+			show_cursor: true,
+			dev_mode: std::env::var("TIBS_DEV_MODE").is_ok_and(|v| v == "1"),
+			background: Background::new(Rc::clone(&assets)),
+			assets,
+			should_exit: false,
+			login_manager,
+			session_manager,
+			login_animation: seq!(
+				BasicAnimation::new("hide_ui", 0.2, ease_in_quad),
+				DelayAnimation::new(
+					0.1,
+					BasicAnimation::new("hide_background", 0.3, ease_in_quad)
+				)
+			)
+			.into(),
+			login_animation_direction: Default::default(),
+			frame_pool: FramePool::new(),
+			outputs: OutputLayout::default(),
+			ui_picture: None,
+			graphics_error: None,
+			center_mouse: true,
+		}
+	}
+	/// Replaces the output layout, resetting all the rendering state that depends on the outputs.
+	pub fn set_outputs(&mut self, outputs: OutputLayout) {
+		if self.outputs == outputs {
+			return;
+		}
+		log::debug!("Outputs changed: {outputs:?}");
+		self.outputs = outputs;
+		self.ui_picture = None;
+		self.cursor.set_scale(self.outputs.max_scale());
+		if let Some(skia) = &mut self.skia {
+			skia.clear_surfaces();
+		}
+	}
+	/// Updates and renders a single output, called from the output's render callback.
+	pub fn render_output(&mut self, screen: &mut Screen) {
+		let Some(output) = self.outputs.get(screen.get_name()).cloned() else {
+			return;
+		};
+		if let Err(e) = self.ensure_skia_context(screen) {
+			log::error!("{e:#}");
+			self.graphics_error = Some(format!("{e:#}"));
+			return;
+		}
+		let mut rmar = screen.get_rustamarine();
+		let cursor = self.outputs.clamp((rmar.get_mouse_x(), rmar.get_mouse_y()));
+		let role = match self.outputs.ui_output(CONFIG.outputs.policy, cursor) {
+			Some(ui_output) if ui_output.name == output.name => OutputRole::Ui,
+			Some(_) if CONFIG.outputs.policy == MultiMonitorPolicy::Mirror => OutputRole::Mirror,
+			_ => OutputRole::Background,
+		};
+		// Input is only handled once per frame, on the output that has the UI
+		if role == OutputRole::Ui {
+			self.update(&mut rmar, &output);
+		}
+		self.render(screen, &output, role);
+	}
+	pub fn update(&mut self, rmar: &mut rustamarine::Rustamarine, output: &Output) {
+		// Drained even while on another TTY, so old touches aren't replayed when coming back
+		let events = rmar.drain_events();
 		if !self.session_manager.is_on_tibs_tty() {
+			self.touch.reset();
 			sleep(Duration::from_millis(2));
 			return;
 		}
-		self.ensure_skia_context(screen);
-
-		// Clamp mouse position to screen boundaries
-		let screen_width = screen.get_width() as i32;
-		let screen_height = screen.get_height() as i32;
-		let mouse_x = rmar.get_mouse_x();
-		let mouse_y = rmar.get_mouse_y();
 
-		rmar.set_mouse_x(mouse_x.max(0).min(screen_width));
-		rmar.set_mouse_y(mouse_y.max(0).min(screen_height));
+		// Touches and pens move the mouse
+		if let Some(primary) = self.outputs.primary() {
+			for event in &events {
+				self.touch.handle_event(event, primary);
+			}
+		}
+		if let Some((x, y)) = self.touch.position() {
+			rmar.set_mouse_x(x);
+			rmar.set_mouse_y(y);
+		}
 
-		let progress = self.boot_progress.poll_progress();
+		// Clamp mouse position to the outputs boundaries
+		let (mouse_x, mouse_y) = self.outputs.clamp((rmar.get_mouse_x(), rmar.get_mouse_y()));
+		rmar.set_mouse_x(mouse_x);
+		rmar.set_mouse_y(mouse_y);
 
+		// Calculate delta time
 		let current_time = std::time::Instant::now();
+		let delta = current_time.duration_since(self.last_time).as_secs_f32();
+		self.last_time = current_time;
+
+		self.login_screen.keyboard_layouts.sync(rmar);
+		self.login_screen.on_screen_keyboard.sync(rmar);
+
+		// Get mouse position relative to the output with the UI
+		let mouse_position = output.to_local((mouse_x, mouse_y));
+		let input = TouchInput {
+			input: &*rmar,
+			button: self.touch.button(),
+		};
+		self.step(&input, mouse_position, delta);
+		self.touch.end_frame();
+	}
+	/// Advances the UI by `delta` seconds, `mouse_position` is relative to the output with the UI.
+	pub fn step(&mut self, input: &dyn Input, mouse_position: (f32, f32), delta: f32) {
+		let progress = self.boot_progress.poll_progress();
 
-		// Handle escape key to exit
-		if rmar.is_key_down(rustamarine::keys::KEY_Escape)
-			&& std::env::var("TIBS_DEV_MODE") == Ok("1".to_string())
+		// Handle escape key to exit, unless the login screen uses it
+		if input.is_key_pressed(rustamarine::keys::KEY_Escape)
+			&& self.dev_mode
+			&& !self.login_screen.handles_escape()
 		{
 			self.should_exit = true;
 			return;
 		}
 
-		// Toggle devtools with Caps Lock
-		if rmar.is_key_pressed(rustamarine::keys::KEY_Caps_Lock)
-			&& std::env::var("TIBS_DEV_MODE") == Ok("1".to_string())
-		{
+		if CONFIG.keybindings.pressed(input) == Some(Action::ToggleDevtools) && self.dev_mode {
 			self.devtools = !self.devtools;
 			self.clay.set_debug_mode(self.devtools);
 		}
 
-		// Calculate delta time
-		let delta = current_time.duration_since(self.last_time).as_secs_f32();
-		self.last_time = current_time;
-
-		if rmar.is_key_down(rustamarine::keys::KEY_p)
-			&& std::env::var("TIBS_DEV_MODE") == Ok("1".to_string())
-		{
+		if input.is_key_down(rustamarine::keys::KEY_p) && self.dev_mode {
 			self.login_animation.update(delta);
 		}
-		if rmar.is_key_down(rustamarine::keys::KEY_P)
-			&& std::env::var("TIBS_DEV_MODE") == Ok("1".to_string())
-		{
+		if input.is_key_down(rustamarine::keys::KEY_P) && self.dev_mode {
 			self.login_animation.update(-delta);
 		}
-		// Get mouse position
-		let mouse_position = (rmar.get_mouse_x() as f32, rmar.get_mouse_y() as f32);
 		// Update animation
 		if let Some((_, p)) = self
 			.screen_slide_animation
@@ -119,12 +249,19 @@ impl AppState<'_> {
 
 		self.login_screen.update(
 			&mut self.clay,
-			rmar,
+			input,
+			mouse_position,
 			&mut self.login_manager,
 			&self.session_manager,
 		);
 		self.loading_screen.update(&progress, delta);
 		// Update background
+		self.background.set_user_wallpaper(
+			self
+				.show_login_screen
+				.then(|| self.login_screen.selected_user_wallpaper().cloned())
+				.flatten(),
+		);
 		self.background.update(delta);
 		if self.session_manager.is_on_tibs_tty() {
 			self.login_animation_direction = LoginAnimationDirection::FadeIn;
@@ -165,21 +302,26 @@ impl AppState<'_> {
 		// Update clay pointer state
 		self.clay.pointer_state(
 			(mouse_position.0, mouse_position.1).into(),
-			rmar.is_mouse_button_down(0),
+			input.is_mouse_button_down(0),
 		);
 		// Update scroll containers
 		// captura input de scroll cru
-		let raw_scroll_x = rmar.get_mouse_scroll_x() as f32;
-		let raw_scroll_y = rmar.get_mouse_scroll_y() as f32;
+		let raw_scroll_x = input.get_mouse_scroll_x() as f32;
+		let raw_scroll_y = input.get_mouse_scroll_y() as f32;
 
 		// adiciona input à velocidade acumulada
-		if rmar.is_key_down(rustamarine::keys::KEY_Shift_L) {
+		if input.modifiers().contains(Modifiers::SHIFT) {
 			self.scroll_velocity.0 += -raw_scroll_y * 0.05;
 			self.scroll_velocity.1 += -raw_scroll_x * 0.05;
 		} else {
 			self.scroll_velocity.0 += -raw_scroll_x * 0.05;
 			self.scroll_velocity.1 += -raw_scroll_y * 0.05;
 		}
+		// The content follows the finger, clay scrolls 10 pixels per unit of velocity. It keeps the
+		// last velocity when the finger is lifted, so it slows down like the mouse wheel
+		if let Some((dx, dy)) = self.touch.drag_delta() {
+			self.scroll_velocity = (dx / 10., dy / 10.);
+		}
 
 		// aplica velocidade ao clay
 		self.clay.update_scroll_containers(
@@ -197,105 +339,137 @@ impl AppState<'_> {
 		// Hot reload assets
 		self.assets.hot_reload();
 	}
-	pub fn render(&mut self, screen: &mut Screen) {
+	pub fn render(&mut self, screen: &mut Screen, output: &Output, role: OutputRole) {
 		if !self.session_manager.is_on_tibs_tty() {
 			sleep(Duration::from_millis(2));
 			return;
 		}
-		macro_rules! skia {
-			() => {
-				self.skia.as_mut().unwrap()
-			};
-		}
-		skia!().use_screen(&mut *screen);
-		macro_rules! canvas {
-			() => {
-				skia!().canvas()
-			};
-		}
+		let mut skia = self.skia.take().unwrap();
+		skia.use_screen(&mut *screen);
 
 		let rmar = screen.get_rustamarine();
-
 		gl!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
+		self.draw(
+			skia.canvas(),
+			output,
+			role,
+			&*rmar,
+			(rmar.get_mouse_x(), rmar.get_mouse_y()),
+		);
+		skia.flush();
+		self.skia = Some(skia);
 
-		canvas!().save_layer_alpha_f(
-			Rect::new(
-				0.,
-				0.,
-				screen.get_width() as f32,
-				screen.get_height() as f32,
-			),
+		// Update FPS counter
+		if role == OutputRole::Ui {
+			if let Some(fps) = self.fps_counter.tick() {
+				println!("FPS: {:.2}", fps);
+			}
+		}
+		screen.swap_buffers();
+	}
+	/// Draws a frame of `output` on `canvas`, `cursor` is in global coordinates.
+	///
+	/// This doesn't touch OpenGL, so it can also draw on raster surfaces.
+	pub fn draw(
+		&mut self,
+		canvas: &Canvas,
+		output: &Output,
+		role: OutputRole,
+		input: &dyn Input,
+		cursor: (i32, i32),
+	) {
+		let (screen_width, screen_height) = output.size();
+
+		// Everything below is drawn in logical coordinates, rotated and scaled to the framebuffer
+		canvas.save();
+		canvas.concat(&output.matrix());
+
+		canvas.save_layer_alpha_f(
+			Rect::new(0., 0., screen_width, screen_height),
 			1.0
 				- self
 					.login_animation
 					.get_animation_progress("hide_background"),
 		);
-		self.background.render(canvas!());
-		canvas!().restore();
-		canvas!().save_layer_alpha_f(
-			None,
-			1.0 - self.login_animation.get_animation_progress("hide_ui"),
-		);
-		self.render_ui(&mut *screen);
-		canvas!().restore();
+		self
+			.background
+			.render(canvas, (screen_width, screen_height));
+		canvas.restore();
+		match role {
+			OutputRole::Ui => {
+				canvas.save_layer_alpha_f(
+					None,
+					1.0 - self.login_animation.get_animation_progress("hide_ui"),
+				);
+				self.render_ui(canvas, output, input);
+				canvas.restore();
+			}
+			OutputRole::Mirror => {
+				if let Some((picture, (ui_width, ui_height))) = &self.ui_picture {
+					canvas.save_layer_alpha_f(
+						None,
+						1.0 - self.login_animation.get_animation_progress("hide_ui"),
+					);
+					// Fit the UI of the other output inside of this one
+					let scale = (screen_width / ui_width).min(screen_height / ui_height);
+					canvas.translate((
+						(screen_width - ui_width * scale) / 2.0,
+						(screen_height - ui_height * scale) / 2.0,
+					));
+					canvas.scale((scale, scale));
+					canvas.draw_picture(picture, None, None);
+					canvas.restore();
+				}
+			}
+			OutputRole::Background => {}
+		}
 
 		let progress = self.boot_progress.poll_progress();
 
-		if progress.finished && self.login_animation.get_animation_progress("hide_ui") < 1.0 {
-			self.cursor.render(canvas!(), &rmar, "default");
+		if self.show_cursor
+			&& progress.finished
+			&& self.login_animation.get_animation_progress("hide_ui") < 1.0
+			&& output.contains(cursor)
+		{
+			self
+				.cursor
+				.render(canvas, input, output.to_local(cursor), "default");
 		}
 
-		skia!().flush();
-		// Update FPS counter
-		if let Some(fps) = self.fps_counter.tick() {
-			println!("FPS: {:.2}", fps);
-		}
-		screen.swap_buffers();
+		canvas.restore();
 		self.frame_pool.reset();
 	}
-	pub fn ensure_skia_context(&mut self, screen: &mut Screen) {
-		let (screen_width, screen_height) = (screen.get_width() as u32, screen.get_height() as u32);
-
-		if self.skia.is_none() {
-			let c = SkiaContext::init_skia(screen);
-			self
-				.clay
-				.set_layout_dimensions((screen_width as f32, screen_height as f32).into());
+	pub fn ensure_skia_context(&mut self, screen: &mut Screen) -> color_eyre::Result<()> {
+		if self.skia.is_some() {
+			return Ok(());
+		}
+		self.skia = Some(SkiaContext::init_skia(screen)?);
 
-			screen
-				.get_rustamarine()
-				.set_mouse_x(screen_width as i32 / 2);
-			screen
-				.get_rustamarine()
-				.set_mouse_y(screen_height as i32 / 2);
-			self.skia = Some(c);
-		} else if let Some(ctx) = &mut self.skia {
-			if ctx.set_size(screen_width, screen_height) {
-				self
-					.clay
-					.set_layout_dimensions((screen_width as f32, screen_height as f32).into());
-				screen
-					.get_rustamarine()
-					.set_mouse_x(screen_width as i32 / 2);
-				screen
-					.get_rustamarine()
-					.set_mouse_y(screen_height as i32 / 2);
-			}
+		// Start with the mouse in the middle of the primary output
+		if let Some(primary) = self.outputs.primary().filter(|_| self.center_mouse) {
+			let rmar = screen.get_rustamarine();
+			rmar.set_mouse_x(primary.x + primary.width as i32 / 2);
+			rmar.set_mouse_y(primary.height as i32 / 2);
+			self.center_mouse = false;
 		}
+		Ok(())
 	}
-	fn render_ui(&mut self, screen: &mut Screen) {
-		macro_rules! skia {
-			() => {
-				self.skia.as_mut().unwrap()
-			};
+	/// Drops everything that lives on the GPU before moving to another one, it's recreated on the
+	/// next frame. Images are decoded on the CPU, so they're uploaded again by the new context.
+	pub fn release_gpu(&mut self) {
+		if let Some(skia) = self.skia.take() {
+			skia.abandon();
 		}
-
-		let screen_height = screen.get_height() as f32;
+	}
+	fn render_ui(&mut self, canvas: &Canvas, output: &Output, input: &dyn Input) {
+		let (screen_width, screen_height) = output.size();
+		self
+			.clay
+			.set_layout_dimensions((screen_width, screen_height).into());
 		let mut c = self.clay.begin::<_, CustomElements>();
 		let frame_pool = self.frame_pool.begin_alloc();
 		let camera_y = self.screen_slide_animation_progress * screen_height;
 		let progress = self.boot_progress.poll_progress();
-		let rmar = screen.get_rustamarine();
 		c.with(
 			Declaration::new()
 				.layout()
@@ -307,7 +481,7 @@ impl AppState<'_> {
 			|c| {
 				let continue_anyway_button_id = c.id("loading_continue_anyway_button");
 				let continue_anyway_button_clicked =
-					c.pointer_over(continue_anyway_button_id) && rmar.is_mouse_button_released(0);
+					c.pointer_over(continue_anyway_button_id) && input.is_mouse_button_released(0);
 				if continue_anyway_button_clicked
 					|| (self.loading_screen.get_animation_progress("progress") >= 0.99
 						&& !progress.has_failed_services())
@@ -337,17 +511,33 @@ impl AppState<'_> {
 							&self.login_manager,
 							&self.session_manager,
 							&frame_pool,
-							&rmar,
+							input,
 						);
 					},
 				);
 			},
 		);
-		skia::clay_renderer::clay_skia_render(
-			skia!().canvas(),
-			c.end(),
-			CustomElements::render,
-			&FONTS,
-		);
+		if CONFIG.outputs.policy != MultiMonitorPolicy::Mirror {
+			skia::clay_renderer::clay_skia_render(canvas, c.end(), CustomElements::render, &FONTS);
+			self.render_focus_ring(canvas);
+			return;
+		}
+		// Record the UI so it can be replayed on the other outputs
+		let mut recorder = PictureRecorder::new();
+		let recording = recorder.begin_recording(Rect::from_wh(screen_width, screen_height), None);
+		skia::clay_renderer::clay_skia_render(recording, c.end(), CustomElements::render, &FONTS);
+		self.render_focus_ring(recording);
+		if let Some(picture) = recorder.finish_recording_as_picture(None) {
+			canvas.draw_picture(&picture, None, None);
+			self.ui_picture = Some((picture, (screen_width, screen_height)));
+		}
+	}
+	fn render_focus_ring(&self, canvas: &Canvas) {
+		if !self.show_login_screen {
+			return;
+		}
+		if let Some(ring) = self.login_screen.focus.ring(&self.clay) {
+			skia::clay_renderer::draw_focus_ring(canvas, ring);
+		}
 	}
 }
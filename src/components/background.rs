@@ -1,22 +1,197 @@
-use std::{collections::HashMap, rc::Rc, sync::mpsc::Sender};
+use std::{collections::HashMap, path::Path, rc::Rc, str::FromStr};
 
 use assets_manager::AssetCache;
 use rand::Rng;
-use skia_safe::{Canvas, Paint, Rect, RuntimeEffect};
+use skia_safe::{
+	image_filters, Canvas, Data, Image, Paint, Rect, RuntimeEffect, SamplingOptions, TileMode,
+};
 
 use crate::{
 	all,
-	animation::{
-		self,
-		colors::{self, interpolate_color_normalized},
-		easing, Animation, BasicAnimation,
-	},
-	login::{LoginManager, LoginScreen, LoginState},
-	skia::asset_loaders::SkiaShaderAsset,
+	animation::{colors, easing, Animation, BasicAnimation},
+	config::CONFIG,
+	skia::{animated_image::AnimatedImage, asset_loaders::SkiaShaderAsset},
 };
 
+/// How an image background is fitted to the screen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFit {
+	/// Scale the image to fill the whole screen, cropping what doesn't fit
+	#[default]
+	Cover,
+	/// Scale the image so it's fully visible, leaving bars on the sides
+	Contain,
+	/// Repeat the image at its original size
+	Tile,
+}
+
+impl FromStr for ImageFit {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"cover" => Ok(Self::Cover),
+			"contain" => Ok(Self::Contain),
+			"tile" => Ok(Self::Tile),
+			_ => Err(()),
+		}
+	}
+}
+
+impl ImageFit {
+	/// Destination rectangle for an image of `image_size` drawn on a screen of `screen_size`.
+	///
+	/// Not meaningful for [`ImageFit::Tile`], which draws the image using a shader instead.
+	pub fn dest_rect(self, image_size: (f32, f32), screen_size: (f32, f32)) -> Rect {
+		let scale_x = screen_size.0 / image_size.0;
+		let scale_y = screen_size.1 / image_size.1;
+		let scale = match self {
+			ImageFit::Cover => scale_x.max(scale_y),
+			ImageFit::Contain => scale_x.min(scale_y),
+			ImageFit::Tile => 1.0,
+		};
+		let (width, height) = (image_size.0 * scale, image_size.1 * scale);
+		Rect::from_xywh(
+			(screen_size.0 - width) / 2.0,
+			(screen_size.1 - height) / 2.0,
+			width,
+			height,
+		)
+	}
+}
+
+/// Crossfades from the previous image to the current one whenever the image changes.
+pub struct ImageCrossfade {
+	current: Option<Image>,
+	previous: Option<Image>,
+	animation: BasicAnimation,
+	progress: f32,
+}
+
+impl ImageCrossfade {
+	pub fn new(duration: f32) -> Self {
+		Self {
+			current: None,
+			previous: None,
+			animation: BasicAnimation::new("crossfade", duration.max(0.001), easing::ease_in_out_quad),
+			progress: 0.0,
+		}
+	}
+	pub fn set(&mut self, image: Option<Image>) {
+		let current_id = self.current.as_ref().map(|i| i.unique_id());
+		if current_id == image.as_ref().map(|i| i.unique_id()) {
+			return;
+		}
+		self.previous = self.current.take();
+		self.current = image;
+		self.animation.reset();
+		self.progress = 0.0;
+	}
+	pub fn update(&mut self, delta: f32) {
+		if let Some((_, p)) = self.animation.update(delta).first() {
+			self.progress = *p;
+		}
+		if self.animation.is_finished() {
+			self.previous = None;
+		}
+	}
+	pub fn is_visible(&self) -> bool {
+		self.current.is_some() || self.previous.is_some()
+	}
+	pub fn render(&self, canvas: &Canvas, screen_size: (f32, f32), fit: ImageFit, blur: f32) {
+		if let Some(previous) = &self.previous {
+			// Only fade the old image out if there's nothing to fade into, otherwise whatever is
+			// under the background would shine through halfway through the transition.
+			let alpha = if self.current.is_some() {
+				1.0
+			} else {
+				1.0 - self.progress
+			};
+			draw_image_fitted(canvas, screen_size, previous, fit, blur, alpha);
+		}
+		if let Some(current) = &self.current {
+			draw_image_fitted(canvas, screen_size, current, fit, blur, self.progress);
+		}
+	}
+}
+
+pub struct Slideshow {
+	images: Vec<Image>,
+	current_image: usize,
+	elapsed: f32,
+	interval: f32,
+	crossfade: ImageCrossfade,
+}
+
+impl Slideshow {
+	pub fn new(images: Vec<Image>, interval: f32, crossfade_duration: f32) -> Self {
+		let mut crossfade = ImageCrossfade::new(crossfade_duration);
+		crossfade.set(images.first().cloned());
+		Self {
+			images,
+			current_image: 0,
+			elapsed: 0.0,
+			interval,
+			crossfade,
+		}
+	}
+	pub fn update(&mut self, delta: f32) {
+		self.elapsed += delta;
+		if self.elapsed >= self.interval && self.images.len() > 1 {
+			self.elapsed = 0.0;
+			self.current_image = (self.current_image + 1) % self.images.len();
+			self
+				.crossfade
+				.set(Some(self.images[self.current_image].clone()));
+		}
+		self.crossfade.update(delta);
+	}
+}
+
+pub enum BackgroundSource {
+	/// The default animated mesh gradient (`shaders.background.meshgradient`)
+	MeshGradient,
+	Image(Image),
+	Slideshow(Slideshow),
+	Animated(AnimatedImage),
+}
+
+impl BackgroundSource {
+	/// Loads a background from an image file, an animated image or a folder of images.
+	pub fn load(path: &Path) -> Option<Self> {
+		if path.is_dir() {
+			let mut entries = std::fs::read_dir(path)
+				.ok()?
+				.filter_map(Result::ok)
+				.map(|e| e.path())
+				.collect::<Vec<_>>();
+			entries.sort();
+			let mut images = entries
+				.iter()
+				.filter_map(|p| Image::from_encoded(Data::from_filename(p)?))
+				.collect::<Vec<_>>();
+			return match images.len() {
+				0 => None,
+				1 => images.pop().map(Self::Image),
+				_ => Some(Self::Slideshow(Slideshow::new(
+					images,
+					CONFIG.background.slideshow_interval,
+					CONFIG.background.crossfade_duration,
+				))),
+			};
+		}
+		let data = Data::from_filename(path)?;
+		if let Some(animated) = AnimatedImage::from_data(data.clone()) {
+			return Some(Self::Animated(animated));
+		}
+		Image::from_encoded(data).map(Self::Image)
+	}
+}
+
 pub struct Background {
 	assets: Rc<AssetCache>,
+	source: BackgroundSource,
+	user_wallpaper: ImageCrossfade,
 	animations_state: HashMap<String, f32>,
 	elapsed_time: f32,
 	pub time_offset: f32,
@@ -27,20 +202,39 @@ impl Background {
 		fn rd() -> f32 {
 			rand::rng().random_range(0.4..7.0)
 		}
+		let source = match &CONFIG.background.source {
+			Some(path) => BackgroundSource::load(path).unwrap_or_else(|| {
+				log::warn!(
+					"Failed to load background from {}, falling back to the mesh gradient",
+					path.display()
+				);
+				BackgroundSource::MeshGradient
+			}),
+			None => BackgroundSource::MeshGradient,
+		};
 		Self {
 			assets,
+			source,
+			user_wallpaper: ImageCrossfade::new(CONFIG.background.crossfade_duration),
 			animations_state: HashMap::new(),
 			fade_in_animation: Box::new(all!(
 				BasicAnimation::new("color_0", rd(), easing::ease_out_quad),
 				BasicAnimation::new("color_1", rd(), easing::ease_out_quad),
 				BasicAnimation::new("color_2", rd(), easing::ease_out_quad),
 				BasicAnimation::new("color_3", rd(), easing::ease_out_quad),
-				BasicAnimation::new("color_4", rd(), easing::ease_out_quad)
+				BasicAnimation::new("color_4", rd(), easing::ease_out_quad),
+				BasicAnimation::new("image", 1.0, easing::ease_out_quad)
 			)),
 			elapsed_time: 0.0,
 			time_offset: 0.0,
 		}
 	}
+	/// Sets the wallpaper drawn on top of the configured background, crossfading to it.
+	///
+	/// Pass `None` to fade back to the configured background.
+	pub fn set_user_wallpaper(&mut self, wallpaper: Option<Image>) {
+		self.user_wallpaper.set(wallpaper);
+	}
 	pub fn update(&mut self, delta: f32) {
 		// Incrementa o tempo com base na velocidade
 		self.elapsed_time += delta;
@@ -49,17 +243,50 @@ impl Background {
 		self
 			.animations_state
 			.extend(self.fade_in_animation.update(delta));
+
+		match &mut self.source {
+			BackgroundSource::Slideshow(slideshow) => slideshow.update(delta),
+			BackgroundSource::Animated(animated) => animated.update(delta),
+			BackgroundSource::MeshGradient | BackgroundSource::Image(_) => {}
+		}
+		self.user_wallpaper.update(delta);
+	}
+	/// Renders the background, `screen_size` is the logical size of the output.
+	pub fn render(&self, canvas: &Canvas, screen_size: (f32, f32)) {
+		let fit = CONFIG.background.fit;
+		let blur = CONFIG.background.blur;
+		match &self.source {
+			BackgroundSource::MeshGradient => self.render_mesh_gradient(canvas, screen_size),
+			BackgroundSource::Image(image) => draw_image_fitted(
+				canvas,
+				screen_size,
+				image,
+				fit,
+				blur,
+				self.get_animation_progress("image"),
+			),
+			BackgroundSource::Slideshow(slideshow) => {
+				slideshow.crossfade.render(canvas, screen_size, fit, blur)
+			}
+			BackgroundSource::Animated(animated) => draw_image_fitted(
+				canvas,
+				screen_size,
+				animated.current_frame(),
+				fit,
+				blur,
+				self.get_animation_progress("image"),
+			),
+		}
+		if self.user_wallpaper.is_visible() {
+			self.user_wallpaper.render(canvas, screen_size, fit, blur);
+		}
 	}
-	pub fn render(&self, canvas: &Canvas) {
+	fn render_mesh_gradient(&self, canvas: &Canvas, screen_size: (f32, f32)) {
 		let light_shader = self
 			.assets
 			.load::<SkiaShaderAsset>("shaders.background.meshgradient")
 			.unwrap()
 			.read();
-		let screen_size = (
-			unsafe { canvas.surface() }.unwrap().width() as f32,
-			unsafe { canvas.surface() }.unwrap().height() as f32,
-		);
 		#[repr(C)]
 		struct LightShaderUniforms {
 			screen_size: (f32, f32),
@@ -67,7 +294,6 @@ impl Background {
 			colors: [(f32, f32, f32); 5],
 			forces: [f32; 5],
 		}
-		let success_animation = self.get_animation_progress("success_animation");
 		let target_colors = [
 			colors::rgb_to_norm("#0F1419"),
 			colors::rgb_to_norm("#225282"),
@@ -100,21 +326,22 @@ impl Background {
 			],
 		};
 
-		self.render_shader(&light_shader, &uniforms, canvas);
+		self.render_shader(&light_shader, &uniforms, canvas, screen_size);
 	}
-	fn render_shader<T>(&self, shader: &RuntimeEffect, uniforms: &T, canvas: &Canvas) {
+	fn render_shader<T>(
+		&self,
+		shader: &RuntimeEffect,
+		uniforms: &T,
+		canvas: &Canvas,
+		screen_size: (f32, f32),
+	) {
 		let uniforms_as_bytes = unsafe {
 			std::slice::from_raw_parts::<u8>((uniforms as *const T) as *const u8, size_of_val(uniforms))
 		};
 		let uniforms_data = skia_safe::Data::new_copy(uniforms_as_bytes);
 		let shader = shader.make_shader(uniforms_data, &[], None).unwrap();
 		canvas.draw_rect(
-			Rect::new(
-				0.,
-				0.,
-				unsafe { canvas.surface() }.unwrap().width() as f32,
-				unsafe { canvas.surface() }.unwrap().height() as f32,
-			),
+			Rect::new(0., 0., screen_size.0, screen_size.1),
 			Paint::default().set_shader(shader),
 		);
 	}
@@ -123,3 +350,67 @@ impl Background {
 		self.animations_state.get(id).copied().unwrap_or(0.0)
 	}
 }
+
+fn draw_image_fitted(
+	canvas: &Canvas,
+	screen_size: (f32, f32),
+	image: &Image,
+	fit: ImageFit,
+	blur: f32,
+	alpha: f32,
+) {
+	if alpha <= 0.0 {
+		return;
+	}
+	let sampling = SamplingOptions::new(skia_safe::FilterMode::Linear, skia_safe::MipmapMode::Linear);
+	let mut paint = Paint::default();
+	paint.set_anti_alias(true);
+	paint.set_alpha_f(alpha);
+	if blur > 0.0 {
+		paint.set_image_filter(image_filters::blur(
+			(blur, blur),
+			TileMode::Clamp,
+			None,
+			None,
+		));
+	}
+	match fit {
+		ImageFit::Tile => {
+			paint.set_shader(image.to_shader((TileMode::Repeat, TileMode::Repeat), sampling, None));
+			canvas.draw_rect(Rect::from_wh(screen_size.0, screen_size.1), &paint);
+		}
+		ImageFit::Cover | ImageFit::Contain => {
+			let image_size = (image.width() as f32, image.height() as f32);
+			canvas.draw_image_rect_with_sampling_options(
+				image,
+				None,
+				fit.dest_rect(image_size, screen_size),
+				sampling,
+				&paint,
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cover_fills_the_screen() {
+		let rect = ImageFit::Cover.dest_rect((1000.0, 1000.0), (1920.0, 1080.0));
+		assert_eq!(rect, Rect::from_xywh(0.0, -420.0, 1920.0, 1920.0));
+	}
+
+	#[test]
+	fn contain_fits_inside_the_screen() {
+		let rect = ImageFit::Contain.dest_rect((1000.0, 1000.0), (1920.0, 1080.0));
+		assert_eq!(rect, Rect::from_xywh(420.0, 0.0, 1080.0, 1080.0));
+	}
+
+	#[test]
+	fn parse_image_fit() {
+		assert_eq!("tile".parse(), Ok(ImageFit::Tile));
+		assert_eq!("".parse::<ImageFit>(), Err(()));
+	}
+}
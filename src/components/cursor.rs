@@ -1,9 +1,9 @@
 use std::{collections::HashMap, ffi::CString};
 
+use crate::input::Input;
 use cairo::{Format, ImageSurface};
 use hyprcursor::{CursorStyleInfo, HyprCursorManager};
 use libhyprcursor_sys::hyprcursor_cursor_image_data_free;
-use rustamarine::Rustamarine;
 use skia_safe::{self, images, Image, ImageInfo, Paint, Point, Rect, SamplingOptions};
 struct CursorVariation {
 	image: Image,
@@ -11,27 +11,53 @@ struct CursorVariation {
 }
 pub struct Cursor {
 	cursors: HashMap<String, CursorVariation>,
+	/// Size in logical pixels
 	cursor_size: u32,
+	/// The cursor images are rasterized at `cursor_size * scale` pixels
+	scale: f32,
 	style_info: CursorStyleInfo,
 	cursor_manager: HyprCursorManager,
 }
 
 impl Cursor {
-	pub fn new(cursor_size: impl Into<Option<u32>>) -> Self {
+	pub fn new(cursor_size: impl Into<Option<u32>>, scale: f32) -> Self {
 		let cursor_size = cursor_size.into().unwrap_or(24);
-		log::debug!("Initializing Cursor with size {}.", cursor_size);
+		log::debug!(
+			"Initializing Cursor with size {} and scale {}.",
+			cursor_size,
+			scale
+		);
 		let manager = HyprCursorManager::new(Some(c""));
-		let style_info = manager.new_style_info(cursor_size);
+		let style_info = manager.new_style_info(Self::pixel_size(cursor_size, scale));
 		manager.load_theme_style(&style_info);
 		log::debug!("Loaded theme style for cursor.");
 		Self {
 			cursors: HashMap::new(),
 			cursor_size,
+			scale,
 			style_info,
 			cursor_manager: manager,
 		}
 	}
 
+	fn pixel_size(cursor_size: u32, scale: f32) -> u32 {
+		(cursor_size as f32 * scale).round() as u32
+	}
+
+	/// Rasterizes the cursors again for a new scale.
+	pub fn set_scale(&mut self, scale: f32) {
+		if self.scale == scale {
+			return;
+		}
+		log::debug!("Changing cursor scale to {}.", scale);
+		self.scale = scale;
+		self.style_info = self
+			.cursor_manager
+			.new_style_info(Self::pixel_size(self.cursor_size, scale));
+		self.cursor_manager.load_theme_style(&self.style_info);
+		self.cursors.clear();
+	}
+
 	fn load_cursor(&mut self, cursor_name: &str) {
 		log::debug!("Attempting to load cursor: {}", cursor_name);
 		let image = if self.cursor_manager.is_theme_valid() {
@@ -50,8 +76,8 @@ impl Cursor {
 			let surface = data[0].surface();
 
 			// Get the width and height of the surface
-			let width = self.cursor_size;
-			let height = self.cursor_size;
+			let width = Self::pixel_size(self.cursor_size, self.scale);
+			let height = width;
 
 			println!(
 				"[DEBUG] Creating new Cairo ImageSurface with dimensions: {}x{}",
@@ -123,15 +149,28 @@ impl Cursor {
 		self.cursors.get(cursor_name)
 	}
 
-	pub fn render(&mut self, skia_canvas: &skia_safe::Canvas, rmar: &Rustamarine, cursor_name: &str) {
-		let (mx, my) = (rmar.get_mouse_x() as f32, rmar.get_mouse_y() as f32);
+	/// Draws the cursor at `position`, relative to the canvas of the output it's on.
+	pub fn render(
+		&mut self,
+		skia_canvas: &skia_safe::Canvas,
+		input: &dyn Input,
+		(mx, my): (f32, f32),
+		cursor_name: &str,
+	) {
+		let scale = self.scale;
 		if let Some(CursorVariation {
 			image,
 			hotspot: (hx, hy),
 		}) = self.get_or_load_cursor(cursor_name)
 		{
-			let pos = Point::new(mx - *hx as f32, my - *hy as f32);
-			let dest_rect = Rect::from_xywh(pos.x, pos.y, image.width() as f32, image.height() as f32);
+			// The image and the hotspot are in pixels, the canvas is in logical coordinates
+			let pos = Point::new(mx - *hx as f32 / scale, my - *hy as f32 / scale);
+			let dest_rect = Rect::from_xywh(
+				pos.x,
+				pos.y,
+				image.width() as f32 / scale,
+				image.height() as f32 / scale,
+			);
 			skia_canvas.draw_image_rect_with_sampling_options(
 				image,
 				None,
@@ -143,7 +182,7 @@ impl Cursor {
 			let pos = Point::new(mx, my);
 			log::debug!("Fallback rendering for cursor '{}'.", cursor_name);
 			// Fallback: draw a circle.
-			let cursor_radius = if rmar.is_mouse_button_down(0) {
+			let cursor_radius = if input.is_mouse_button_down(0) {
 				5.0
 			} else {
 				10.0
@@ -4,10 +4,67 @@ use clay_layout::{
 	math::BoundingBox,
 	render_commands::{Custom, RenderCommand},
 };
-use skia_safe::{Canvas, Color, Image, Paint, PaintCap, PaintStyle, Rect};
+use skia_safe::{Canvas, Color, Image, Paint, PaintCap, PaintJoin, PaintStyle, Path, Rect};
 #[derive(Debug, Clone)]
 pub enum CustomElements {
 	Spinner,
+	KeyIcon(KeyIcon),
+}
+
+/// Icons of the special keys of the on-screen keyboard, drawn as lines so they follow the size of
+/// the key.
+#[derive(Debug, Clone, Copy)]
+pub enum KeyIcon {
+	Backspace,
+	Enter,
+	Shift,
+	/// Shift is active for the next key
+	ShiftActive,
+	Keyboard,
+}
+
+impl KeyIcon {
+	/// SVG paths on a 24x24 grid
+	fn svg_paths(self) -> &'static [&'static str] {
+		match self {
+			Self::Backspace => &[
+				"M10 5a2 2 0 0 0-1.344.519l-6.328 5.74a1 1 0 0 0 0 1.481l6.328 5.741A2 2 0 0 0 10 19h10a2 2 0 0 0 2-2V7a2 2 0 0 0-2-2z",
+				"M12 9l6 6",
+				"M18 9l-6 6",
+			],
+			Self::Enter => &["M20 4v7a4 4 0 0 1-4 4H4", "M9 10l-5 5 5 5"],
+			Self::Shift | Self::ShiftActive => &[
+				"M9 18v-6H5l7-7 7 7h-4v6H9z",
+			],
+			Self::Keyboard => &[
+				"M4 4h16a2 2 0 0 1 2 2v12a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V6a2 2 0 0 1 2-2z",
+				"M6 8h.01M10 8h.01M14 8h.01M18 8h.01M8 12h.01M12 12h.01M16 12h.01",
+				"M7 16h10",
+			],
+		}
+	}
+
+	fn render(self, bounding_box: BoundingBox, canvas: &Canvas) {
+		let mut paint = Paint::default();
+		paint.set_anti_alias(true);
+		paint.set_color(Color::WHITE);
+		paint.set_stroke_width(2.0);
+		paint.set_style(if matches!(self, Self::ShiftActive) {
+			PaintStyle::StrokeAndFill
+		} else {
+			PaintStyle::Stroke
+		});
+		paint.set_stroke_cap(PaintCap::Round);
+		paint.set_stroke_join(PaintJoin::Round);
+
+		canvas.save();
+		canvas.translate((bounding_box.x, bounding_box.y));
+		canvas.scale((bounding_box.width / 24.0, bounding_box.height / 24.0));
+		for path in self.svg_paths().iter().filter_map(Path::from_svg) {
+			canvas.draw_path(&path, &paint);
+		}
+		canvas.restore();
+	}
 }
 
 impl CustomElements {
@@ -48,6 +105,7 @@ impl CustomElements {
 
 				canvas.draw_arc(oval, start_angle, sweep_angle, use_center, &paint);
 			}
+			CustomElements::KeyIcon(icon) => icon.render(command.bounding_box, canvas),
 		}
 	}
 }
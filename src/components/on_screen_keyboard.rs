@@ -0,0 +1,387 @@
+//! Keyboard drawn on the screen, for touchscreens without a keyboard and for people who can't use
+//! a physical one.
+//!
+//! The keys are identified by their evdev keycode and labeled with what they type in the active
+//! xkb layout, so they match the physical keyboard. The keys clicked during a frame are handed to
+//! the rest of the UI through [`OnScreenKeyboard::input`], the focused [`Textbox`] gets them from
+//! `get_typed_characters` like the ones typed on a physical keyboard.
+//!
+//! [`Textbox`]: crate::textbox::Textbox
+use std::collections::HashMap;
+
+use clay_layout::{
+	elements::{FloatingAttachPointType, FloatingAttachToElement},
+	fixed,
+	layout::{Alignment, LayoutAlignmentX as LX, LayoutAlignmentY as LY, LayoutDirection, Padding},
+	text::TextConfig,
+	Clay, Declaration,
+};
+use rustamarine::{events::Modifiers, keymap::keysym_to_text, keys, Rustamarine};
+
+use crate::{
+	animation::colors::hsl_to_rgb,
+	custom_elements::{CustomElements, KeyIcon},
+	input::Input,
+	skia::fonts::Fonts,
+	TibsClayScope,
+};
+
+/// Keycodes of the keys that type characters, by row
+const CHARACTER_ROWS: [std::ops::RangeInclusive<u32>; 4] = [2..=13, 16..=27, 30..=40, 44..=53];
+/// What the character keys type in the US layout, used until rustamarine describes the active one
+const US_LABELS: [(&str, &str); 4] = [
+	("1234567890-=", "!@#$%^&*()_+"),
+	("qwertyuiop[]", "QWERTYUIOP{}"),
+	("asdfghjkl;'", "ASDFGHJKL:\""),
+	("zxcvbnm,./", "ZXCVBNM<>?"),
+];
+const KEY_SIZE: f32 = 48.0;
+const KEY_GAP: u16 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpecialKey {
+	Backspace,
+	Enter,
+	Shift,
+	Space,
+	Hide,
+}
+
+impl SpecialKey {
+	fn id(self) -> &'static str {
+		match self {
+			Self::Backspace => "on-screen-keyboard-backspace",
+			Self::Enter => "on-screen-keyboard-enter",
+			Self::Shift => "on-screen-keyboard-shift",
+			Self::Space => "on-screen-keyboard-space",
+			Self::Hide => "on-screen-keyboard-hide",
+		}
+	}
+
+	/// Width in keys
+	fn width(self) -> f32 {
+		match self {
+			Self::Space => 6.0,
+			_ => 1.5,
+		}
+	}
+}
+
+pub struct OnScreenKeyboard {
+	pub visible: bool,
+	/// Applies to the next character key, like a latched Shift
+	shift: bool,
+	/// What each character key types, without and with Shift
+	labels: HashMap<u32, [String; 2]>,
+	/// Layout the labels were read for
+	labels_layout: Option<usize>,
+	/// Typed with the keys clicked this frame
+	typed_characters: String,
+	pressed_key: Option<u32>,
+}
+
+impl Default for OnScreenKeyboard {
+	fn default() -> Self {
+		let labels = CHARACTER_ROWS
+			.into_iter()
+			.zip(US_LABELS)
+			.flat_map(|(keycodes, (labels, shifted_labels))| {
+				keycodes
+					.zip(labels.chars().zip(shifted_labels.chars()))
+					.map(|(keycode, (label, shifted))| (keycode, [label.into(), shifted.into()]))
+			})
+			.collect();
+		Self {
+			visible: false,
+			shift: false,
+			labels,
+			labels_layout: None,
+			typed_characters: String::new(),
+			pressed_key: None,
+		}
+	}
+}
+
+impl OnScreenKeyboard {
+	/// Reads the labels of the keys again when the active layout changes.
+	pub fn sync(&mut self, rmar: &Rustamarine) {
+		let layout = rmar.active_keyboard_layout();
+		if self.labels_layout == Some(layout) {
+			return;
+		}
+		self.labels_layout = Some(layout);
+		for keycode in CHARACTER_ROWS.into_iter().flatten() {
+			let label = |shift| {
+				rmar
+					.key_keysym(keycode, shift)
+					.and_then(keysym_to_text)
+					.unwrap_or_default()
+			};
+			self.labels.insert(keycode, [label(false), label(true)]);
+		}
+	}
+
+	fn label(&self, keycode: u32) -> &str {
+		self
+			.labels
+			.get(&keycode)
+			.map_or("", |labels| &labels[self.shift as usize])
+	}
+
+	pub fn update(&mut self, c: &Clay, input: &dyn Input) {
+		self.typed_characters.clear();
+		self.pressed_key = None;
+		if c.pointer_over(c.id("on-screen-keyboard-toggle")) && input.is_mouse_button_released(0) {
+			self.visible = !self.visible;
+		}
+		if !self.visible || !input.is_mouse_button_released(0) {
+			return;
+		}
+		if let Some(keycode) = CHARACTER_ROWS
+			.into_iter()
+			.flatten()
+			.find(|&keycode| c.pointer_over(c.id_index("on-screen-keyboard-key", keycode)))
+		{
+			self.typed_characters = self.label(keycode).to_string();
+			self.shift = false;
+			return;
+		}
+		let special_keys = [
+			SpecialKey::Backspace,
+			SpecialKey::Enter,
+			SpecialKey::Shift,
+			SpecialKey::Space,
+			SpecialKey::Hide,
+		];
+		match special_keys
+			.into_iter()
+			.find(|key| c.pointer_over(c.id(key.id())))
+		{
+			Some(SpecialKey::Backspace) => self.pressed_key = Some(keys::KEY_BackSpace),
+			Some(SpecialKey::Enter) => self.pressed_key = Some(keys::KEY_Return),
+			Some(SpecialKey::Shift) => self.shift = !self.shift,
+			Some(SpecialKey::Space) => self.typed_characters = " ".into(),
+			Some(SpecialKey::Hide) => self.visible = false,
+			None => {}
+		}
+	}
+
+	/// `input` with the keys clicked this frame pressed and typed.
+	pub fn input<'a>(&self, input: &'a dyn Input) -> OnScreenKeyboardInput<'a> {
+		OnScreenKeyboardInput {
+			input,
+			typed_characters: self.typed_characters.clone(),
+			pressed_key: self.pressed_key,
+		}
+	}
+
+	pub fn render<'clay, 'render>(
+		&'render self,
+		c: &mut TibsClayScope<'clay, 'render>,
+		input: &dyn Input,
+	) where
+		'clay: 'render,
+	{
+		c.with(
+			Declaration::new()
+				.id(c.id("on-screen-keyboard"))
+				.background_color(hsl_to_rgb(230., 27.6, 10.2).into())
+				.corner_radius()
+				.all(14.)
+				.end()
+				.floating()
+				.attach_to(FloatingAttachToElement::Parent)
+				.attach_points(
+					FloatingAttachPointType::CenterBottom,
+					FloatingAttachPointType::CenterBottom,
+				)
+				.offset((0.0, -24.0).into())
+				.end()
+				.layout()
+				.direction(LayoutDirection::TopToBottom)
+				.child_alignment(Alignment::new(LX::Center, LY::Top))
+				.child_gap(KEY_GAP)
+				.padding(Padding::all(12))
+				.end(),
+			|c| {
+				for (row, keycodes) in CHARACTER_ROWS.into_iter().enumerate() {
+					Self::row(c, |c| {
+						if row == 3 {
+							self.render_special_key(c, SpecialKey::Shift, input);
+						}
+						for keycode in keycodes {
+							Self::render_key(
+								c,
+								"on-screen-keyboard-key",
+								Some(keycode),
+								1.0,
+								input,
+								|c| {
+									c.text(
+										self.label(keycode),
+										TextConfig::new()
+											.color((0xFF, 0xFF, 0xFF).into())
+											.font_size(18)
+											.font_id(Fonts::MEDIUM)
+											.end(),
+									);
+								},
+							);
+						}
+						match row {
+							0 => self.render_special_key(c, SpecialKey::Backspace, input),
+							2 => self.render_special_key(c, SpecialKey::Enter, input),
+							_ => {}
+						}
+					});
+				}
+				Self::row(c, |c| {
+					self.render_special_key(c, SpecialKey::Space, input);
+					self.render_special_key(c, SpecialKey::Hide, input);
+				});
+			},
+		);
+	}
+
+	fn row<'clay, 'render>(
+		c: &mut TibsClayScope<'clay, 'render>,
+		f: impl FnOnce(&mut TibsClayScope<'clay, 'render>),
+	) where
+		'clay: 'render,
+	{
+		c.with(
+			Declaration::new()
+				.layout()
+				.child_gap(KEY_GAP)
+				.child_alignment(Alignment::new(LX::Center, LY::Center))
+				.end(),
+			f,
+		);
+	}
+
+	fn render_special_key<'clay, 'render>(
+		&'render self,
+		c: &mut TibsClayScope<'clay, 'render>,
+		key: SpecialKey,
+		input: &dyn Input,
+	) where
+		'clay: 'render,
+	{
+		let icon: &'static CustomElements = match key {
+			SpecialKey::Backspace => &CustomElements::KeyIcon(KeyIcon::Backspace),
+			SpecialKey::Enter => &CustomElements::KeyIcon(KeyIcon::Enter),
+			SpecialKey::Shift if self.shift => &CustomElements::KeyIcon(KeyIcon::ShiftActive),
+			SpecialKey::Shift => &CustomElements::KeyIcon(KeyIcon::Shift),
+			SpecialKey::Hide => &CustomElements::KeyIcon(KeyIcon::Keyboard),
+			SpecialKey::Space => {
+				Self::render_key(c, key.id(), None, key.width(), input, |_| {});
+				return;
+			}
+		};
+		Self::render_key(c, key.id(), None, key.width(), input, |c| {
+			c.with(
+				Declaration::new()
+					.layout()
+					.width(fixed!(22.0))
+					.height(fixed!(22.0))
+					.end()
+					.custom_element(icon),
+				|_| {},
+			);
+		});
+	}
+
+	/// `width` is in keys, wider keys also take the gaps they replace.
+	fn render_key<'clay, 'render>(
+		c: &mut TibsClayScope<'clay, 'render>,
+		id: &'static str,
+		index: Option<u32>,
+		width: f32,
+		input: &dyn Input,
+		content: impl FnOnce(&mut TibsClayScope<'clay, 'render>),
+	) where
+		'clay: 'render,
+	{
+		c.with_styling(
+			|c| {
+				let mut d = Declaration::new();
+				d.id(match index {
+					Some(index) => c.id_index(id, index),
+					None => c.id(id),
+				})
+				.layout()
+				.child_alignment(Alignment::new(LX::Center, LY::Center))
+				.width(fixed!(
+					KEY_SIZE * width + KEY_GAP as f32 * (width - 1.0).ceil()
+				))
+				.height(fixed!(KEY_SIZE))
+				.end()
+				.background_color((0xFF, 0xFF, 0xFF, 0x14).into())
+				.corner_radius()
+				.all(8.0)
+				.end();
+				if c.hovered() {
+					d.background_color((0xFF, 0xFF, 0xFF, 0x24).into());
+					if input.is_mouse_button_down(0) {
+						d.background_color((0xFF, 0xFF, 0xFF, 0x34).into());
+					}
+				}
+				d
+			},
+			content,
+		);
+	}
+}
+
+/// Input with the keys of the on-screen keyboard, see [`OnScreenKeyboard::input`].
+pub struct OnScreenKeyboardInput<'a> {
+	input: &'a dyn Input,
+	typed_characters: String,
+	pressed_key: Option<u32>,
+}
+
+impl Input for OnScreenKeyboardInput<'_> {
+	fn is_key_down(&self, key: u32) -> bool {
+		self.input.is_key_down(key) || self.pressed_key == Some(key)
+	}
+	fn is_key_pressed(&self, key: u32) -> bool {
+		self.input.is_key_pressed(key) || self.pressed_key == Some(key)
+	}
+	fn is_mouse_button_down(&self, button: u32) -> bool {
+		self.input.is_mouse_button_down(button)
+	}
+	fn is_mouse_button_pressed(&self, button: u32) -> bool {
+		self.input.is_mouse_button_pressed(button)
+	}
+	fn is_mouse_button_released(&self, button: u32) -> bool {
+		self.input.is_mouse_button_released(button)
+	}
+	fn get_mouse_scroll_x(&self) -> f64 {
+		self.input.get_mouse_scroll_x()
+	}
+	fn get_mouse_scroll_y(&self) -> f64 {
+		self.input.get_mouse_scroll_y()
+	}
+	fn get_typed_characters(&self) -> String {
+		self.input.get_typed_characters() + &self.typed_characters
+	}
+	fn modifiers(&self) -> Modifiers {
+		self.input.modifiers()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn us_labels_until_synced() {
+		let mut keyboard = OnScreenKeyboard::default();
+		assert_eq!(keyboard.label(16), "q");
+		assert_eq!(keyboard.label(40), "'");
+		assert_eq!(keyboard.label(53), "/");
+		keyboard.shift = true;
+		assert_eq!(keyboard.label(2), "!");
+		assert_eq!(keyboard.label(1), "");
+	}
+}
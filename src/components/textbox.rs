@@ -1,19 +1,29 @@
+use crate::config::CONFIG;
+use crate::input::Input;
+use crate::keybindings::Action;
 use crate::{skia::clay_renderer::create_measure_text_function, TibsClayScope, FONTS};
 use clay_layout::{
+	elements::{FloatingAttachPointType, FloatingAttachToElement},
 	fixed, grow,
 	layout::{Alignment, Padding},
 	text::TextConfig,
 	Clay, Declaration,
 };
-use rustamarine::keys;
-use rustamarine::Rustamarine;
+use rustamarine::{events::Modifiers, keys};
+use skia_safe::Image;
 
 pub struct Textbox {
 	buffer: String,
 	cursor: usize,
 	focused: bool,
 	censored_buffer: String,
+	/// Set with select all, the next edit replaces the whole text
+	selected_all: bool,
 	id: String,
+	/// Caps Lock was on during the last update
+	caps_lock: bool,
+	/// Shown while Caps Lock is on and the input is hidden
+	caps_lock_icon: Option<Image>,
 	pub hide_input: bool,
 	pub disabled: bool,
 }
@@ -25,11 +35,18 @@ impl Textbox {
 			cursor: 0,
 			focused: true,
 			censored_buffer: String::new(),
+			selected_all: false,
 			id: id.into(),
+			caps_lock: false,
+			caps_lock_icon: None,
 			hide_input,
 			disabled: false,
 		}
 	}
+	pub fn with_caps_lock_icon(mut self, icon: Image) -> Self {
+		self.caps_lock_icon = Some(icon);
+		self
+	}
 	fn chars_count(s: &str) -> usize {
 		s.chars().count()
 	}
@@ -47,6 +64,63 @@ impl Textbox {
 			.map(|(i, _)| i)
 			.unwrap_or_else(|| Self::char_index_to_byte_index(s, chars_count - 1))
 	}
+	/// Char index of the start of the word before `cursor`, skipping the spaces in between.
+	fn previous_word_start(s: &str, cursor: usize) -> usize {
+		let chars = s.chars().take(cursor).collect::<Vec<_>>();
+		let mut i = chars.len();
+		while i > 0 && chars[i - 1].is_whitespace() {
+			i -= 1;
+		}
+		while i > 0 && !chars[i - 1].is_whitespace() {
+			i -= 1;
+		}
+		i
+	}
+	/// Char index of the end of the word after `cursor`, skipping the spaces in between.
+	fn next_word_end(s: &str, cursor: usize) -> usize {
+		let chars = s.chars().collect::<Vec<_>>();
+		let mut i = cursor.min(chars.len());
+		while i < chars.len() && chars[i].is_whitespace() {
+			i += 1;
+		}
+		while i < chars.len() && !chars[i].is_whitespace() {
+			i += 1;
+		}
+		i
+	}
+	/// Word boundaries would give away where the spaces of a hidden input are, so it's one word.
+	fn word_boundary(&self, forward: bool) -> usize {
+		match (self.hide_input, forward) {
+			(true, false) => 0,
+			(true, true) => Self::chars_count(&self.buffer),
+			(false, false) => Self::previous_word_start(&self.buffer, self.cursor),
+			(false, true) => Self::next_word_end(&self.buffer, self.cursor),
+		}
+	}
+	/// Removes the characters from `start` to `end` (char indices) and puts the cursor at `start`.
+	fn delete_chars(&mut self, start: usize, end: usize) {
+		let start_byte_index = Self::char_index_to_byte_index(&self.buffer, start);
+		let end_byte_index = Self::char_index_to_byte_index(&self.buffer, end);
+		self
+			.buffer
+			.replace_range(start_byte_index..end_byte_index, "");
+		self.cursor = start;
+	}
+	fn handle_action(&mut self, action: Action) {
+		match action {
+			Action::SelectAll => {
+				self.selected_all = !self.buffer.is_empty();
+				self.cursor = Self::chars_count(&self.buffer);
+			}
+			Action::DeleteWordBackward => self.delete_chars(self.word_boundary(false), self.cursor),
+			Action::DeleteWordForward => self.delete_chars(self.cursor, self.word_boundary(true)),
+			Action::DeleteToStart => self.delete_chars(0, self.cursor),
+			Action::WordLeft => self.cursor = self.word_boundary(false),
+			Action::WordRight => self.cursor = self.word_boundary(true),
+			// Not text editing actions
+			Action::NextKeyboardLayout | Action::ToggleDevtools => {}
+		}
+	}
 	fn scroll_cursor_into_view(&self, c: &mut Clay) {
 		let measure_text = create_measure_text_function(&FONTS);
 
@@ -75,9 +149,14 @@ impl Textbox {
 			unsafe { (*textbox_scroll.scrollPosition).x = -(x_position_cursor - view_width + 24.) };
 		}
 	}
-	fn handle_mouse_clicks(&mut self, rmar: &Rustamarine, c: &mut clay_layout::Clay) {
+	fn handle_mouse_clicks(
+		&mut self,
+		input: &dyn Input,
+		mouse_position: (f32, f32),
+		c: &mut clay_layout::Clay,
+	) {
 		let id = c.id(&self.id);
-		if !rmar.is_mouse_button_pressed(0) || !c.pointer_over(id) {
+		if !input.is_mouse_button_pressed(0) || !c.pointer_over(id) {
 			return;
 		}
 
@@ -98,7 +177,7 @@ impl Textbox {
 		let text_config = Self::text_config();
 		let measure_text = create_measure_text_function(&FONTS);
 
-		let click_x = rmar.get_mouse_x() as f32;
+		let click_x = mouse_position.0;
 		let relative_x = click_x - bounding_box.x - unsafe { (*textbox_scroll.scrollPosition).x } - 15.;
 
 		let mut best_index = 0;
@@ -132,16 +211,32 @@ impl Textbox {
 			.alignment(clay_layout::text::TextAlignment::Left);
 		return config;
 	}
-	pub fn update<'clay, 'render>(&mut self, rmar: &Rustamarine, c: &mut clay_layout::Clay)
-	where
+	pub fn update<'clay, 'render>(
+		&mut self,
+		input: &dyn Input,
+		mouse_position: (f32, f32),
+		c: &mut clay_layout::Clay,
+	) where
 		'clay: 'render,
 	{
+		self.caps_lock = input.modifiers().contains(Modifiers::CAPS_LOCK);
 		if !self.focused || self.disabled {
 			return;
 		}
-		self.handle_mouse_clicks(rmar, c);
+		self.handle_mouse_clicks(input, mouse_position, c);
 		let chars_count = Self::chars_count(&self.buffer);
-		if rmar.is_key_pressed(keys::KEY_BackSpace) {
+		let action = CONFIG.keybindings.pressed(input);
+		let selected_all = std::mem::take(&mut self.selected_all);
+		if selected_all
+			&& action.is_none()
+			&& (input.is_key_pressed(keys::KEY_BackSpace) || input.is_key_pressed(keys::KEY_Delete))
+		{
+			self.delete_chars(0, chars_count);
+			self.scroll_cursor_into_view(c);
+		} else if let Some(action) = action {
+			self.handle_action(action);
+			self.scroll_cursor_into_view(c);
+		} else if input.is_key_pressed(keys::KEY_BackSpace) {
 			if self.cursor > 0 {
 				if self.cursor >= chars_count {
 					let cursor_byte_index = Self::char_index_to_byte_index(&self.buffer, chars_count - 1);
@@ -154,31 +249,34 @@ impl Textbox {
 				}
 			}
 			self.scroll_cursor_into_view(c);
-		} else if rmar.is_key_pressed(keys::KEY_Left) {
+		} else if input.is_key_pressed(keys::KEY_Left) {
 			if self.cursor > 0 {
 				self.cursor -= 1;
 			}
 			self.scroll_cursor_into_view(c);
-		} else if rmar.is_key_pressed(keys::KEY_Right) {
+		} else if input.is_key_pressed(keys::KEY_Right) {
 			if self.cursor < chars_count {
 				self.cursor += 1;
 			}
 			self.scroll_cursor_into_view(c);
-		} else if rmar.is_key_pressed(keys::KEY_Delete) {
+		} else if input.is_key_pressed(keys::KEY_Delete) {
 			let cursor_byte_index = Self::char_index_to_byte_index(&self.buffer, self.cursor);
 			if self.buffer.len() > cursor_byte_index && !self.buffer.is_empty() {
 				self.buffer.remove(cursor_byte_index);
 			}
 			self.scroll_cursor_into_view(c);
-		} else if rmar.is_key_pressed(keys::KEY_Home) {
+		} else if input.is_key_pressed(keys::KEY_Home) {
 			self.cursor = 0;
 			self.scroll_cursor_into_view(c);
-		} else if rmar.is_key_pressed(keys::KEY_End) {
+		} else if input.is_key_pressed(keys::KEY_End) {
 			self.cursor = self.buffer.chars().count();
 			self.scroll_cursor_into_view(c);
 		}
-		let input_characters = rmar.get_typed_characters();
+		let input_characters = input.get_typed_characters();
 		if input_characters.len() > 0 {
+			if selected_all {
+				self.delete_chars(0, Self::chars_count(&self.buffer));
+			}
 			let cursor_byte_index = Self::char_index_to_byte_index(&self.buffer, self.cursor);
 			self.buffer.insert_str(cursor_byte_index, &input_characters);
 			self.cursor += input_characters.len();
@@ -192,13 +290,22 @@ impl Textbox {
 	where
 		'clay: 'render,
 	{
+		let caps_lock_icon = self
+			.caps_lock_icon
+			.as_ref()
+			.filter(|_| self.hide_input && self.caps_lock);
 		c.with_styling(
 			|c| {
 				let mut d = Declaration::new();
 				d.layout()
 					.width(fixed!(300.0))
 					.height(fixed!(50.0))
-					.padding(Padding::all(15))
+					// Keeps the text from going under the caps lock icon
+					.padding(if caps_lock_icon.is_some() {
+						Padding::new(15, 15 + 18 + 8, 15, 15)
+					} else {
+						Padding::all(15)
+					})
 					.child_alignment(Alignment::new(
 						clay_layout::layout::LayoutAlignmentX::Left,
 						clay_layout::layout::LayoutAlignmentY::Center,
@@ -219,19 +326,32 @@ impl Textbox {
 					&self.buffer
 				};
 				let cursor_byte_index = Self::char_index_to_byte_index(buffer_to_render, self.cursor);
-				c.text(
-					&buffer_to_render[..cursor_byte_index],
-					Self::text_config()
-						.color(
-							if self.disabled {
-								(0xFF, 0xFF, 0xFF, 0x50)
-							} else {
-								(0xFF, 0xFF, 0xFF, 0xFF)
-							}
-							.into(),
-						)
-						.end(),
-				);
+				let text_color = if self.disabled {
+					(0xFF, 0xFF, 0xFF, 0x50)
+				} else {
+					(0xFF, 0xFF, 0xFF, 0xFF)
+				};
+				if self.selected_all {
+					// Select all puts the cursor at the end, so all the text is before it
+					c.with(
+						Declaration::new()
+							.background_color((0x3D, 0x8B, 0xFF, 0x80).into())
+							.corner_radius()
+							.all(3.0)
+							.end(),
+						|c| {
+							c.text(
+								&buffer_to_render[..cursor_byte_index],
+								Self::text_config().color(text_color.into()).end(),
+							);
+						},
+					);
+				} else {
+					c.text(
+						&buffer_to_render[..cursor_byte_index],
+						Self::text_config().color(text_color.into()).end(),
+					);
+				}
 				if self.focused {
 					c.with(
 						Declaration::new()
@@ -257,6 +377,27 @@ impl Textbox {
 					&buffer_to_render[cursor_byte_index..],
 					Self::text_config().end(),
 				);
+				if let Some(icon) = caps_lock_icon {
+					c.with(
+						Declaration::new()
+							.floating()
+							.attach_to(FloatingAttachToElement::Parent)
+							.attach_points(
+								FloatingAttachPointType::RightCenter,
+								FloatingAttachPointType::RightCenter,
+							)
+							.offset((-15.0, 0.0).into())
+							.end()
+							.image()
+							.data(icon)
+							.end()
+							.layout()
+							.width(fixed!(18.0))
+							.height(fixed!(18.0))
+							.end(),
+						|_| {},
+					);
+				}
 			},
 		);
 	}
@@ -272,4 +413,28 @@ impl Textbox {
 	pub fn text(&self) -> &str {
 		&self.buffer
 	}
+
+	pub fn clear(&mut self) {
+		self.buffer.clear();
+		self.censored_buffer.clear();
+		self.cursor = 0;
+		self.selected_all = false;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn word_boundaries() {
+		let text = "correct horse  battery";
+		assert_eq!(Textbox::previous_word_start(text, 22), 15);
+		assert_eq!(Textbox::previous_word_start(text, 15), 8);
+		assert_eq!(Textbox::previous_word_start(text, 3), 0);
+		assert_eq!(Textbox::next_word_end(text, 0), 7);
+		assert_eq!(Textbox::next_word_end(text, 13), 22);
+		assert_eq!(Textbox::next_word_end(text, 30), 22);
+		assert_eq!(Textbox::previous_word_start("ção nã", 6), 4);
+	}
 }
@@ -0,0 +1,339 @@
+//! Headless mode: renders scripted frames on a CPU raster surface and saves them as PNGs.
+//!
+//! This doesn't need a GPU, DRM or systemd, so theme and layout changes can be checked on build
+//! machines. Run it with `tibs --headless <script> [output folder]`, the script is a list of
+//! frames:
+//!
+//! ```text
+//! # Comments start with #
+//! size 1920x1080
+//! scale 1
+//! users alice,bob
+//! frame 0.5 loading.png progress=0.3
+//! frame 2 loading-failed.png progress=0.6 failed=2
+//! frame 4 login.png login
+//! frame 5 typing.png login type=hunter2 mouse=960,600
+//! frame 6 login-failed.png login state=failed
+//! ```
+//!
+//! `frame <time> <png> [options]` renders the UI until `<time>` seconds have passed (at 60 fps,
+//! so animations always end up in the same place) and saves the last frame to `<png>`, relative
+//! to the output folder (the folder of the script by default). `users` replaces the single `user`
+//! shown on the login screen. The options only affect that frame and the ones after it:
+//!
+//! - `progress=<0..1>`: boot progress, `1` finishes the boot
+//! - `failed=<n>`: number of services that failed to start
+//! - `login`: slide to the login screen
+//! - `state=logging|failed`: login state of the selected user, sessions are never started
+//! - `type=<text>`: text typed on the first frame
+//! - `mouse=<x>,<y>`: cursor position, in logical pixels. The cursor isn't drawn, as its theme
+//!   depends on the machine, but what's under it is hovered
+//! - `click`: left click on the first frame
+//! - `popup`: open the desktop environments popup
+//! - `background`: only render the background, just for this frame
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	rc::Rc,
+	str::FromStr,
+};
+
+use assets_manager::AssetCache;
+use color_eyre::eyre::{bail, eyre, OptionExt, Result, WrapErr};
+use rustamarine::screen::Transform;
+use skia_safe::{surfaces, Color, EncodedImageFormat};
+use uzers::User;
+
+use crate::{
+	app::{AppState, OutputRole},
+	config::CONFIG,
+	input::FakeInput,
+	login::{LoginManager, LoginScreen, LoginState, UserAccount},
+	outputs::{Output, OutputLayout},
+	progress_watcher::{ProgressData, ProgressWatcher, ServiceState},
+	session_manager::SessionManager,
+	skia::clay_renderer::create_measure_text_function,
+	FONTS,
+};
+
+/// Time between two rendered frames
+const FRAME_TIME: f32 = 1.0 / 60.0;
+/// Number of fake services the boot progress is made of
+const SERVICES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script {
+	/// Size of the screenshots in pixels
+	pub size: (u32, u32),
+	pub scale: f32,
+	/// Names of the users on the login screen, made up so the frames don't depend on the users of
+	/// the machine rendering them
+	pub users: Vec<String>,
+	pub frames: Vec<ScriptFrame>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScriptFrame {
+	/// Seconds since the start of the script
+	pub time: f32,
+	pub path: PathBuf,
+	pub progress: Option<f32>,
+	pub failed_services: Option<usize>,
+	pub login_screen: bool,
+	pub login_state: Option<LoginState>,
+	pub typed_characters: String,
+	pub mouse: Option<(i32, i32)>,
+	pub click: bool,
+	pub desktop_environment_popup: bool,
+	pub background_only: bool,
+}
+
+impl FromStr for ScriptFrame {
+	type Err = color_eyre::Report;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let mut parts = s.split_whitespace();
+		let time = parts.next().ok_or_eyre("Missing frame time")?;
+		let mut frame = ScriptFrame {
+			time: time
+				.parse()
+				.wrap_err_with(|| format!("Invalid time {time:?}"))?,
+			path: parts.next().ok_or_eyre("Missing frame path")?.into(),
+			..Default::default()
+		};
+		for option in parts {
+			let (key, value) = option.split_once('=').unwrap_or((option, ""));
+			let invalid = || eyre!("Invalid frame option {option:?}");
+			match key {
+				"progress" => frame.progress = Some(value.parse().map_err(|_| invalid())?),
+				"failed" => frame.failed_services = Some(value.parse().map_err(|_| invalid())?),
+				"login" => frame.login_screen = true,
+				"state" => {
+					frame.login_state = Some(match value {
+						"logging" => LoginState::Logging,
+						"failed" => LoginState::Failed,
+						_ => return Err(invalid()),
+					})
+				}
+				"type" => frame.typed_characters = value.to_string(),
+				"mouse" => {
+					let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+					frame.mouse = Some((
+						x.parse().map_err(|_| invalid())?,
+						y.parse().map_err(|_| invalid())?,
+					));
+				}
+				"click" => frame.click = true,
+				"popup" => frame.desktop_environment_popup = true,
+				"background" => frame.background_only = true,
+				_ => return Err(invalid()),
+			}
+		}
+		Ok(frame)
+	}
+}
+
+impl FromStr for Script {
+	type Err = color_eyre::Report;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let mut script = Script {
+			size: (1920, 1080),
+			scale: 1.0,
+			users: vec!["user".to_string()],
+			frames: Vec::new(),
+		};
+		for (i, line) in s.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+			let args = args.trim();
+			let result = match command {
+				"size" => args
+					.split_once('x')
+					.and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+					.map(|size| script.size = size)
+					.ok_or_else(|| eyre!("Invalid size {args:?}")),
+				"scale" => args
+					.parse()
+					.map(|scale| script.scale = scale)
+					.map_err(|_| eyre!("Invalid scale {args:?}")),
+				"users" => {
+					script.users = args.split(',').map(|u| u.trim().to_string()).collect();
+					Ok(())
+				}
+				"frame" => args.parse().map(|frame| script.frames.push(frame)),
+				_ => Err(eyre!("Unknown command {command:?}")),
+			};
+			result.wrap_err_with(|| format!("Line {}", i + 1))?;
+		}
+		Ok(script)
+	}
+}
+
+fn progress_data(progress: f32, failed_services: usize) -> ProgressData {
+	let loaded = (progress.clamp(0.0, 1.0) * SERVICES as f32).round() as usize;
+	ProgressData {
+		services: (0..SERVICES)
+			.map(|i| {
+				let state = if i < failed_services {
+					ServiceState::Failed
+				} else if i < loaded {
+					ServiceState::Loaded
+				} else {
+					ServiceState::Loading
+				};
+				(format!("fake{i}.service"), state)
+			})
+			.collect::<HashMap<_, _>>(),
+		finished: progress >= 1.0,
+	}
+}
+
+/// Renders every frame of the script at `script_path`, saving them in `output_folder`.
+pub fn run(script_path: &Path, output_folder: Option<&Path>) -> Result<()> {
+	let script: Script = std::fs::read_to_string(script_path)
+		.wrap_err_with(|| format!("Failed to read {}", script_path.display()))?
+		.parse()?;
+	if script.frames.is_empty() {
+		bail!("{} has no frames", script_path.display());
+	}
+	let folder = output_folder
+		.or(script_path.parent())
+		.unwrap_or(Path::new("."));
+	std::fs::create_dir_all(folder)?;
+
+	let mut clay = clay_layout::Clay::new((0.0, 0.0).into());
+	clay.set_measure_text_function(create_measure_text_function(&FONTS));
+	let assets = Rc::new(AssetCache::new(&CONFIG.assets_folder)?);
+	let output = Output::new("headless", script.size, Transform::Normal, script.scale);
+	let users = script
+		.users
+		.iter()
+		.zip(1000..)
+		.map(|(name, uid)| UserAccount::from_passwd(&User::new(uid, name, uid), &HashMap::new()))
+		.collect();
+	let login_screen = LoginScreen::with_users(&assets, script.scale, users);
+	let mut app = AppState::new(
+		clay,
+		assets,
+		script.scale,
+		ProgressWatcher::fake(ProgressData::default()),
+		LoginManager::fake(),
+		SessionManager::nested(),
+		login_screen,
+	);
+	app.show_cursor = false;
+	app.set_outputs(OutputLayout::new([output.clone()], None));
+
+	let mut surface = surfaces::raster_n32_premul((script.size.0 as i32, script.size.1 as i32))
+		.ok_or_eyre("Failed to create the raster surface")?;
+	let mut time = 0.0;
+	let mut progress = 0.0;
+	let mut failed_services = 0;
+	let mut mouse = (output.width as i32 / 2, output.height as i32 / 2);
+	for frame in &script.frames {
+		progress = frame.progress.unwrap_or(progress);
+		failed_services = frame.failed_services.unwrap_or(failed_services);
+		app
+			.boot_progress
+			.set_progress(progress_data(progress, failed_services));
+		app.show_login_screen |= frame.login_screen;
+		if let Some(state) = frame.login_state {
+			app
+				.login_manager
+				.set_login_state(app.login_screen.username(), state);
+		}
+		mouse = frame.mouse.unwrap_or(mouse);
+		if frame.desktop_environment_popup {
+			app.login_screen.set_desktop_environment_popup_open(true);
+		}
+		let role = if frame.background_only {
+			OutputRole::Background
+		} else {
+			OutputRole::Ui
+		};
+
+		let mut input = FakeInput {
+			typed_characters: frame.typed_characters.clone(),
+			..Default::default()
+		};
+		if frame.click {
+			input.click();
+		}
+		loop {
+			let delta = (frame.time - time).clamp(0.0, FRAME_TIME);
+			time += delta;
+			app.step(&input, output.to_local(mouse), delta);
+			let canvas = surface.canvas();
+			canvas.clear(Color::BLACK);
+			app.draw(canvas, &output, role, &input, mouse);
+			// Typing and clicking only happen on the first frame
+			input = FakeInput::default();
+			if time >= frame.time {
+				break;
+			}
+		}
+
+		let path = folder.join(&frame.path);
+		let png = surface
+			.image_snapshot()
+			.encode(None, EncodedImageFormat::PNG, None)
+			.ok_or_eyre("Failed to encode the frame")?;
+		std::fs::write(&path, png.as_bytes())
+			.wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+		println!("[INFO] Saved {}", path.display());
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_script() {
+		let script: Script = "
+			# Comment
+			size 800x600
+			scale 2
+			users alice, bob
+			frame 0.5 loading.png progress=0.5 failed=1
+			frame 3 login.png login state=failed mouse=10,20 click type=abc
+		"
+		.parse()
+		.unwrap();
+		assert_eq!(script.size, (800, 600));
+		assert_eq!(script.scale, 2.0);
+		assert_eq!(script.users, ["alice", "bob"]);
+		assert_eq!(script.frames.len(), 2);
+		assert_eq!(script.frames[0].progress, Some(0.5));
+		assert_eq!(script.frames[0].failed_services, Some(1));
+		let login = &script.frames[1];
+		assert_eq!(login.path, PathBuf::from("login.png"));
+		assert!(login.login_screen && login.click);
+		assert_eq!(login.login_state, Some(LoginState::Failed));
+		assert_eq!(login.mouse, Some((10, 20)));
+		assert_eq!(login.typed_characters, "abc");
+	}
+
+	#[test]
+	fn invalid_script() {
+		assert!("frame".parse::<Script>().is_err());
+		assert!("frame 1 a.png state=authenticated"
+			.parse::<Script>()
+			.is_err());
+		assert!("size 800".parse::<Script>().is_err());
+		assert!("jump 1".parse::<Script>().is_err());
+	}
+
+	#[test]
+	fn fake_progress() {
+		let progress = progress_data(0.5, 2);
+		assert_eq!(progress.get_percentage(), 0.5);
+		assert!(progress.has_failed_services());
+		assert!(progress_data(1.0, 0).finished);
+	}
+}
@@ -0,0 +1,355 @@
+//! Users that can log in, with what the login screen shows about them.
+//!
+//! The users come from passwd, along with the ones AccountsService (`org.freedesktop.Accounts`)
+//! knows when it's running: it knows their real names, the avatars picked in the settings of the
+//! desktops, their language and the session they used last, and it's watched so users added,
+//! removed or changed while tibs runs show up. Without it, the real name comes from the GECOS
+//! field and the avatar from `~/.face`. The users of systemd-homed get them from their user record
+//! instead, their home is locked until they log in.
+use std::{cmp::Reverse, collections::HashMap, path::PathBuf, thread, time::SystemTime};
+
+use futures_util::{stream, FutureExt as _, StreamExt};
+use smol::channel;
+use uzers::{os::unix::UserExt, User};
+use zbus_systemd::zbus::{self, zvariant::OwnedObjectPath, Connection, Proxy};
+
+use super::{homed_user, HomedUser, LastLogins};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserAccount {
+	pub uid: u32,
+	pub username: String,
+	/// Empty if the user doesn't have one
+	pub real_name: String,
+	pub home_dir: PathBuf,
+	/// Avatar picked in AccountsService, `~/.face` is used if it's `None`
+	pub icon_file: Option<PathBuf>,
+	/// Locale, like `pt_PT.UTF-8`
+	pub language: Option<String>,
+	/// Desktop file name (without `.desktop`) of the session the user picked last
+	pub session: Option<String>,
+	/// Accounts of services, hidden from the login screen
+	pub system_account: bool,
+	/// Shown instead of `~/.config/tibs/background.*`, for homes that can't be read before login
+	pub login_background: Option<PathBuf>,
+	/// From wtmp and lastlog, set by [`sort_by_last_login`]
+	pub last_login: Option<SystemTime>,
+}
+
+impl UserAccount {
+	/// Account from passwd, `real_names` are the GECOS real names by uid.
+	pub fn from_passwd(user: &User, real_names: &HashMap<u32, String>) -> Self {
+		Self {
+			uid: user.uid(),
+			username: user.name().to_string_lossy().into_owned(),
+			real_name: real_names.get(&user.uid()).cloned().unwrap_or_default(),
+			home_dir: user.home_dir().to_path_buf(),
+			icon_file: None,
+			language: None,
+			session: None,
+			system_account: false,
+			login_background: None,
+			last_login: None,
+		}
+	}
+
+	/// Fills in what the user record of a homed user has.
+	pub fn apply_homed(&mut self, homed: &HomedUser) {
+		if self.real_name.is_empty() {
+			self.real_name = homed.real_name.clone().unwrap_or_default();
+		}
+		if !self.icon_file.as_ref().is_some_and(|path| path.is_file()) {
+			self.icon_file = Some(homed.avatar_file()).filter(|path| path.is_file());
+		}
+		self.login_background = Some(homed.login_background_file()).filter(|path| path.is_file());
+	}
+
+	/// The real name, or the username if there's none.
+	pub fn display_name(&self) -> &str {
+		if self.real_name.is_empty() {
+			&self.username
+		} else {
+			&self.real_name
+		}
+	}
+
+	/// The AccountsService avatar if it exists, `~/.face` otherwise.
+	pub fn avatar_file(&self) -> PathBuf {
+		self
+			.icon_file
+			.clone()
+			.filter(|path| path.is_file())
+			.unwrap_or_else(|| self.home_dir.join(".face"))
+	}
+}
+
+/// Real names from the GECOS field (`Full Name,Room,Phone,...`) of the `/etc/passwd` lines, by uid.
+pub fn parse_passwd_real_names(passwd: &str) -> HashMap<u32, String> {
+	passwd
+		.lines()
+		.filter_map(|line| {
+			let fields = line.split(':').collect::<Vec<_>>();
+			let uid = fields.get(2)?.parse().ok()?;
+			let real_name = fields.get(4)?.split(',').next()?.trim();
+			(!real_name.is_empty()).then(|| (uid, real_name.to_string()))
+		})
+		.collect()
+}
+
+/// Fills in `last_login` and puts the users that logged in most recently first.
+pub fn sort_by_last_login(users: &mut [UserAccount]) {
+	let last_logins = LastLogins::load();
+	for user in users.iter_mut() {
+		user.last_login = last_logins.get(user.uid, &user.username);
+	}
+	users.sort_by_key(|user| Reverse(user.last_login));
+}
+
+/// Adds what AccountsService knows to the users from passwd, by uid. Users only AccountsService
+/// knows are added, and the ones it says are system accounts are removed.
+pub fn merge_accounts(
+	passwd_users: &[UserAccount],
+	accounts: Vec<UserAccount>,
+) -> Vec<UserAccount> {
+	let mut users = passwd_users.to_vec();
+	for account in accounts {
+		let passwd_user = users.iter().position(|user| user.uid == account.uid);
+		match passwd_user {
+			Some(i) if account.system_account => {
+				users.remove(i);
+			}
+			_ if account.system_account => {}
+			Some(i) => {
+				let real_name = std::mem::take(&mut users[i].real_name);
+				users[i] = account;
+				if users[i].real_name.is_empty() {
+					users[i].real_name = real_name;
+				}
+			}
+			None => users.push(account),
+		}
+	}
+	users
+}
+
+pub fn passwd_real_names() -> HashMap<u32, String> {
+	std::fs::read_to_string("/etc/passwd")
+		.map(|passwd| parse_passwd_real_names(&passwd))
+		.unwrap_or_default()
+}
+
+/// Watches the users of AccountsService in a thread.
+pub struct AccountsWatcher {
+	users_rx: channel::Receiver<Vec<UserAccount>>,
+	/// Closed to stop the thread
+	shutdown: channel::Sender<()>,
+	handle: Option<thread::JoinHandle<()>>,
+}
+
+impl AccountsWatcher {
+	/// `passwd_users` are reported with what AccountsService and homed know about them.
+	pub fn new(passwd_users: Vec<UserAccount>) -> Self {
+		let (tx, rx) = channel::unbounded();
+		let (shutdown, shutdown_rx) = channel::bounded(1);
+		let handle = thread::spawn(move || {
+			smol::block_on(async {
+				let connection = match Connection::system().await {
+					Ok(connection) => connection,
+					Err(e) => {
+						log::warn!("Failed to connect to the system bus: {e}");
+						return;
+					}
+				};
+				if let Err(e) = watch_accounts(&connection, &passwd_users, &tx, &shutdown_rx).await {
+					log::info!("AccountsService isn't available, using passwd: {e}");
+					let mut users = passwd_users;
+					add_homed_records(&connection, &mut users).await;
+					sort_by_last_login(&mut users);
+					tx.send(users).await.ok();
+				}
+			})
+		});
+		Self {
+			users_rx: rx,
+			shutdown,
+			handle: Some(handle),
+		}
+	}
+
+	/// Watcher that never reports any users, the ones from passwd are kept.
+	pub fn disabled() -> Self {
+		let (_, rx) = channel::unbounded();
+		let (shutdown, _) = channel::bounded(1);
+		Self {
+			users_rx: rx,
+			shutdown,
+			handle: None,
+		}
+	}
+
+	/// The users that can log in, if they changed since the last call.
+	pub fn poll(&self) -> Option<Vec<UserAccount>> {
+		let mut users = None;
+		while let Ok(new_users) = self.users_rx.try_recv() {
+			users = Some(new_users);
+		}
+		users
+	}
+}
+
+impl Drop for AccountsWatcher {
+	fn drop(&mut self) {
+		self.shutdown.close();
+		if let Some(handle) = self.handle.take() {
+			handle.join().ok();
+		}
+	}
+}
+
+async fn watch_accounts(
+	connection: &Connection,
+	passwd_users: &[UserAccount],
+	tx: &channel::Sender<Vec<UserAccount>>,
+	shutdown: &channel::Receiver<()>,
+) -> zbus::Result<()> {
+	let accounts = Proxy::new(
+		connection,
+		"org.freedesktop.Accounts",
+		"/org/freedesktop/Accounts",
+		"org.freedesktop.Accounts",
+	)
+	.await?;
+	let mut user_added = accounts.receive_signal("UserAdded").await?;
+	let mut user_deleted = accounts.receive_signal("UserDeleted").await?;
+	loop {
+		let paths: Vec<OwnedObjectPath> = accounts.call("ListCachedUsers", &()).await?;
+		let mut cached = Vec::with_capacity(paths.len());
+		let mut user_changed = Vec::with_capacity(paths.len());
+		for path in paths {
+			let user = Proxy::new(
+				connection,
+				"org.freedesktop.Accounts",
+				path,
+				"org.freedesktop.Accounts.User",
+			)
+			.await?;
+			// Sent when the real name, avatar, language or session of the user changes
+			user_changed.push(user.receive_signal("Changed").await?);
+			match user_account(&user).await {
+				Ok(account) => cached.push(account),
+				Err(e) => log::warn!("Failed to read a user from AccountsService: {e}"),
+			}
+		}
+		let mut users = merge_accounts(passwd_users, cached);
+		add_homed_records(connection, &mut users).await;
+		sort_by_last_login(&mut users);
+		if tx.send(users).await.is_err() {
+			break;
+		}
+		let mut user_changed = stream::select_all(user_changed);
+		futures_util::select! {
+			_ = user_added.next().fuse() => {},
+			_ = user_deleted.next().fuse() => {},
+			_ = user_changed.select_next_some() => {},
+			_ = shutdown.recv().fuse() => break,
+		}
+	}
+	Ok(())
+}
+
+async fn add_homed_records(connection: &Connection, users: &mut [UserAccount]) {
+	for user in users {
+		if let Some(homed) = homed_user(connection, &user.username).await {
+			user.apply_homed(&homed);
+		}
+	}
+}
+
+async fn user_account(user: &Proxy<'_>) -> zbus::Result<UserAccount> {
+	let non_empty = |s: String| (!s.is_empty()).then_some(s);
+	// `Session` is newer than `XSession`, older versions only have the latter
+	let session = match user.get_property::<String>("Session").await {
+		Ok(session) => non_empty(session),
+		Err(_) => None,
+	};
+	let session = match session {
+		Some(session) => Some(session),
+		None => non_empty(user.get_property("XSession").await?),
+	};
+	Ok(UserAccount {
+		uid: user.get_property::<u64>("Uid").await? as u32,
+		username: user.get_property("UserName").await?,
+		real_name: user.get_property("RealName").await?,
+		home_dir: user.get_property::<String>("HomeDirectory").await?.into(),
+		icon_file: non_empty(user.get_property("IconFile").await?).map(PathBuf::from),
+		language: non_empty(user.get_property("Language").await?),
+		session,
+		system_account: user.get_property("SystemAccount").await?,
+		login_background: None,
+		last_login: None,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn gecos_real_names() {
+		let real_names = parse_passwd_real_names(
+			"root:x:0:0:System administrator:/root:/bin/sh\n\
+			 alice:x:1000:100:Alice Liddell,,,:/home/alice:/bin/bash\n\
+			 bob:x:1001:100::/home/bob:/bin/bash\n\
+			 invalid\n",
+		);
+		assert_eq!(real_names.len(), 2);
+		assert_eq!(real_names[&1000], "Alice Liddell");
+		assert_eq!(real_names[&0], "System administrator");
+	}
+
+	fn account(uid: u32, username: &str, real_name: &str) -> UserAccount {
+		UserAccount {
+			real_name: real_name.into(),
+			..UserAccount::from_passwd(&User::new(uid, username, 100), &HashMap::new())
+		}
+	}
+
+	#[test]
+	fn accounts_service_is_merged_into_passwd() {
+		let passwd = [
+			account(1000, "alice", "Alice Liddell"),
+			account(1001, "bob", ""),
+			account(1002, "backup", ""),
+		];
+		let accounts = vec![
+			UserAccount {
+				language: Some("pt_PT.UTF-8".into()),
+				..account(1000, "alice", "")
+			},
+			account(1001, "bob", "Bob"),
+			UserAccount {
+				system_account: true,
+				..account(1002, "backup", "")
+			},
+			account(1003, "carol", "Carol"),
+			UserAccount {
+				system_account: true,
+				..account(1004, "service", "")
+			},
+		];
+		let users = merge_accounts(&passwd, accounts);
+		assert_eq!(
+			users,
+			[
+				UserAccount {
+					language: Some("pt_PT.UTF-8".into()),
+					..account(1000, "alice", "Alice Liddell")
+				},
+				account(1001, "bob", "Bob"),
+				account(1003, "carol", "Carol"),
+			]
+		);
+		// Nothing from AccountsService keeps the passwd users
+		assert_eq!(merge_accounts(&passwd, vec![]), passwd);
+	}
+}
@@ -0,0 +1,116 @@
+//! Users managed by systemd-homed (`org.freedesktop.home1`).
+//!
+//! Their home directory is encrypted and only unlocked when they log in, so `~/.face` and the tibs
+//! config in it can't be read on the login screen. homed keeps what's needed outside of the home: the
+//! JSON user record has the real name, and the blob directory of the record has the avatar and the
+//! login background.
+use std::path::PathBuf;
+
+use zbus_systemd::zbus::{self, zvariant::OwnedObjectPath, Connection, Proxy};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HomedUser {
+	pub real_name: Option<String>,
+	/// Files homed shows before the home is unlocked, like `avatar` and `login-background`
+	pub blob_directory: PathBuf,
+	/// `inactive`, `active`, `absent`... see `homectl inspect`
+	pub state: String,
+}
+
+impl HomedUser {
+	/// Reads the JSON user record of `user_name`, `state` is the state of their home.
+	pub fn from_record(user_name: &str, record: &str, state: String) -> Option<Self> {
+		let record: serde_json::Value = serde_json::from_str(record).ok()?;
+		let blob_directory = record
+			.get("blobDirectory")
+			.and_then(|dir| dir.as_str())
+			.map(PathBuf::from)
+			.unwrap_or_else(|| PathBuf::from("/var/cache/systemd/home").join(user_name));
+		Some(Self {
+			real_name: record
+				.get("realName")
+				.and_then(|name| name.as_str())
+				.filter(|name| !name.is_empty())
+				.map(String::from),
+			blob_directory,
+			state,
+		})
+	}
+
+	pub fn avatar_file(&self) -> PathBuf {
+		self.blob_directory.join("avatar")
+	}
+
+	pub fn login_background_file(&self) -> PathBuf {
+		self.blob_directory.join("login-background")
+	}
+
+	/// The home is unlocked and mounted.
+	pub fn is_active(&self) -> bool {
+		matches!(
+			self.state.as_str(),
+			"active" | "active-saving" | "lingering"
+		)
+	}
+}
+
+/// The homed user named `user_name`, `None` if they aren't one or homed isn't running.
+pub async fn homed_user(connection: &Connection, user_name: &str) -> Option<HomedUser> {
+	let result: zbus::Result<HomedUser> = async {
+		let manager = Proxy::new(
+			connection,
+			"org.freedesktop.home1",
+			"/org/freedesktop/home1",
+			"org.freedesktop.home1.Manager",
+		)
+		.await?;
+		let (_, state, _, _, _, _, _): (u32, String, u32, String, String, String, OwnedObjectPath) =
+			manager.call("GetHomeByName", &(user_name,)).await?;
+		let (record, _, _): (String, bool, OwnedObjectPath) =
+			manager.call("GetUserRecordByName", &(user_name,)).await?;
+		HomedUser::from_record(user_name, &record, state)
+			.ok_or_else(|| zbus::Error::Failure("Invalid user record".into()))
+	}
+	.await;
+	result
+		.inspect_err(|e| log::debug!("{user_name} isn't a homed user: {e}"))
+		.ok()
+}
+
+/// Blocking [`homed_user`], for the threads that don't run an executor.
+pub fn find_homed_user(user_name: &str) -> Option<HomedUser> {
+	smol::block_on(async {
+		let connection = Connection::system().await.ok()?;
+		homed_user(&connection, user_name).await
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn user_record() {
+		let user = HomedUser::from_record(
+			"alice",
+			r#"{"userName":"alice","realName":"Alice Liddell","blobDirectory":"/home/.alice.blob"}"#,
+			"inactive".into(),
+		)
+		.unwrap();
+		assert_eq!(user.real_name.as_deref(), Some("Alice Liddell"));
+		assert_eq!(
+			user.avatar_file(),
+			PathBuf::from("/home/.alice.blob/avatar")
+		);
+		assert!(!user.is_active());
+
+		let user = HomedUser::from_record("bob", r#"{"userName":"bob"}"#, "active".into()).unwrap();
+		assert_eq!(user.real_name, None);
+		assert_eq!(
+			user.login_background_file(),
+			PathBuf::from("/var/cache/systemd/home/bob/login-background")
+		);
+		assert!(user.is_active());
+		assert!(HomedUser::from_record("bob", "not json", "active".into()).is_none());
+	}
+}
@@ -1,3 +1,9 @@
+mod accounts;
+pub use accounts::*;
+mod homed;
+pub use homed::*;
+mod login_records;
+pub use login_records::*;
 mod manager;
 pub use manager::*;
 #[path = "../screens/login_screen.rs"]
@@ -0,0 +1,227 @@
+//! Login records: utmp (`who`), wtmp (`last`) and lastlog, written when a session starts and ends.
+//!
+//! wtmp and lastlog are also read back to sort the user list by the last login. Systems that
+//! replaced them with wtmpdb and lastlog2 don't have these files, so nothing is written there and
+//! the user list stays in the order of passwd.
+use std::{
+	collections::HashMap,
+	fs::OpenOptions,
+	io::{Read, Seek, SeekFrom, Write},
+	mem,
+	path::Path,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use nix::libc;
+
+const WTMP_PATH: &str = "/var/log/wtmp";
+const LASTLOG_PATH: &str = "/var/log/lastlog";
+
+/// `struct lastlog` of glibc, `ll_time` stays 32 bits on x86_64 to be compatible with 32 bit programs
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Lastlog {
+	time: LastlogTime,
+	line: [libc::c_char; 32],
+	_host: [libc::c_char; 256],
+}
+#[cfg(any(target_arch = "x86_64", target_pointer_width = "32"))]
+type LastlogTime = i32;
+#[cfg(not(any(target_arch = "x86_64", target_pointer_width = "32")))]
+type LastlogTime = i64;
+
+fn copy_str(destination: &mut [libc::c_char], source: &str) {
+	for (d, s) in destination.iter_mut().zip(source.bytes()) {
+		*d = s as libc::c_char;
+	}
+}
+
+fn read_str(source: &[libc::c_char]) -> String {
+	let bytes = source
+		.iter()
+		.map(|&c| c as u8)
+		.take_while(|&c| c != 0)
+		.collect::<Vec<_>>();
+	String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn as_bytes<T>(value: &T) -> &[u8] {
+	unsafe { std::slice::from_raw_parts((value as *const T).cast::<u8>(), mem::size_of::<T>()) }
+}
+
+fn utmp_entry(kind: libc::c_short, username: &str, pid: u32, tty: u16) -> libc::utmpx {
+	let mut entry: libc::utmpx = unsafe { mem::zeroed() };
+	entry.ut_type = kind;
+	entry.ut_pid = pid as libc::pid_t;
+	entry.ut_session = pid as _;
+	copy_str(&mut entry.ut_line, &format!("tty{tty}"));
+	// `login` uses the tty number too, so a getty on the same tty reuses the entry
+	copy_str(&mut entry.ut_id, &tty.to_string());
+	copy_str(&mut entry.ut_user, username);
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default();
+	entry.ut_tv.tv_sec = now.as_secs() as _;
+	entry.ut_tv.tv_usec = now.subsec_micros() as _;
+	entry
+}
+
+fn write_utmp(entry: &libc::utmpx) {
+	unsafe {
+		libc::setutxent();
+		if libc::pututxline(entry).is_null() {
+			log::warn!(
+				"Failed to write the utmp record: {}",
+				std::io::Error::last_os_error()
+			);
+		}
+		libc::endutxent();
+	}
+}
+
+fn append_wtmp(path: &Path, entry: &libc::utmpx) -> std::io::Result<()> {
+	OpenOptions::new()
+		.append(true)
+		.open(path)?
+		.write_all(as_bytes(entry))
+}
+
+fn write_lastlog(path: &Path, uid: u32, tty: u16) -> std::io::Result<()> {
+	let mut entry: Lastlog = unsafe { mem::zeroed() };
+	entry.time = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs() as LastlogTime;
+	copy_str(&mut entry.line, &format!("tty{tty}"));
+	let mut file = OpenOptions::new().write(true).open(path)?;
+	file.seek(SeekFrom::Start(
+		uid as u64 * mem::size_of::<Lastlog>() as u64,
+	))?;
+	file.write_all(as_bytes(&entry))
+}
+
+fn read_lastlog(path: &Path, uid: u32) -> Option<SystemTime> {
+	let mut file = OpenOptions::new().read(true).open(path).ok()?;
+	file
+		.seek(SeekFrom::Start(
+			uid as u64 * mem::size_of::<Lastlog>() as u64,
+		))
+		.ok()?;
+	let mut bytes = [0u8; mem::size_of::<Lastlog>()];
+	file.read_exact(&mut bytes).ok()?;
+	let entry = unsafe { bytes.as_ptr().cast::<Lastlog>().read_unaligned() };
+	(entry.time > 0).then(|| UNIX_EPOCH + Duration::from_secs(entry.time as u64))
+}
+
+/// The last login of each user in wtmp, by username.
+fn read_wtmp_logins(path: &Path) -> std::io::Result<HashMap<String, SystemTime>> {
+	let data = std::fs::read(path)?;
+	let mut logins = HashMap::new();
+	for chunk in data.chunks_exact(mem::size_of::<libc::utmpx>()) {
+		let entry = unsafe { chunk.as_ptr().cast::<libc::utmpx>().read_unaligned() };
+		if entry.ut_type != libc::USER_PROCESS {
+			continue;
+		}
+		let time = UNIX_EPOCH + Duration::from_secs(entry.ut_tv.tv_sec as u64);
+		let last_login = logins.entry(read_str(&entry.ut_user)).or_insert(time);
+		*last_login = (*last_login).max(time);
+	}
+	Ok(logins)
+}
+
+/// Records that `username` logged in on `tty`, `pid` is the process of the session.
+pub fn record_login(username: &str, uid: u32, pid: u32, tty: u16) {
+	let entry = utmp_entry(libc::USER_PROCESS, username, pid, tty);
+	write_utmp(&entry);
+	if let Err(e) = append_wtmp(Path::new(WTMP_PATH), &entry) {
+		log::debug!("Failed to write the wtmp record: {e}");
+	}
+	if let Err(e) = write_lastlog(Path::new(LASTLOG_PATH), uid, tty) {
+		log::debug!("Failed to write the lastlog record: {e}");
+	}
+}
+
+/// Records that the session of [`record_login`] ended, `last` finds the login by the tty.
+pub fn record_logout(pid: u32, tty: u16) {
+	let entry = utmp_entry(libc::DEAD_PROCESS, "", pid, tty);
+	write_utmp(&entry);
+	if let Err(e) = append_wtmp(Path::new(WTMP_PATH), &entry) {
+		log::debug!("Failed to write the wtmp record: {e}");
+	}
+}
+
+/// When the users last logged in, from wtmp and lastlog.
+pub struct LastLogins {
+	wtmp: HashMap<String, SystemTime>,
+}
+
+impl LastLogins {
+	pub fn load() -> Self {
+		Self {
+			wtmp: read_wtmp_logins(Path::new(WTMP_PATH)).unwrap_or_default(),
+		}
+	}
+
+	pub fn get(&self, uid: u32, username: &str) -> Option<SystemTime> {
+		let lastlog = read_lastlog(Path::new(LASTLOG_PATH), uid);
+		self.wtmp.get(username).copied().max(lastlog)
+	}
+}
+
+/// `time` like `last` shows it, e.g. `Sat Oct 18 14:03`, in the local timezone.
+pub fn format_login_time(time: SystemTime) -> String {
+	let seconds = time
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs() as libc::time_t;
+	let mut tm: libc::tm = unsafe { mem::zeroed() };
+	let mut buffer = [0u8; 64];
+	let length = unsafe {
+		libc::localtime_r(&seconds, &mut tm);
+		libc::strftime(
+			buffer.as_mut_ptr().cast(),
+			buffer.len(),
+			c"%a %b %e %H:%M".as_ptr(),
+			&tm,
+		)
+	};
+	String::from_utf8_lossy(&buffer[..length]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn wtmp_last_logins() {
+		let path = std::env::temp_dir().join(format!("tibs-wtmp-{}", std::process::id()));
+		std::fs::write(&path, []).unwrap();
+		for (username, time) in [("alice", 100), ("bob", 200), ("alice", 300), ("alice", 50)] {
+			let mut entry = utmp_entry(libc::USER_PROCESS, username, 1, 2);
+			entry.ut_tv.tv_sec = time as _;
+			append_wtmp(&path, &entry).unwrap();
+		}
+		append_wtmp(&path, &utmp_entry(libc::DEAD_PROCESS, "", 1, 2)).unwrap();
+		let logins = read_wtmp_logins(&path).unwrap();
+		std::fs::remove_file(&path).ok();
+		assert_eq!(logins.len(), 2);
+		assert_eq!(logins["alice"], UNIX_EPOCH + Duration::from_secs(300));
+		assert_eq!(logins["bob"], UNIX_EPOCH + Duration::from_secs(200));
+	}
+
+	#[test]
+	fn lastlog_round_trip() {
+		let path = std::env::temp_dir().join(format!("tibs-lastlog-{}", std::process::id()));
+		std::fs::write(&path, []).unwrap();
+		let before = SystemTime::now() - Duration::from_secs(1);
+		write_lastlog(&path, 1000, 3).unwrap();
+		let login = read_lastlog(&path, 1000);
+		// Users before it are left empty, and the ones after it are past the end of the file
+		let empty = read_lastlog(&path, 999);
+		let missing = read_lastlog(&path, 1001);
+		std::fs::remove_file(&path).ok();
+		assert!(login.is_some_and(|login| login >= before && login <= SystemTime::now()));
+		assert_eq!(empty, None);
+		assert_eq!(missing, None);
+	}
+}
@@ -1,23 +1,60 @@
-use pam::Client;
+use super::homed;
+use pam::{Client, Conversation};
 use std::{
 	collections::HashMap,
 	error::Error,
-	sync::{Arc, Mutex},
+	ffi::{CStr, CString},
+	sync::{mpsc, Arc, Mutex},
 	thread::JoinHandle,
 };
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LoginState {
 	Logging,
 	Failed,
 	Authenticated(u32),
 }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PromptKind {
+	/// Asks for something that shouldn't be shown, like the PIN of a security token
+	Secret,
+	Visible,
+	/// Tells the user to do something, like touching their security token
+	Info,
+	/// Why the login failed, like the drive with the home directory not being plugged in
+	Error,
+}
+/// Something PAM asks or tells the user while logging in, after it was given the password.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoginPrompt {
+	pub message: String,
+	pub kind: PromptKind,
+}
+impl LoginPrompt {
+	pub fn expects_answer(&self) -> bool {
+		matches!(self.kind, PromptKind::Secret | PromptKind::Visible)
+	}
+}
+struct PendingPrompt {
+	prompt: LoginPrompt,
+	answer: Option<mpsc::Sender<String>>,
+}
+type PromptMap = Arc<Mutex<HashMap<String, PendingPrompt>>>;
 pub struct LoginManager {
 	login_state_map: Arc<Mutex<HashMap<String, LoginState>>>,
+	prompts: PromptMap,
+	/// Don't authenticate with PAM, logins stay in [`LoginState::Logging`] until
+	/// [`LoginManager::set_login_state`] is called
+	fake: bool,
+	/// PAM sessions can only be opened by root, so in nested mode users are only authenticated
+	nested: bool,
 }
 impl Clone for LoginManager {
 	fn clone(&self) -> Self {
 		Self {
 			login_state_map: Arc::clone(&self.login_state_map),
+			prompts: Arc::clone(&self.prompts),
+			fake: self.fake,
+			nested: self.nested,
 		}
 	}
 }
@@ -25,6 +62,27 @@ impl LoginManager {
 	pub fn new() -> Self {
 		Self {
 			login_state_map: Default::default(),
+			prompts: Default::default(),
+			fake: false,
+			nested: false,
+		}
+	}
+	/// Login manager for running tibs as a window, see [`SessionManager::nested`](crate::session_manager::SessionManager::nested).
+	pub fn nested() -> Self {
+		Self {
+			login_state_map: Default::default(),
+			prompts: Default::default(),
+			fake: false,
+			nested: true,
+		}
+	}
+	/// Login manager that never talks to PAM, used by the headless mode.
+	pub fn fake() -> Self {
+		Self {
+			login_state_map: Default::default(),
+			prompts: Default::default(),
+			fake: true,
+			nested: false,
 		}
 	}
 
@@ -36,6 +94,7 @@ impl LoginManager {
 	) -> bool {
 		let name = name.into();
 		let password = password.into();
+		let open_session = open_session && !self.nested;
 		{
 			let mut login_map_lock = self.login_state_map.lock().unwrap();
 			match login_map_lock.get(&name) {
@@ -43,6 +102,13 @@ impl LoginManager {
 				_ => {
 					let login_map = Arc::clone(&self.login_state_map);
 					login_map_lock.insert(name.clone(), LoginState::Logging);
+					if let Ok(mut prompts) = self.prompts.lock() {
+						prompts.remove(&name);
+					}
+					if self.fake {
+						return true;
+					}
+					let prompts = Arc::clone(&self.prompts);
 					std::thread::spawn(move || {
 						let error = || {
 							let Ok(mut login_map_lock) = login_map.lock() else {
@@ -51,23 +117,43 @@ impl LoginManager {
 							login_map_lock.insert(name.clone(), LoginState::Failed);
 							return;
 						};
-						let mut client = match Client::with_password("login") {
-							Ok(client) => client,
-							Err(_) => {
-								return error();
-							}
+						// pam_systemd_home can ask for more than the password, and the session can only
+						// start once it unlocked the home
+						let homed = homed::find_homed_user(&name).is_some();
+						let authenticated = if homed {
+							let conversation = HomedConversation {
+								name: name.clone(),
+								password: Some(password),
+								asked_name: false,
+								prompts: Arc::clone(&prompts),
+							};
+							Client::with_conversation("login", conversation)
+								.is_ok_and(|mut client| authenticate(&mut client, open_session))
+						} else {
+							Client::with_password("login").is_ok_and(|mut client| {
+								client.conversation_mut().set_credentials(&name, &password);
+								authenticate(&mut client, open_session)
+							})
 						};
-						client.close_on_drop = false;
-						client.conversation_mut().set_credentials(&name, &password);
-						if let Err(e) = client.authenticate() {
-							println!("[ERROR] Failed to authenticate: {e:#?}");
+						if let Ok(mut prompts) = prompts.lock() {
+							// Errors stay, they're why the login failed
+							let failed_with_error = !authenticated
+								&& prompts
+									.get(&name)
+									.is_some_and(|p| p.prompt.kind == PromptKind::Error);
+							if !failed_with_error {
+								prompts.remove(&name);
+							}
+						}
+						if !authenticated {
 							return error();
 						}
-						if open_session {
-							if let Err(e) = client.open_session() {
-								println!("[ERROR] Failed to open PAM session: {e:#?}");
-								return error();
-							}
+						if homed
+							&& open_session
+							&& !homed::find_homed_user(&name).is_some_and(|user| user.is_active())
+						{
+							println!("[ERROR] The home directory of {name} wasn't activated");
+							return error();
 						}
 						let uid = uzers::get_user_by_name(&name).unwrap().uid();
 						let Ok(mut login_map_lock) = login_map.lock() else {
@@ -83,13 +169,105 @@ impl LoginManager {
 		true
 	}
 
+	/// What PAM is asking or telling `name`, answered with [`LoginManager::answer_prompt`].
+	pub fn prompt(&self, name: &str) -> Option<LoginPrompt> {
+		Some(self.prompts.lock().ok()?.get(name)?.prompt.clone())
+	}
+	pub fn answer_prompt(&self, name: &str, answer: impl Into<String>) {
+		let Ok(mut prompts) = self.prompts.lock() else {
+			return;
+		};
+		if let Some(sender) = prompts.remove(name).and_then(|p| p.answer) {
+			sender.send(answer.into()).ok();
+		}
+	}
+
 	pub fn get_current_login_state(&self, name: impl Into<String>) -> Option<LoginState> {
 		self.login_state_map.lock().ok()?.get(&name.into()).copied()
 	}
+	pub fn set_login_state(&self, name: impl Into<String>, state: LoginState) {
+		let Ok(mut m) = self.login_state_map.lock() else {
+			return;
+		};
+		m.insert(name.into(), state);
+	}
 	pub fn reset_login_state(&self, name: impl Into<String>) {
+		let name = name.into();
+		// Dropping the sender of a pending prompt makes the login fail
+		if let Ok(mut prompts) = self.prompts.lock() {
+			prompts.remove(&name);
+		}
 		let Ok(mut m) = self.login_state_map.lock() else {
 			return;
 		};
-		m.remove(&name.into());
+		m.remove(&name);
+	}
+}
+
+fn authenticate<C: Conversation>(client: &mut Client<'_, C>, open_session: bool) -> bool {
+	client.close_on_drop = false;
+	if let Err(e) = client.authenticate() {
+		println!("[ERROR] Failed to authenticate: {e:#?}");
+		return false;
+	}
+	if open_session {
+		if let Err(e) = client.open_session() {
+			println!("[ERROR] Failed to open PAM session: {e:#?}");
+			return false;
+		}
+	}
+	true
+}
+
+/// Conversation for pam_systemd_home, which asks for the PIN of the security token the home is
+/// locked with, to touch it, or to plug in the drive the home is on. Everything after the password
+/// goes to [`LoginManager::prompt`] and waits for [`LoginManager::answer_prompt`].
+struct HomedConversation {
+	name: String,
+	/// Taken by the first password prompt, the ones after it are asked to the user
+	password: Option<String>,
+	asked_name: bool,
+	prompts: PromptMap,
+}
+
+impl HomedConversation {
+	fn ask(&self, message: &CStr, kind: PromptKind) -> Result<CString, ()> {
+		let (sender, receiver) = mpsc::channel();
+		self.show(message, kind, Some(sender));
+		let answer = receiver.recv().map_err(|_| ())?;
+		CString::new(answer).map_err(|_| ())
+	}
+	fn show(&self, message: &CStr, kind: PromptKind, answer: Option<mpsc::Sender<String>>) {
+		let Ok(mut prompts) = self.prompts.lock() else {
+			return;
+		};
+		let prompt = LoginPrompt {
+			message: message.to_string_lossy().trim().to_string(),
+			kind,
+		};
+		prompts.insert(self.name.clone(), PendingPrompt { prompt, answer });
+	}
+}
+
+impl Conversation for HomedConversation {
+	fn prompt_echo(&mut self, msg: &CStr) -> Result<CString, ()> {
+		// PAM asks for the username first, it isn't given to pam_start
+		if !self.asked_name {
+			self.asked_name = true;
+			return CString::new(self.name.clone()).map_err(|_| ());
+		}
+		self.ask(msg, PromptKind::Visible)
+	}
+	fn prompt_blind(&mut self, msg: &CStr) -> Result<CString, ()> {
+		match self.password.take() {
+			Some(password) => CString::new(password).map_err(|_| ()),
+			None => self.ask(msg, PromptKind::Secret),
+		}
+	}
+	fn info(&mut self, msg: &CStr) {
+		self.show(msg, PromptKind::Info, None);
+	}
+	fn error(&mut self, msg: &CStr) {
+		self.show(msg, PromptKind::Error, None);
 	}
 }
@@ -1,11 +1,13 @@
-use crate::login::LoginManager;
+use crate::login::{record_login, record_logout, LoginManager};
 use crate::tty::*;
 use color_eyre::eyre::bail;
 use color_eyre::eyre::OptionExt;
 use freedesktop_entry_parser::parse_entry;
 use nix::libc;
 use nix::libc::setsid;
+use rustamarine::keymap::KeyboardLayout;
 use rustamarine::Rustamarine;
+use std::cell::Cell;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -17,16 +19,23 @@ use std::os::unix::process::CommandExt;
 use std::process::Child;
 use std::process::Command;
 use std::rc::Rc;
+use std::thread::sleep;
+use std::time::Duration;
 use zbus_systemd::login1::{ManagerProxy, SessionProxy};
 use zbus_systemd::zbus::zvariant::Value;
 use zbus_systemd::zbus::Connection;
 
 #[derive(Debug, Clone)]
 pub struct DesktopEnvironmentFile {
+	/// Name of the desktop file without `.desktop`, what AccountsService stores as the session
+	id: String,
 	name: String,
 	command: String,
 }
 impl DesktopEnvironmentFile {
+	pub fn id(&self) -> &str {
+		self.id.as_str()
+	}
 	pub fn name(&self) -> &str {
 		self.name.as_str()
 	}
@@ -42,8 +51,11 @@ pub enum SessionStatus {
 }
 pub struct Session {
 	process: RefCell<Child>,
-	tty: TTYInfo,
+	/// `None` in nested mode, where the session runs inside the same compositor as tibs
+	tty: Option<TTYInfo>,
 	user_id: u32,
+	/// The end of the session was written to utmp and wtmp
+	logged_out: Cell<bool>,
 }
 
 impl Session {
@@ -51,6 +63,8 @@ impl Session {
 		uid: u32,
 		tty: TTYInfo,
 		session_file: &DesktopEnvironmentFile,
+		keyboard_layout: Option<&KeyboardLayout>,
+		language: Option<&str>,
 	) -> color_eyre::Result<Session> {
 		let session_file = session_file.clone();
 		tty.make_current().unwrap();
@@ -60,12 +74,18 @@ impl Session {
 		// 	std::hint::spin_loop();
 		// }
 		let tty_fd = tty.fd.as_raw_fd();
+		let mut command = Command::new("bash");
+		// .args(["-c", "Hyprland" /* &session_file.command*/])
+		command
+			.env("XDG_SESSION_TYPE", "wayland")
+			.env("XDG_VTNR", tty.number.to_string())
+			.env("XDG_SEAT", "seat0")
+			// The session picks its own GPUs, not the one tibs was told to use
+			.env_remove("AQ_DRM_DEVICES");
+		set_keyboard_layout_env(&mut command, keyboard_layout);
+		set_language_env(&mut command, language);
 		let process = RefCell::new(
-			Command::new("bash")
-				// .args(["-c", "Hyprland" /* &session_file.command*/])
-				.env("XDG_SESSION_TYPE", "wayland")
-				.env("XDG_VTNR", tty.number.to_string())
-				.env("XDG_SEAT", "seat0")
+			command
 				.before_exec(move || {
 					unsafe { setsid() };
 
@@ -122,18 +142,57 @@ impl Session {
 				})
 				.spawn()?,
 		);
+		record_login(&username, uid, process.borrow().id(), tty.number);
 		Ok(Self {
 			process,
-			tty,
+			tty: Some(tty),
 			user_id: uid,
+			logged_out: Cell::new(false),
+		})
+	}
+	/// Runs the session as a nested compositor, without switching ttys or users.
+	fn new_nested(
+		uid: u32,
+		session_file: &DesktopEnvironmentFile,
+		keyboard_layout: Option<&KeyboardLayout>,
+		language: Option<&str>,
+	) -> color_eyre::Result<Session> {
+		println!(
+			"[INFO] Starting {} nested: {}",
+			session_file.name, session_file.command
+		);
+		let mut command = Command::new("sh");
+		command
+			.args(["-c", &session_file.command])
+			.env_remove("AQ_DRM_DEVICES");
+		set_keyboard_layout_env(&mut command, keyboard_layout);
+		set_language_env(&mut command, language);
+		let process = command.spawn()?;
+		Ok(Self {
+			process: RefCell::new(process),
+			tty: None,
+			user_id: uid,
+			logged_out: Cell::new(false),
 		})
 	}
 	pub fn status(&self) -> SessionStatus {
-		match self.process.borrow_mut().try_wait() {
+		let status = match self.process.borrow_mut().try_wait() {
 			Ok(Some(code)) if code.success() => SessionStatus::ShutdownGracefully,
 			Ok(Some(_)) => SessionStatus::Crashed,
 			Ok(None) => SessionStatus::Running,
 			Err(_) => SessionStatus::Crashed,
+		};
+		if status != SessionStatus::Running {
+			self.record_logout();
+		}
+		status
+	}
+	/// Nested sessions aren't logins, only the ones on a tty are recorded.
+	fn record_logout(&self) {
+		if let Some(tty) = &self.tty {
+			if !self.logged_out.replace(true) {
+				record_logout(self.process.borrow().id(), tty.number);
+			}
 		}
 	}
 	pub fn user_id(&self) -> u32 {
@@ -141,13 +200,33 @@ impl Session {
 	}
 }
 
+/// Makes the compositor of the session start with the layout picked on the login screen.
+fn set_keyboard_layout_env(command: &mut Command, keyboard_layout: Option<&KeyboardLayout>) {
+	if let Some(keyboard_layout) = keyboard_layout {
+		command
+			.env("XKB_DEFAULT_LAYOUT", &keyboard_layout.layout)
+			.env("XKB_DEFAULT_VARIANT", &keyboard_layout.variant);
+	}
+}
+
+/// Starts the session in the language the user picked in AccountsService.
+fn set_language_env(command: &mut Command, language: Option<&str>) {
+	if let Some(language) = language {
+		command.env("LANG", language);
+	}
+}
+
 impl Drop for Session {
 	fn drop(&mut self) {
 		match self.status() {
 			SessionStatus::Running => {
 				self.process.borrow_mut().kill().ok();
+				self.record_logout();
+				let Some(tty) = &self.tty else {
+					return;
+				};
 				let current_tty = TTYInfo::get_active_tty_number();
-				if self.tty.number == current_tty {
+				if tty.number == current_tty {
 					println!("[WARN] Dropped session while still inside the session's tty: {current_tty}");
 				}
 			}
@@ -158,7 +237,8 @@ impl Drop for Session {
 
 pub struct SessionManager {
 	sessions: HashMap<u32, Rc<Session>>,
-	tibs_tty: u16,
+	/// `None` in nested mode, tibs is then always considered to be on screen
+	tibs_tty: Option<u16>,
 	wayland_desktop_environments_cache: Vec<DesktopEnvironmentFile>,
 }
 
@@ -186,11 +266,12 @@ impl SessionManager {
 			})
 			.filter_map(|entry| {
 				let path = entry.path();
+				let id = path.file_stem()?.to_string_lossy().into_owned();
 				let entry = parse_entry(&path).ok()?;
 				let section = entry.section("Desktop Entry");
 				let name = section.attr("Name")?.to_string();
 				let command = section.attr("Exec")?.to_string();
-				Some(DesktopEnvironmentFile { name, command })
+				Some(DesktopEnvironmentFile { id, name, command })
 			})
 			.collect()
 	}
@@ -203,7 +284,15 @@ impl SessionManager {
 	pub fn new() -> Self {
 		Self {
 			sessions: Default::default(),
-			tibs_tty: TTYInfo::get_active_tty_number(),
+			tibs_tty: Some(TTYInfo::get_active_tty_number()),
+			wayland_desktop_environments_cache: Self::discover_wayland_desktop_environments(),
+		}
+	}
+	/// Session manager that doesn't own a tty, sessions are started as nested compositors.
+	pub fn nested() -> Self {
+		Self {
+			sessions: Default::default(),
+			tibs_tty: None,
 			wayland_desktop_environments_cache: Self::discover_wayland_desktop_environments(),
 		}
 	}
@@ -213,11 +302,11 @@ impl SessionManager {
 			.sessions
 			.values()
 			.filter(|s| matches!(s.status(), SessionStatus::Running))
-			.map(|s| s.tty.number)
+			.filter_map(|s| s.tty.as_ref().map(|tty| tty.number))
 			.collect::<HashSet<_>>();
 		(1..64u16)
 			.into_iter()
-			.find_map(|i| (i != self.tibs_tty && !used_ttys.contains(&i)).then(|| TTYInfo::new(i)))
+			.find_map(|i| (Some(i) != self.tibs_tty && !used_ttys.contains(&i)).then(|| TTYInfo::new(i)))
 			.flatten()
 	}
 	pub fn start_session(
@@ -225,16 +314,23 @@ impl SessionManager {
 		login_manager: &LoginManager,
 		username: &str,
 		session_file: &DesktopEnvironmentFile,
+		keyboard_layout: Option<&KeyboardLayout>,
+		language: Option<&str>,
 	) -> color_eyre::Result<Rc<Session>> {
 		let Some(crate::login::LoginState::Authenticated(uid)) =
 			login_manager.get_current_login_state(username)
 		else {
 			bail!("Tried to start session without being authenticated (user={username})");
 		};
-		let free_tty = self
-			.next_tty()
-			.ok_or_eyre("There's no free tty's left for this session.")?;
-		let session = Session::new(uid, free_tty, session_file).map(Rc::new)?;
+		let session = if self.tibs_tty.is_none() {
+			Session::new_nested(uid, session_file, keyboard_layout, language)
+		} else {
+			let free_tty = self
+				.next_tty()
+				.ok_or_eyre("There's no free tty's left for this session.")?;
+			Session::new(uid, free_tty, session_file, keyboard_layout, language)
+		}
+		.map(Rc::new)?;
 		self.sessions.insert(uid, Rc::clone(&session));
 		Ok(session)
 	}
@@ -254,6 +350,20 @@ impl SessionManager {
 			.is_some_and(|s| matches!(s.status(), SessionStatus::Crashed))
 	}
 	pub fn is_on_tibs_tty(&self) -> bool {
-		self.tibs_tty == TTYInfo::get_active_tty_number()
+		self
+			.tibs_tty
+			.is_none_or(|tty| tty == TTYInfo::get_active_tty_number())
+	}
+	/// Blocks until the user switches back to the tty of tibs.
+	pub fn wait_for_tibs_tty(&self) {
+		let Some(tty) = self.tibs_tty else {
+			return;
+		};
+		if let Err(e) = TTYInfo::wait_active(tty) {
+			println!("[WARN] Failed to wait for tty {tty}, polling it instead: {e}");
+			while !self.is_on_tibs_tty() {
+				sleep(Duration::from_millis(50));
+			}
+		}
 	}
 }
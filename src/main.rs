@@ -1,10 +1,13 @@
 #![allow(unsafe_op_in_unsafe_fn)]
 
-use background::Background;
 #[path = "components/background.rs"]
 pub mod background;
+#[path = "utils/config.rs"]
+pub mod config;
 #[path = "components/custom_elements.rs"]
 pub mod custom_elements;
+#[path = "utils/focus.rs"]
+pub mod focus;
 #[path = "utils/fps_counter.rs"]
 pub mod fps_counter;
 #[path = "utils/gl.rs"]
@@ -33,6 +36,25 @@ pub mod textbox;
 pub mod app;
 #[path = "utils/frame_pool.rs"]
 pub mod frame_pool;
+#[path = "utils/gpu.rs"]
+pub mod gpu;
+pub mod headless;
+#[path = "utils/input.rs"]
+pub mod input;
+#[path = "utils/input_devices.rs"]
+pub mod input_devices;
+#[path = "utils/keybindings.rs"]
+pub mod keybindings;
+#[path = "utils/keyboard_layouts.rs"]
+pub mod keyboard_layouts;
+#[path = "components/on_screen_keyboard.rs"]
+pub mod on_screen_keyboard;
+#[path = "utils/outputs.rs"]
+pub mod outputs;
+#[path = "screens/text_mode.rs"]
+pub mod text_mode;
+#[path = "utils/touch.rs"]
+pub mod touch;
 #[path = "utils/tty.rs"]
 pub mod tty;
 
@@ -40,111 +62,284 @@ pub type TibsClayScope<'clay, 'render> =
 	SkiaClayScope<'clay, 'render, custom_elements::CustomElements>;
 
 use crate::{
-	animation::{
-		easing::{ease_in_out_circ, ease_in_quad},
-		BasicAnimation, DelayAnimation,
-	},
-	cursor::Cursor,
-	frame_pool::FramePool,
-	loading_screen::LoadingScreen,
+	config::CONFIG,
 	login::{LoginManager, LoginScreen},
+	outputs::OutputLayout,
+	progress_watcher::ProgressWatcher,
 	session_manager::SessionManager,
-	skia::clay_renderer::{create_measure_text_function, SkiaClayScope},
+	skia::{
+		clay_renderer::{create_measure_text_function, SkiaClayScope},
+		fonts::Fonts,
+	},
 };
 use assets_manager::AssetCache;
-use skia_safe::{
-	font_style::{Slant, Weight, Width},
-	FontMgr, FontStyle, Typeface,
-};
+use color_eyre::eyre::{bail, OptionExt};
 use std::{
+	cell::Cell,
 	rc::Rc,
 	sync::{LazyLock, Mutex},
 	thread::sleep,
 	time::{Duration, Instant},
 };
 
-static UBUNTU_FONT: LazyLock<Typeface> = LazyLock::new(|| {
-	FontMgr::new()
-		.match_family_style("UbuntuSans NF", FontStyle::normal())
-		.unwrap()
-});
-static BOLD_UBUNTU_FONT: LazyLock<Typeface> = LazyLock::new(|| {
-	FontMgr::new()
-		.match_family_style("UbuntuSans NF", FontStyle::bold())
-		.unwrap()
-});
-static MEDIUM_UBUNTU_FONT: LazyLock<Typeface> = LazyLock::new(|| {
-	FontMgr::new()
-		.match_family_style(
-			"UbuntuSans NF",
-			FontStyle::new(Weight::MEDIUM, Width::NORMAL, Slant::Upright),
-		)
-		.unwrap()
+/// Fonts used by clay, see [`Fonts`] for the ids
+pub static FONTS: LazyLock<Fonts> = LazyLock::new(|| {
+	Fonts::load(
+		&CONFIG.fonts.family,
+		&CONFIG.fonts.fonts,
+		&CONFIG.assets_folder,
+	)
 });
-pub static FONTS: LazyLock<Vec<&Typeface>> =
-	LazyLock::new(|| vec![&UBUNTU_FONT, &BOLD_UBUNTU_FONT, &MEDIUM_UBUNTU_FONT]);
+
+fn configure_screen(screen: &mut rustamarine::screen::Screen) {
+	let settings = CONFIG.outputs.settings_for(screen.get_name());
+	outputs::configure_screen(screen, settings, CONFIG.outputs.ui_scale);
+}
+
+/// A greeter needs a real screen, tibs only runs in a window when it's nested.
+fn backend_options(nested: bool) -> rustamarine::Options {
+	use rustamarine::BackendRequest;
+	if nested {
+		return rustamarine::Options {
+			drm: BackendRequest::Disabled,
+			wayland: BackendRequest::Required,
+			headless: BackendRequest::Disabled,
+			drm_device: None,
+		};
+	}
+	rustamarine::Options {
+		drm: BackendRequest::Required,
+		wayland: BackendRequest::Disabled,
+		headless: BackendRequest::Optional,
+		drm_device: CONFIG.drm_device.clone(),
+	}
+}
 
 fn main() -> color_eyre::Result<()> {
 	color_eyre::install()?;
 	env_logger::init();
 
+	let args = std::env::args().collect::<Vec<_>>();
+	if args.get(1).is_some_and(|a| a == "--headless") {
+		let script = args
+			.get(2)
+			.ok_or_eyre("Usage: tibs --headless <script> [output folder]")?;
+		return headless::run(script.as_ref(), args.get(3).map(std::path::Path::new));
+	}
+	// Runs as a window inside of a compositor, for developing themes without rebooting
+	let nested = args.get(1).is_some_and(|a| a == "--nested");
+	if nested {
+		println!("[INFO] Running nested, sessions are started inside of the current compositor");
+	}
+
+	let login_manager = if nested {
+		LoginManager::nested()
+	} else {
+		LoginManager::new()
+	};
+	let mut session_manager = if nested {
+		SessionManager::nested()
+	} else {
+		SessionManager::new()
+	};
+	loop {
+		match run_graphics(nested, login_manager.clone(), session_manager)? {
+			GraphicsExit::Exited => return Ok(()),
+			// There's no tty to fall back to when running nested
+			GraphicsExit::Failed { error, .. } if nested => bail!(error),
+			GraphicsExit::Failed {
+				error,
+				session_manager: manager,
+			} => {
+				log::error!("Graphics failed, falling back to the text-mode greeter: {error}");
+				session_manager = manager;
+				text_mode::run(&error, &login_manager, &mut session_manager)?;
+			}
+		}
+	}
+}
+
+enum GraphicsExit {
+	/// The app asked to quit
+	Exited,
+	/// The graphics couldn't be initialized, the session manager is handed back so the sessions
+	/// that are already running are kept track of
+	Failed {
+		error: String,
+		session_manager: SessionManager,
+	},
+}
+
+/// Runs the graphical greeter until it exits or the graphics fail.
+fn run_graphics(
+	nested: bool,
+	login_manager: LoginManager,
+	session_manager: SessionManager,
+) -> color_eyre::Result<GraphicsExit> {
+	let failed = |error: String, session_manager| {
+		Ok(GraphicsExit::Failed {
+			error,
+			session_manager,
+		})
+	};
 	// Create clay layout
 	let mut clay = clay_layout::Clay::new((0.0, 0.0).into());
 	clay.set_measure_text_function(create_measure_text_function(&FONTS));
-	let mut rmar = rustamarine::Rustamarine::new();
-	gl::load_with(|n| rmar.get_opengl_proc_address(n));
+	let mut rmar = match start_rustamarine(backend_options(nested)) {
+		Ok(rmar) => rmar,
+		Err(error) => return failed(error, session_manager),
+	};
+
+	// Outputs are configured upfront so images can be loaded with the right scale
+	for screen in &mut rmar.screens() {
+		configure_screen(screen);
+	}
+	let scale = OutputLayout::from_screens(&rmar.screens(), None).max_scale();
 
 	// Create assets
-	let assets = Rc::new(AssetCache::new(
-		std::env::var("TIBS_ASSETS_FOLDER").unwrap_or("assets".into()),
-	)?);
+	let assets = Rc::new(AssetCache::new(&CONFIG.assets_folder)?);
 
 	// Create app state
-	let app_state = Mutex::new(app::AppState {
-		boot_progress: progress_watcher::ProgressWatcher::new(),
-		fps_counter: fps_counter::FPSCounter::new(),
-		last_time: std::time::Instant::now(),
-		scroll_velocity: (0., 0.),
+	let login_screen = LoginScreen::new(&assets, scale);
+	let boot_progress = if nested {
+		ProgressWatcher::fake_progress_bar()
+	} else {
+		ProgressWatcher::new()
+	};
+	let mut app_state = app::AppState::new(
 		clay,
-		skia: None,
-		loading_screen: LoadingScreen::new(&assets),
-		login_screen: LoginScreen::new(&assets),
-		cursor: Cursor::new(None),
-		screen_slide_animation: BasicAnimation::new("screen_slide", 1.5, ease_in_out_circ),
-		show_login_screen: false,
-		screen_slide_animation_progress: 0.0,
-		devtools: false,
-		background: Background::new(Rc::clone(&assets)),
 		assets,
-		should_exit: false,
-		login_manager: LoginManager::new(),
-		session_manager: SessionManager::new(),
-		login_animation: seq!(
-			BasicAnimation::new("hide_ui", 0.2, ease_in_quad),
-			DelayAnimation::new(
-				0.1,
-				BasicAnimation::new("hide_background", 0.3, ease_in_quad)
-			)
-		)
-		.into(),
-		login_animation_direction: Default::default(),
-		frame_pool: FramePool::new(),
-	});
-	while !app_state.lock().unwrap().should_exit {
-		let mut screens = rmar.screens();
-		let Some(mut screen) = screens.first_mut() else {
+		scale,
+		boot_progress,
+		login_manager,
+		session_manager,
+		login_screen,
+	);
+	app_state.dev_mode |= nested;
+	let app_state = Mutex::new(app_state);
+	// Outputs can be plugged in and out at any time
+	let screens_changed = Rc::new(Cell::new(true));
+	watch_screens(&mut rmar, &screens_changed);
+	// Moving to another GPU is only done when it was picked automatically
+	let can_change_gpu = !nested && CONFIG.drm_device.is_none();
+	loop {
+		// Checked right away too, the GPU might have shown up while tibs was starting
+		let mut drm_devices_changed = true;
+		// The device to move to, or why the graphics failed
+		let next_gpu = loop {
+			if app_state.lock().unwrap().should_exit {
+				return Ok(GraphicsExit::Exited);
+			}
+			if let Some(error) = app_state.lock().unwrap().graphics_error.take() {
+				break Err(error);
+			}
+			// The driver of the GPU replaced the boot framebuffer, or the GPU was unplugged
+			if std::mem::take(&mut drm_devices_changed) && can_change_gpu {
+				let devices = rustamarine::drm::devices();
+				let target = rmar
+					.drm_device()
+					.and_then(|current| gpu::migration_target(current, &devices));
+				if let Some(target) = target {
+					break Ok(target.path.clone());
+				}
+			}
+			let mut screens = rmar.screens();
+
+			// Set render callbacks, every output is drawn separately when it's ready for a new frame
+			if screens_changed.replace(false) {
+				for screen in &mut screens {
+					configure_screen(screen);
+					screen.set_on_render(|mut screen| {
+						app_state.lock().unwrap().render_output(&mut screen);
+					});
+				}
+			}
+
+			// Modes can also change without a hotplug, so the layout is checked every time
+			app_state
+				.lock()
+				.unwrap()
+				.set_outputs(OutputLayout::from_screens(
+					&screens,
+					CONFIG.outputs.primary.clone(),
+				));
+
 			rmar.poll_events();
-			continue;
+			drm_devices_changed |= !rmar.take_drm_events().is_empty();
 		};
 
-		// Set render callback
-		screen.set_on_render(|mut screen| {
-			let mut app_state = app_state.lock().unwrap();
-			app_state.update(&mut screen.get_rustamarine(), &mut screen);
-			app_state.render(&mut screen);
-		});
+		// The GL context goes away with rustamarine, everything else in the app state (animations,
+		// typed passwords, logins) is kept
+		app_state.lock().unwrap().release_gpu();
+		let mouse = (rmar.get_mouse_x(), rmar.get_mouse_y());
+		let keyboard_layout = rmar.active_keyboard_layout();
+		let locks = rmar.locked_modifiers();
+		// The render callbacks borrow the app state
+		drop(rmar);
+		let error = match next_gpu {
+			Ok(device) => {
+				println!("[INFO] Moving to {}", device.display());
+				let options = rustamarine::Options {
+					drm_device: Some(device),
+					..backend_options(nested)
+				};
+				match start_rustamarine(options) {
+					Ok(new_rmar) => {
+						rmar = new_rmar;
+						rmar.set_mouse_x(mouse.0);
+						rmar.set_mouse_y(mouse.1);
+						// Otherwise the layout and locks go back to the ones of the config
+						rmar.set_active_keyboard_layout(keyboard_layout);
+						rmar.set_locked(rustamarine::events::Modifiers::LOCKS, false);
+						rmar.set_locked(locks, true);
+						watch_screens(&mut rmar, &screens_changed);
+						screens_changed.set(true);
+						continue;
+					}
+					Err(error) => error,
+				}
+			}
+			Err(error) => error,
+		};
+		return failed(error, app_state.into_inner().unwrap().session_manager);
+	}
+}
 
-		rmar.poll_events();
+/// Creates rustamarine and loads the OpenGL functions of its context.
+fn start_rustamarine(options: rustamarine::Options) -> Result<rustamarine::Rustamarine, String> {
+	let mut rmar = rustamarine::Rustamarine::new(options).map_err(|e| e.to_string())?;
+	gl::load_with(|n| rmar.get_opengl_proc_address(n));
+	if !gl::Clear::is_loaded() || !gl::GetString::is_loaded() {
+		return Err("Failed to load the OpenGL functions".into());
+	}
+	if !CONFIG.keyboard_layouts.is_empty() && !rmar.set_keyboard_layouts(&CONFIG.keyboard_layouts) {
+		log::warn!(
+			"Failed to set the keyboard layouts {:?}",
+			CONFIG.keyboard_layouts
+		);
+	}
+	if CONFIG.numlock {
+		rmar.set_locked(rustamarine::events::Modifiers::NUM_LOCK, true);
 	}
-	Ok(())
+	input_devices::InputSettings::load().apply(&mut rmar);
+	Ok(rmar)
+}
+
+/// Logs the outputs that are plugged in and out, and sets `screens_changed` so the new ones get a
+/// render callback.
+fn watch_screens(rmar: &mut rustamarine::Rustamarine, screens_changed: &Rc<Cell<bool>>) {
+	rmar.set_on_screen_added({
+		let screens_changed = Rc::clone(screens_changed);
+		move |_, name| {
+			println!("[INFO] Output {name} connected");
+			screens_changed.set(true);
+		}
+	});
+	rmar.set_on_screen_removed({
+		let screens_changed = Rc::clone(screens_changed);
+		move |_, name| {
+			println!("[INFO] Output {name} disconnected");
+			screens_changed.set(true);
+		}
+	});
 }
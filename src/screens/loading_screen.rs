@@ -34,16 +34,15 @@ pub struct LoadingScreen {
 }
 
 impl LoadingScreen {
-	pub fn new(assets: &AssetCache) -> Self {
-		let SkiaImageAsset(success_icon) = assets
-			.load_owned("icons.check")
-			.expect("Failed to load check icon");
-		let SkiaImageAsset(alert_icon) = assets
-			.load_owned("icons.triangle-alert")
-			.expect("Failed to load warning icon");
-		let SkiaImageAsset(logo) = assets
-			.load_owned("logo")
-			.expect("Failed to load check icon");
+	/// `scale` is the biggest scale of the outputs, used to pick the resolution of the icons.
+	pub fn new(assets: &AssetCache, scale: f32) -> Self {
+		let SkiaImageAsset(success_icon) =
+			SkiaImageAsset::load_scaled(assets, "icons.check", scale).expect("Failed to load check icon");
+		let SkiaImageAsset(alert_icon) =
+			SkiaImageAsset::load_scaled(assets, "icons.triangle-alert", scale)
+				.expect("Failed to load warning icon");
+		let SkiaImageAsset(logo) =
+			SkiaImageAsset::load_scaled(assets, "logo", scale).expect("Failed to load check icon");
 		let (progress_bar_animation, progress_bar_sender) = ProgressBarAnimation::new("progress", 2.5);
 		Self {
 			loading_animation: all!(
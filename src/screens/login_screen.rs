@@ -1,21 +1,33 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use super::{LoginManager, LoginState};
+use super::{
+	format_login_time, passwd_real_names, sort_by_last_login, AccountsWatcher, LoginManager,
+	LoginPrompt, LoginState, PromptKind, UserAccount,
+};
 use crate::animation::colors::hsl_to_rgb;
+use crate::config::CONFIG;
+use crate::focus::{FocusId, FocusManager};
 use crate::frame_pool::FrameAllocator;
+use crate::input::Input;
+use crate::input_devices;
+use crate::keybindings::Action;
+use crate::keyboard_layouts::KeyboardLayouts;
+use crate::on_screen_keyboard::OnScreenKeyboard;
 use crate::session_manager::{self, DesktopEnvironmentFile, SessionManager, SessionStatus};
 use crate::textbox::Textbox;
-use crate::{custom_elements::CustomElements, skia::asset_loaders::SkiaImageAsset};
+use crate::{
+	custom_elements::{CustomElements, KeyIcon},
+	skia::{asset_loaders::SkiaImageAsset, fonts::Fonts},
+};
 use crate::{format_id, frame_alloc_format, TibsClayScope};
-use assets_manager::AssetCache;
+use assets_manager::{Asset, AssetCache};
 use clay_layout::fit;
 use clay_layout::text::TextElementConfig;
-use rustamarine::keys::{KEY_KP_Enter, KEY_Return};
-use rustamarine::Rustamarine;
+use rustamarine::keys::{KEY_Escape, KEY_KP_Enter, KEY_Return};
 use skia_safe::Image;
+use uzers::all_users;
 use uzers::os::unix::UserExt;
-use uzers::{all_users, User};
 
 use clay_layout::{
 	elements::{FloatingAttachPointType, FloatingAttachToElement},
@@ -51,31 +63,91 @@ impl KnownDEs {
 // --------- Login Screen
 
 pub struct LoginScreen {
-	user_list: Vec<User>,
-	selected_user: u32,
+	user_list: Vec<UserAccount>,
+	accounts_watcher: AccountsWatcher,
+	/// "last login: …" of the users in `user_list`, by uid
+	last_login_labels: HashMap<u32, String>,
+	/// `None` until the username typed in "Other user…" is found
+	selected_user: Option<u32>,
 	selected_username: String,
+	/// The username is typed in `username_input` instead of picked from the list
+	other_user: bool,
+	username_input: Textbox,
+	/// Set if the typed username doesn't exist
+	username_error: Option<String>,
 	login_icon: Image,
 	eye_icon: Image,
 	eye_off_icon: Image,
 	avatars: HashMap<u32, Image>,
+	wallpapers: HashMap<u32, Image>,
 	password_input: Textbox,
+	/// What PAM asks after the password, answered in `password_input`
+	prompt: Option<LoginPrompt>,
 	de_icons: HashMap<KnownDEs, SkiaImageAsset>,
 	is_desktop_environment_popup_open: bool,
 	selected_de: Option<DesktopEnvironmentFile>,
 	session_open_error: Option<String>,
+	pub keyboard_layouts: KeyboardLayouts,
+	is_keyboard_layout_popup_open: bool,
+	pub on_screen_keyboard: OnScreenKeyboard,
+	pub focus: FocusManager,
+}
+
+const USERNAME_INPUT: FocusId = FocusId::new("username-input");
+const PASSWORD_INPUT: FocusId = FocusId::new("login-input");
+const SHOW_PASSWORD: FocusId = FocusId::new("show-password");
+const LOGIN_BUTTON: FocusId = FocusId::new("login-button");
+const ON_SCREEN_KEYBOARD_TOGGLE: FocusId = FocusId::new("on-screen-keyboard-toggle");
+const KEYBOARD_LAYOUT_INDICATOR: FocusId = FocusId::new("keyboard-layout-indicator");
+const fn user_item(i: usize) -> FocusId {
+	FocusId::indexed("user-item", i as u32)
+}
+const fn desktop_environment(i: usize) -> FocusId {
+	FocusId::indexed("desktop-environment", i as u32)
+}
+const fn keyboard_layout(i: usize) -> FocusId {
+	FocusId::indexed("keyboard-layout", i as u32)
 }
 
 fn is_user_uid(uid: u32) -> bool {
 	return uid >= 1000 && uid < 65534;
 }
 
-fn load_avatar(u: &User) -> Option<Image> {
-	let face_file_path = u.home_dir().join(".face");
-	let face_image_data = skia_safe::Data::from_filename(face_file_path)?;
+fn load_avatar(u: &UserAccount) -> Option<Image> {
+	let face_image_data = skia_safe::Data::from_filename(u.avatar_file())?;
 	let face_image = Image::from_encoded(face_image_data)?;
 	return Some(face_image);
 }
 
+fn last_login_labels(users: &[UserAccount]) -> HashMap<u32, String> {
+	users
+		.iter()
+		.filter_map(|u| {
+			let label = format!("last login: {}", format_login_time(u.last_login?));
+			Some((u.uid, label))
+		})
+		.collect()
+}
+
+fn load_wallpaper(u: &UserAccount) -> Option<Image> {
+	/// Far more than any sensible wallpaper needs
+	const MAX_SIZE: u64 = 64 * 1024 * 1024;
+	if let Some(login_background) = &u.login_background {
+		let data = skia_safe::Data::from_filename(login_background)?;
+		return Image::from_encoded(data);
+	}
+	// Owned by the user, who could make it a symlink to any file or a FIFO
+	let config_dir = u.home_dir.join(".config/tibs");
+	SkiaImageAsset::EXTENSIONS.iter().find_map(|ext| {
+		let data = input_devices::read_user_file(
+			&config_dir.join(format!("background.{ext}")),
+			u.uid,
+			MAX_SIZE,
+		)?;
+		Image::from_encoded(skia_safe::Data::new_copy(&data))
+	})
+}
+
 pub enum LoginScreenOutMessage {
 	StartLoginAnimation,
 }
@@ -83,48 +155,107 @@ impl LoginScreen {
 	pub fn username(&self) -> &str {
 		&self.selected_username
 	}
-	pub fn new(assets: &AssetCache) -> Self {
-		let SkiaImageAsset(login_icon) = assets
-			.load_owned("icons.login")
+	/// The wallpaper the selected user set in `~/.config/tibs/background.*`, if any.
+	pub fn selected_user_wallpaper(&self) -> Option<&Image> {
+		self.wallpapers.get(&self.selected_user?)
+	}
+	/// `scale` is the biggest scale of the outputs, used to pick the resolution of the icons.
+	pub fn new(assets: &AssetCache, scale: f32) -> Self {
+		if CONFIG.hide_user_list {
+			return Self::with_users(assets, scale, vec![]);
+		}
+		// Shown until AccountsService answers, or for good if it isn't running
+		let real_names = passwd_real_names();
+		let mut user_list = unsafe { all_users() }
+			.filter(|u| is_user_uid(u.uid()) && !u.shell().ends_with("nologin"))
+			.map(|u| UserAccount::from_passwd(&u, &real_names))
+			.collect::<Vec<UserAccount>>();
+		sort_by_last_login(&mut user_list);
+		Self {
+			accounts_watcher: AccountsWatcher::new(user_list.clone()),
+			..Self::with_users(assets, scale, user_list)
+		}
+	}
+	/// Login screen showing `user_list` instead of the users of the system. The username has to be
+	/// typed if it's empty.
+	pub fn with_users(assets: &AssetCache, scale: f32, user_list: Vec<UserAccount>) -> Self {
+		let SkiaImageAsset(login_icon) = SkiaImageAsset::load_scaled(assets, "icons.login", scale)
 			.expect("Failed to load icons.login");
-		let SkiaImageAsset(eye_icon) = assets
-			.load_owned("icons.eye")
-			.expect("Failed to load icons.eye");
-		let SkiaImageAsset(eye_off_icon) = assets
-			.load_owned("icons.eye-off")
+		let SkiaImageAsset(eye_icon) =
+			SkiaImageAsset::load_scaled(assets, "icons.eye", scale).expect("Failed to load icons.eye");
+		let SkiaImageAsset(eye_off_icon) = SkiaImageAsset::load_scaled(assets, "icons.eye-off", scale)
 			.expect("Failed to load icons.eye-off");
+		let SkiaImageAsset(caps_lock_icon) =
+			SkiaImageAsset::load_scaled(assets, "icons.caps-lock", scale)
+				.expect("Failed to load icons.caps-lock");
 
 		let mut de_icons: HashMap<KnownDEs, SkiaImageAsset> = HashMap::new();
-		de_icons.insert(KnownDEs::KDE, assets.load_owned("icons.kde").unwrap());
-		de_icons.insert(KnownDEs::GNOME, assets.load_owned("icons.gnome").unwrap());
+		de_icons.insert(
+			KnownDEs::KDE,
+			SkiaImageAsset::load_scaled(assets, "icons.kde", scale).unwrap(),
+		);
+		de_icons.insert(
+			KnownDEs::GNOME,
+			SkiaImageAsset::load_scaled(assets, "icons.gnome", scale).unwrap(),
+		);
 		de_icons.insert(
 			KnownDEs::Hyprland,
-			assets.load_owned("icons.hyprland").unwrap(),
+			SkiaImageAsset::load_scaled(assets, "icons.hyprland", scale).unwrap(),
+		);
+		de_icons.insert(
+			KnownDEs::HyprDE,
+			SkiaImageAsset::load_scaled(assets, "icons.hyprde", scale).unwrap(),
 		);
-		de_icons.insert(KnownDEs::HyprDE, assets.load_owned("icons.hyprde").unwrap());
 		de_icons.insert(
 			KnownDEs::Unknown,
-			assets.load_owned("icons.unknown").unwrap(),
+			SkiaImageAsset::load_scaled(assets, "icons.unknown", scale).unwrap(),
 		);
-		let user_list = unsafe { all_users() }
-			.filter(|u| is_user_uid(u.uid()) && !u.shell().ends_with("nologin"))
-			.collect::<Vec<User>>();
-
-		let selected_user = user_list[0].uid();
-		let selected_username = user_list[0].name().to_str().unwrap().to_string();
+		let selected_user = user_list.first().map(|u| u.uid);
+		let selected_username = user_list
+			.first()
+			.map(|u| u.username.clone())
+			.unwrap_or_default();
+		let mut keyboard_layouts = KeyboardLayouts::new(
+			CONFIG.keyboard_layouts.clone(),
+			Some(CONFIG.state_folder.join("keyboard-layouts")),
+		);
+		keyboard_layouts.restore(&selected_username);
+		let other_user = user_list.is_empty();
 		Self {
 			avatars: user_list
 				.iter()
-				.filter_map(|u| Some((u.uid(), load_avatar(u)?)))
+				.filter_map(|u| Some((u.uid, load_avatar(u)?)))
 				.collect(),
+			wallpapers: if CONFIG.background.user_wallpapers {
+				user_list
+					.iter()
+					.filter_map(|u| Some((u.uid, load_wallpaper(u)?)))
+					.collect()
+			} else {
+				HashMap::new()
+			},
+			last_login_labels: last_login_labels(&user_list),
 			user_list,
+			accounts_watcher: AccountsWatcher::disabled(),
 			selected_user,
 			selected_username,
+			other_user,
+			username_input: Textbox::new("username-input", false),
+			username_error: None,
 			login_icon,
-			password_input: Textbox::new("login-input", true),
+			password_input: Textbox::new("login-input", true).with_caps_lock_icon(caps_lock_icon),
+			prompt: None,
 			eye_icon,
 			eye_off_icon,
 			de_icons,
+			keyboard_layouts,
+			is_keyboard_layout_popup_open: false,
+			on_screen_keyboard: OnScreenKeyboard::default(),
+			focus: FocusManager::new(if other_user {
+				USERNAME_INPUT
+			} else {
+				PASSWORD_INPUT
+			}),
 
 			// Don't forget to reset these fields when switching users
 			is_desktop_environment_popup_open: false,
@@ -135,34 +266,85 @@ impl LoginScreen {
 	pub fn update<'clay, 'render>(
 		&'render mut self,
 		c: &mut clay_layout::Clay,
-		rmar: &Rustamarine,
+		input: &dyn Input,
+		mouse_position: (f32, f32),
 		login_manager: &mut LoginManager,
 		session_manager: &SessionManager,
 	) where
 		'clay: 'render,
 	{
-		if rmar.is_mouse_button_pressed(0) && !c.pointer_over(c.id("desktop-environments-popup")) {
+		if input.is_mouse_button_pressed(0) && !c.pointer_over(c.id("desktop-environments-popup")) {
 			self.is_desktop_environment_popup_open = false;
 		}
+		if let Some(users) = self.accounts_watcher.poll() {
+			self.set_users(users, login_manager, session_manager);
+		}
 		if let Some(selected) = self
 			.user_list
 			.iter()
-			.find(|u| u.uid() == self.selected_user)
+			.find(|u| Some(u.uid) == self.selected_user)
+			.filter(|_| !self.other_user)
 		{
-			let n = selected.name().to_str().unwrap();
+			let n = selected.username.as_str();
 			if self.selected_username != n {
 				self.selected_username = n.to_string();
+				self.keyboard_layouts.restore(n);
 			}
 		}
-		self.password_input.update(rmar, &mut *c);
-		if c.pointer_over(c.id("show-password")) && rmar.is_mouse_button_released(0) {
+		self.update_other_user(login_manager);
+		self.update_prompt(login_manager);
+		self.update_focus(input, login_manager, session_manager);
+		self.update_keyboard_layouts(c, input);
+		self.on_screen_keyboard.update(c, input);
+		if self.focus.activated(ON_SCREEN_KEYBOARD_TOGGLE, input) {
+			self.on_screen_keyboard.visible = true;
+		}
+		let input = &self.on_screen_keyboard.input(input);
+		if input.is_mouse_button_pressed(0) && c.pointer_over(c.id("login-input")) {
+			self.focus.focus(PASSWORD_INPUT);
+		}
+		if input.is_mouse_button_pressed(0) && c.pointer_over(c.id("username-input")) {
+			self.focus.focus(USERNAME_INPUT);
+		}
+		self
+			.username_input
+			.set_focused(self.focus.is_focused(USERNAME_INPUT));
+		self.username_input.disabled = self.is_logging(login_manager, session_manager);
+		self.username_input.update(input, mouse_position, &mut *c);
+		self
+			.password_input
+			.set_focused(self.focus.is_focused(PASSWORD_INPUT));
+		self.password_input.update(input, mouse_position, &mut *c);
+		if (c.pointer_over(c.id("show-password")) && input.is_mouse_button_released(0))
+			|| self.focus.activated(SHOW_PASSWORD, input)
+		{
 			self.password_input.hide_input = !self.password_input.hide_input
 		}
-		if ((c.pointer_over(c.id("login-button")) && rmar.is_mouse_button_released(0))
+		// Before the login button, so the Enter that opens the popup doesn't also pick the first one
+		if self.is_desktop_environment_popup_open {
+			for (i, de) in session_manager
+				.get_desktop_environments_list()
+				.iter()
+				.enumerate()
+			{
+				if (c.pointer_over(c.id_index("desktop-environment", i as u32))
+					&& input.is_mouse_button_released(0))
+					|| self.focus.activated(desktop_environment(i), input)
+				{
+					self.on_de_select(de, login_manager, session_manager);
+					break;
+				}
+			}
+		}
+		let submitted = (c.pointer_over(c.id("login-button")) && input.is_mouse_button_released(0))
+			|| self.focus.activated(LOGIN_BUTTON, input)
 			|| (self.password_input.is_focused()
-				&& (rmar.is_key_pressed(KEY_Return) || rmar.is_key_pressed(KEY_KP_Enter))))
-			&& !self.password_input.disabled
-		{
+				&& (input.is_key_pressed(KEY_Return) || input.is_key_pressed(KEY_KP_Enter)));
+		if submitted && self.is_answering_prompt() {
+			login_manager.answer_prompt(&self.selected_username, self.password_input.text());
+			self.password_input.clear();
+			self.prompt = None;
+		} else if submitted && !self.password_input.disabled && self.find_other_user() {
 			if session_manager.get_desktop_environments_list().len() == 1 {
 				self.on_de_select(
 					session_manager
@@ -173,19 +355,291 @@ impl LoginScreen {
 					session_manager,
 				);
 			} else {
-				self.is_desktop_environment_popup_open = true
+				self.is_desktop_environment_popup_open = true;
+				// The session the user picked last, if AccountsService knows it
+				let last_session = self.selected_account().and_then(|u| u.session.as_deref());
+				let i = session_manager
+					.get_desktop_environments_list()
+					.iter()
+					.position(|de| Some(de.id()) == last_session)
+					.unwrap_or(0);
+				self.focus.focus(desktop_environment(i));
+			}
+		}
+		self.password_input.disabled =
+			self.is_logging(login_manager, session_manager) && !self.is_answering_prompt();
+		// After the login button, so the password isn't sent in the same frame
+		if self.username_input.is_focused()
+			&& (input.is_key_pressed(KEY_Return) || input.is_key_pressed(KEY_KP_Enter))
+		{
+			self.focus.focus(PASSWORD_INPUT);
+		}
+		// Last, so the Enter that picks a user doesn't also log in with the empty password
+		if let Some(i) = (0..self.user_list.len() + 1).find(|&i| {
+			(c.pointer_over(c.id_index("user-item", i as u32)) && input.is_mouse_button_released(0))
+				|| self.focus.activated(user_item(i), input)
+		}) {
+			self.select_user(i, login_manager, session_manager);
+		}
+	}
+	/// Closes the popups or cancels what PAM asks with Escape and moves the focus with the keyboard.
+	fn update_focus(
+		&mut self,
+		input: &dyn Input,
+		login_manager: &LoginManager,
+		session_manager: &SessionManager,
+	) {
+		if input.is_key_pressed(KEY_Escape) {
+			if self.is_desktop_environment_popup_open {
+				self.is_desktop_environment_popup_open = false;
+				self.focus.focus(LOGIN_BUTTON);
+			} else if self.is_keyboard_layout_popup_open {
+				self.is_keyboard_layout_popup_open = false;
+				self.focus.focus(KEYBOARD_LAYOUT_INDICATOR);
+			} else if self.is_answering_prompt() {
+				// Makes the login fail instead of leaving PAM waiting for the answer
+				login_manager.reset_login_state(&self.selected_username);
+				self.prompt = None;
+				self.password_input.clear();
+				self.password_input.hide_input = true;
+				self.focus.focus(PASSWORD_INPUT);
 			}
 		}
-		self.password_input.disabled = self.is_logging(login_manager, session_manager);
-		for (i, de) in session_manager
-			.get_desktop_environments_list()
+		let order = self.focus_order(login_manager, session_manager);
+		self.focus.update(input, &order);
+	}
+	/// Elements that can be focused, in Tab order. The focus stays inside of open popups.
+	fn focus_order(
+		&self,
+		login_manager: &LoginManager,
+		session_manager: &SessionManager,
+	) -> Vec<FocusId> {
+		if self.is_logging(login_manager, session_manager) {
+			return if self.is_answering_prompt() {
+				vec![PASSWORD_INPUT, SHOW_PASSWORD, LOGIN_BUTTON]
+			} else {
+				vec![]
+			};
+		}
+		if self.is_desktop_environment_popup_open {
+			return (0..session_manager.get_desktop_environments_list().len())
+				.map(desktop_environment)
+				.collect();
+		}
+		if self.is_keyboard_layout_popup_open {
+			return (0..self.keyboard_layouts.len())
+				.map(keyboard_layout)
+				.collect();
+		}
+		let mut order = vec![];
+		if self.other_user {
+			order.push(USERNAME_INPUT);
+		}
+		order.extend([PASSWORD_INPUT, SHOW_PASSWORD, LOGIN_BUTTON]);
+		// The users and "Other user…"
+		if !self.user_list.is_empty() {
+			order.extend((0..=self.user_list.len()).map(user_item));
+		}
+		if !self.on_screen_keyboard.visible {
+			order.push(ON_SCREEN_KEYBOARD_TOGGLE);
+		}
+		if self.keyboard_layouts.len() > 1 {
+			order.push(KEYBOARD_LAYOUT_INDICATOR);
+		}
+		order
+	}
+	/// `true` if Escape closes a popup or cancels a prompt instead of doing something else.
+	pub fn handles_escape(&self) -> bool {
+		self.is_desktop_environment_popup_open
+			|| self.is_keyboard_layout_popup_open
+			|| self.is_answering_prompt()
+	}
+	/// Lets the password input answer what PAM asks after the password, like the PIN of a security
+	/// token.
+	fn update_prompt(&mut self, login_manager: &LoginManager) {
+		let prompt = login_manager.prompt(&self.selected_username);
+		if prompt == self.prompt {
+			return;
+		}
+		if let Some(prompt) = prompt.as_ref().filter(|p| p.expects_answer()) {
+			self.password_input.clear();
+			self.password_input.hide_input = prompt.kind == PromptKind::Secret;
+			self.focus.focus(PASSWORD_INPUT);
+		} else if self
+			.prompt
+			.as_ref()
+			.is_some_and(|p| p.kind == PromptKind::Visible)
+		{
+			self.password_input.hide_input = true;
+		}
+		self.prompt = prompt;
+	}
+	fn is_answering_prompt(&self) -> bool {
+		self
+			.prompt
+			.as_ref()
+			.is_some_and(LoginPrompt::expects_answer)
+	}
+	/// The selected user, `None` with "Other user…" if it isn't in the list.
+	fn selected_account(&self) -> Option<&UserAccount> {
+		self
+			.user_list
 			.iter()
-			.enumerate()
+			.find(|u| Some(u.uid) == self.selected_user)
+	}
+	/// Replaces the user list with the one from AccountsService, keeping the selected user if they're
+	/// still there.
+	fn set_users(
+		&mut self,
+		users: Vec<UserAccount>,
+		login_manager: &LoginManager,
+		session_manager: &SessionManager,
+	) {
+		if users == self.user_list || CONFIG.hide_user_list {
+			return;
+		}
+		for user in &users {
+			if let Some(avatar) = load_avatar(user) {
+				self.avatars.insert(user.uid, avatar);
+			}
+			if !self.wallpapers.contains_key(&user.uid) {
+				if let Some(wallpaper) = CONFIG
+					.background
+					.user_wallpapers
+					.then(|| load_wallpaper(user))
+					.flatten()
+				{
+					self.wallpapers.insert(user.uid, wallpaper);
+				}
+			}
+		}
+		self.last_login_labels = last_login_labels(&users);
+		self.user_list = users;
+		if self.other_user || self.selected_account().is_some() {
+			return;
+		}
+		// The selected user was removed
+		if self.is_logging(login_manager, session_manager) {
+			return;
+		}
+		self.select_user(0, login_manager, session_manager);
+	}
+	/// Switches to the user at `i` of the user list, or to "Other user…" if it's past the end.
+	fn select_user(
+		&mut self,
+		i: usize,
+		login_manager: &LoginManager,
+		session_manager: &SessionManager,
+	) {
+		if self.is_logging(login_manager, session_manager) {
+			return;
+		}
+		let user = self.user_list.get(i);
+		let already_selected = match user {
+			Some(user) => !self.other_user && Some(user.uid) == self.selected_user,
+			None => self.other_user,
+		};
+		if already_selected {
+			return;
+		}
+		self.other_user = user.is_none();
+		if let Some(user) = user {
+			self.selected_user = Some(user.uid);
+		} else {
+			self.selected_user = None;
+			self.selected_username.clear();
+		}
+		self.is_desktop_environment_popup_open = false;
+		self.selected_de = None;
+		self.session_open_error = None;
+		self.username_error = None;
+		self.focus.focus(if self.other_user {
+			USERNAME_INPUT
+		} else {
+			PASSWORD_INPUT
+		});
+	}
+	/// Takes the username typed in "Other user…". It isn't looked up before PAM accepts it, so the
+	/// login screen doesn't tell which users exist. Returns `false` if it's empty.
+	fn find_other_user(&mut self) -> bool {
+		if !self.other_user {
+			return true;
+		}
+		let username = self.username_input.text().trim();
+		if username.is_empty() {
+			self.username_error = Some("Type your username.".into());
+			self.focus.focus(USERNAME_INPUT);
+			return false;
+		}
+		self.username_error = None;
+		if self.selected_username != username {
+			self.selected_user = None;
+			self.selected_username = username.to_string();
+		}
+		true
+	}
+	/// Loads the avatar, wallpaper and keyboard layout of the user typed in "Other user…" once they
+	/// logged in, the same as the ones of the user list. The layout isn't restored earlier as it
+	/// would tell which names logged in before.
+	fn update_other_user(&mut self, login_manager: &LoginManager) {
+		if !self.other_user || self.selected_user.is_some() {
+			return;
+		}
+		let Some(LoginState::Authenticated(uid)) =
+			login_manager.get_current_login_state(&self.selected_username)
+		else {
+			return;
+		};
+		self.selected_user = Some(uid);
+		self.keyboard_layouts.restore(&self.selected_username);
+		let Some(user) =
+			uzers::get_user_by_uid(uid).map(|u| UserAccount::from_passwd(&u, &passwd_real_names()))
+		else {
+			return;
+		};
+		if let Some(avatar) = load_avatar(&user) {
+			self.avatars.insert(uid, avatar);
+		}
+		if let Some(wallpaper) = CONFIG
+			.background
+			.user_wallpapers
+			.then(|| load_wallpaper(&user))
+			.flatten()
 		{
-			if c.pointer_over(c.id_index("desktop-environment", i as u32))
-				&& rmar.is_mouse_button_released(0)
-			{
-				self.on_de_select(de, login_manager, session_manager);
+			self.wallpapers.insert(uid, wallpaper);
+		}
+	}
+	fn update_keyboard_layouts(&mut self, c: &mut clay_layout::Clay, input: &dyn Input) {
+		if CONFIG.keybindings.pressed(input) == Some(Action::NextKeyboardLayout) {
+			self.keyboard_layouts.select_next();
+		}
+		let indicator_hovered = c.pointer_over(c.id("keyboard-layout-indicator"));
+		if input.is_mouse_button_pressed(0)
+			&& !indicator_hovered
+			&& !c.pointer_over(c.id("keyboard-layouts-popup"))
+		{
+			self.is_keyboard_layout_popup_open = false;
+		}
+		if self.is_keyboard_layout_popup_open {
+			if let Some(i) = (0..self.keyboard_layouts.len()).find(|&i| {
+				(c.pointer_over(c.id_index("keyboard-layout", i as u32))
+					&& input.is_mouse_button_released(0))
+					|| self.focus.activated(keyboard_layout(i), input)
+			}) {
+				self.keyboard_layouts.select(i);
+				self.is_keyboard_layout_popup_open = false;
+				self.focus.focus(KEYBOARD_LAYOUT_INDICATOR);
+				return;
+			}
+		}
+		if (indicator_hovered && input.is_mouse_button_released(0))
+			|| self.focus.activated(KEYBOARD_LAYOUT_INDICATOR, input)
+		{
+			self.is_keyboard_layout_popup_open = !self.is_keyboard_layout_popup_open;
+			if self.is_keyboard_layout_popup_open {
+				self
+					.focus
+					.focus(keyboard_layout(self.keyboard_layouts.active_index()));
 			}
 		}
 	}
@@ -196,7 +650,9 @@ impl LoginScreen {
 		session_manager: &SessionManager,
 	) {
 		if self.is_logging(login_manager, session_manager)
-			|| session_manager.is_running(self.selected_user)
+			|| self
+				.selected_user
+				.is_some_and(|uid| session_manager.is_running(uid))
 		{
 			return;
 		}
@@ -211,13 +667,28 @@ impl LoginScreen {
 		session_manager: &mut SessionManager,
 	) {
 		if let Some(selected_de) = &self.selected_de {
-			if let Err(e) =
-				session_manager.start_session(login_manager, &self.selected_username, selected_de)
-			{
-				self.session_open_error = Some(e.to_string());
+			let language = self
+				.selected_account()
+				.filter(|_| !self.other_user)
+				.and_then(|u| u.language.as_deref());
+			match session_manager.start_session(
+				login_manager,
+				&self.selected_username,
+				selected_de,
+				self.keyboard_layouts.active(),
+				language,
+			) {
+				Ok(_) => {
+					self.keyboard_layouts.remember(&self.selected_username);
+					input_devices::remember_last_user(&self.selected_username);
+				}
+				Err(e) => self.session_open_error = Some(e.to_string()),
 			}
 		}
 	}
+	pub fn set_desktop_environment_popup_open(&mut self, open: bool) {
+		self.is_desktop_environment_popup_open = open;
+	}
 	pub fn session_open_failed(&self) -> bool {
 		self.session_open_error.is_some()
 	}
@@ -243,7 +714,9 @@ impl LoginScreen {
 			Some(LoginState::Logging | LoginState::Authenticated(_))
 		) {
 			matches!(
-				session_manager.get_session_state_of_user(self.selected_user),
+				self
+					.selected_user
+					.and_then(|uid| session_manager.get_session_state_of_user(uid)),
 				None | Some(SessionStatus::Crashed) | Some(SessionStatus::ShutdownGracefully)
 			)
 		} else {
@@ -262,22 +735,223 @@ impl LoginScreen {
 		login_manager: &LoginManager,
 		session_manager: &'render SessionManager,
 		frame_pool: &FrameAllocator<'render>,
-		rmar: &Rustamarine,
+		input: &dyn Input,
 	) where
 		'clay: 'render,
 	{
-		self.render_selected_user(c, login_manager, session_manager, frame_pool, rmar);
+		self.render_selected_user(c, login_manager, session_manager, frame_pool, input);
 		if !self.is_logging(login_manager, session_manager) {
-			self.render_user_list(c, frame_pool);
+			if !self.user_list.is_empty() {
+				self.render_user_list(c);
+			}
+			self.render_input_buttons(c, frame_pool, input);
+			if self.on_screen_keyboard.visible {
+				self.on_screen_keyboard.render(c, input);
+			}
 		}
 	}
 
-	fn render_user_list<'clay, 'render>(
+	/// The on-screen keyboard toggle and the keyboard layout indicator, on the bottom right.
+	fn render_input_buttons<'clay, 'render>(
+		&'render self,
+		c: &mut TibsClayScope<'clay, 'render>,
+		frame_pool: &FrameAllocator<'render>,
+		input: &dyn Input,
+	) where
+		'clay: 'render,
+	{
+		c.with(
+			Declaration::new()
+				.floating()
+				.attach_to(FloatingAttachToElement::Parent)
+				.attach_points(
+					FloatingAttachPointType::RightBottom,
+					FloatingAttachPointType::RightBottom,
+				)
+				.offset((-55.0, -55.0).into())
+				.end()
+				.layout()
+				.child_gap(14)
+				.end(),
+			|c| {
+				// Hidden while the on-screen keyboard is open, it has its own hide key
+				if !self.on_screen_keyboard.visible {
+					self.render_on_screen_keyboard_toggle(c, input);
+				}
+				// There's nothing to switch to with a single layout
+				if self.keyboard_layouts.len() > 1 {
+					self.render_keyboard_layouts(c, frame_pool, input);
+				}
+			},
+		);
+	}
+
+	fn render_on_screen_keyboard_toggle<'clay, 'render>(
+		&'render self,
+		c: &mut TibsClayScope<'clay, 'render>,
+		input: &dyn Input,
+	) where
+		'clay: 'render,
+	{
+		c.with_styling(
+			|c| {
+				let mut d = Declaration::new();
+				d.id(c.id("on-screen-keyboard-toggle"))
+					.layout()
+					.child_alignment(Alignment::new(LX::Center, LY::Center))
+					.width(fixed!(50.0))
+					.height(fixed!(50.0))
+					.end()
+					.background_color((0x0E, 0x1A, 0x26, 0x30).into())
+					.corner_radius()
+					.all(10.0)
+					.end();
+				if c.hovered() {
+					d.background_color((0x0E + 20, 0x1A + 20, 0x26 + 20, 0x30).into());
+					if input.is_mouse_button_down(0) {
+						d.background_color((0x0E + 30, 0x1A + 30, 0x26 + 30, 0x30).into());
+					}
+				}
+				d
+			},
+			|c| {
+				c.with(
+					Declaration::new()
+						.layout()
+						.width(fixed!(24.0))
+						.height(fixed!(24.0))
+						.end()
+						.custom_element(&CustomElements::KeyIcon(KeyIcon::Keyboard)),
+					|_| {},
+				);
+			},
+		);
+	}
+
+	fn render_keyboard_layouts<'clay, 'render>(
 		&'render self,
 		c: &mut TibsClayScope<'clay, 'render>,
 		frame_pool: &FrameAllocator<'render>,
+		input: &dyn Input,
+	) where
+		'clay: 'render,
+	{
+		c.with_styling(
+			|c| {
+				let mut d = Declaration::new();
+				d.id(c.id("keyboard-layout-indicator"))
+					.layout()
+					.child_alignment(Alignment::new(LX::Center, LY::Center))
+					.width(fit!(50.0))
+					.height(fixed!(50.0))
+					.padding(Padding::new(14, 14, 0, 0))
+					.end()
+					.background_color((0x0E, 0x1A, 0x26, 0x30).into())
+					.corner_radius()
+					.all(10.0)
+					.end();
+				if c.hovered() {
+					d.background_color((0x0E + 20, 0x1A + 20, 0x26 + 20, 0x30).into());
+					if input.is_mouse_button_down(0) {
+						d.background_color((0x0E + 30, 0x1A + 30, 0x26 + 30, 0x30).into());
+					}
+				}
+				d
+			},
+			|c| {
+				let short_name = self
+					.keyboard_layouts
+					.active_short_name()
+					.unwrap_or_default();
+				c.text(
+					frame_pool.alloc(short_name).as_str(),
+					TextConfig::new()
+						.color((0xFF, 0xFF, 0xFF).into())
+						.font_size(16)
+						.font_id(Fonts::MEDIUM)
+						.end(),
+				);
+				if self.is_keyboard_layout_popup_open {
+					self.render_keyboard_layouts_popup(c, input);
+				}
+			},
+		);
+	}
+
+	fn render_keyboard_layouts_popup<'clay, 'render>(
+		&'render self,
+		c: &mut TibsClayScope<'clay, 'render>,
+		input: &dyn Input,
 	) where
 		'clay: 'render,
+	{
+		c.with(
+			Declaration::new()
+				.background_color(hsl_to_rgb(230., 27.6, 10.2).into())
+				.corner_radius()
+				.all(10.)
+				.end()
+				.floating()
+				.attach_to(FloatingAttachToElement::Parent)
+				.attach_points(
+					FloatingAttachPointType::RightBottom,
+					FloatingAttachPointType::RightTop,
+				)
+				.offset((0.0, -10.0).into())
+				.end()
+				.layout()
+				.direction(LayoutDirection::TopToBottom)
+				.width(fit!(200.0))
+				.padding(Padding::all(8))
+				.end()
+				.id(c.id("keyboard-layouts-popup")),
+			|c| {
+				let active = self.keyboard_layouts.active_index();
+				for (i, name) in self.keyboard_layouts.names().iter().enumerate() {
+					c.with_styling(
+						|c| {
+							let mut d = Declaration::new();
+							d.layout()
+								.padding(Padding::new(12, 12, 10, 10))
+								.width(grow!())
+								.end()
+								.corner_radius()
+								.all(6.)
+								.end()
+								.id(c.id_index("keyboard-layout", i as u32));
+							if c.hovered() {
+								d.background_color((0xff, 0xff, 0xff, 0x1f).into());
+								if input.is_mouse_button_down(0) {
+									d.background_color((0xff, 0xff, 0xff, 0x3f).into());
+								}
+							} else if i == active {
+								d.background_color((0xff, 0xff, 0xff, 0x10).into());
+							}
+							d
+						},
+						|c| {
+							c.text(
+								name,
+								TextConfig::new()
+									.color((0xFF, 0xFF, 0xFF).into())
+									.font_size(14)
+									.font_id(if i == active {
+										Fonts::MEDIUM
+									} else {
+										Fonts::REGULAR
+									})
+									.end(),
+							);
+						},
+					);
+				}
+			},
+		);
+	}
+
+	fn render_user_list<'clay, 'render>(&'render self, c: &mut TibsClayScope<'clay, 'render>)
+	where
+		'clay: 'render,
 	{
 		c.with(
 			Declaration::new()
@@ -294,10 +968,11 @@ impl LoginScreen {
 				.width(fit!(250.0))
 				.end(),
 			|c| {
-				for user in &self.user_list {
-					let is_selected = user.uid() == self.selected_user;
-					self.render_user_item(c, user, is_selected, &frame_pool);
+				for (i, user) in self.user_list.iter().enumerate() {
+					let is_selected = !self.other_user && Some(user.uid) == self.selected_user;
+					self.render_user_item(c, i, Some(user), is_selected);
 				}
+				self.render_user_item(c, self.user_list.len(), None, self.other_user);
 			},
 		);
 	}
@@ -305,14 +980,15 @@ impl LoginScreen {
 	fn render_user_item<'clay, 'render>(
 		&'render self,
 		c: &mut TibsClayScope<'clay, 'render>,
-		user: &'render User,
+		index: usize,
+		// `None` for "Other user…"
+		user: Option<&'render UserAccount>,
 		is_selected: bool,
-		frame_pool: &FrameAllocator<'render>,
 	) where
 		'clay: 'render,
 	{
-		let user_name = user.name().to_str().unwrap();
-		let id = c.id(frame_pool.alloc(format!("user_item-{user_name}")).as_str());
+		let user_name = user.map_or("Other user…", UserAccount::display_name);
+		let id = c.id_index("user-item", index as u32);
 		// If the user is selected, apply a highlight background color.
 		let mut decl = Declaration::new();
 		decl
@@ -347,18 +1023,39 @@ impl LoginScreen {
 				.corner_radius()
 				.all(99999.0)
 				.end();
-			if let Some(avatar) = self.avatars.get(&user.uid()) {
+			if let Some(avatar) = user.and_then(|u| self.avatars.get(&u.uid)) {
 				avatar_declaration.image().data(avatar).end();
+			} else if user.is_none() {
+				avatar_declaration.background_color((0xAA, 0xAA, 0xAA, 0x30).into());
 			}
 			c.with(&avatar_declaration, |_| {});
-			// Display name text
-			c.text(
-				&user_name,
-				TextConfig::new()
-					.color((0xFF, 0xFF, 0xFF).into())
-					.font_size(20)
-					.alignment(clay_layout::text::TextAlignment::Left)
+			c.with(
+				Declaration::new()
+					.layout()
+					.direction(LayoutDirection::TopToBottom)
+					.child_gap(4)
 					.end(),
+				|c| {
+					// Display name text
+					c.text(
+						&user_name,
+						TextConfig::new()
+							.color((0xFF, 0xFF, 0xFF).into())
+							.font_size(20)
+							.alignment(clay_layout::text::TextAlignment::Left)
+							.end(),
+					);
+					if let Some(last_login) = user.and_then(|u| self.last_login_labels.get(&u.uid)) {
+						c.text(
+							last_login,
+							TextConfig::new()
+								.color((0xFF, 0xFF, 0xFF, 0xA0).into())
+								.font_size(14)
+								.alignment(clay_layout::text::TextAlignment::Left)
+								.end(),
+						);
+					}
+				},
 			);
 		});
 	}
@@ -369,16 +1066,13 @@ impl LoginScreen {
 		login_manager: &LoginManager,
 		session_manager: &'render SessionManager,
 		frame_pool: &FrameAllocator<'render>,
-		rmar: &Rustamarine,
+		input: &dyn Input,
 	) where
 		'clay: 'render,
 	{
 		// Retrieve the selected user info
-		if let Some(selected) = self
-			.user_list
-			.iter()
-			.find(|u| u.uid() == self.selected_user)
-		{
+		let selected = self.selected_account().filter(|_| !self.other_user);
+		if selected.is_some() || self.other_user {
 			c.with(
 				Declaration::new()
 					.layout()
@@ -409,7 +1103,7 @@ impl LoginScreen {
 								.all(99999.0)
 								.end();
 
-							if let Some(avatar) = self.avatars.get(&selected.uid()) {
+							if let Some(avatar) = self.selected_user.and_then(|uid| self.avatars.get(&uid)) {
 								avatar_declaration.image().data(avatar).end();
 							}
 							// Selected user avatar
@@ -423,23 +1117,47 @@ impl LoginScreen {
 									.end(),
 								|_| {},
 							);
-							let user_name = selected.name().to_str().unwrap();
-							// Selected user name text
-							c.text(
-								&user_name,
-								TextConfig::new()
-									.color((0xFF, 0xFF, 0xFF).into())
-									.font_size(32)
-									.alignment(clay_layout::text::TextAlignment::Center)
-									.end(),
-							);
+							if let Some(selected) = selected {
+								let user_name = selected.display_name();
+								// Selected user name text
+								c.text(
+									&user_name,
+									TextConfig::new()
+										.color((0xFF, 0xFF, 0xFF).into())
+										.font_size(32)
+										.alignment(clay_layout::text::TextAlignment::Center)
+										.end(),
+								);
+							} else {
+								self.username_input.render(c);
+							}
 
-							let error_message = self.session_open_error.as_deref().or_else(|| {
-								self.login_failed(login_manager).then_some(
-									"Failed to login, please check if your password is correct and try again.",
-								)
-							});
-							if let Some(error_message) = error_message {
+							let error_message = self
+								.session_open_error
+								.as_deref()
+								.or(self.username_error.as_deref())
+								.or_else(|| {
+									self.login_failed(login_manager).then(|| {
+										self
+											.prompt
+											.as_ref()
+											.filter(|p| p.kind == PromptKind::Error)
+											.map_or(
+												"Failed to login, please check if your password is correct and try again.",
+												|p| p.message.as_str(),
+											)
+									})
+								});
+							// Like "Security token PIN:" or "Please touch your security token"
+							let prompt_message = self
+								.prompt
+								.as_ref()
+								.filter(|p| p.kind != PromptKind::Error)
+								.map(|p| p.message.as_str());
+							let message = error_message
+								.map(|m| (m, (0xFF, 0x50, 0x50)))
+								.or(prompt_message.map(|m| (m, (0xFF, 0xFF, 0xFF))));
+							if let Some((message, color)) = message {
 								// Selected user name text
 								c.with(
 									Declaration::new()
@@ -449,11 +1167,11 @@ impl LoginScreen {
 										.end(),
 									|c| {
 										c.text(
-											error_message,
+											message,
 											TextConfig::new()
-												.color((0xFF, 0x50, 0x50).into())
+												.color(color.into())
 												.font_size(16)
-												.font_id(2)
+												.font_id(Fonts::MEDIUM)
 												.alignment(clay_layout::text::TextAlignment::Center)
 												.end(),
 										);
@@ -469,15 +1187,15 @@ impl LoginScreen {
 									.padding(Padding::new(
 										0,
 										0,
-										if error_message.is_some() { 0 } else { 56 },
+										if message.is_some() { 0 } else { 56 },
 										0,
 									))
 									.child_gap(14)
 									.end(),
 								|c| {
 									self.password_input.render(c);
-									self.render_eye_button(c, rmar);
-									self.render_login_button(c, login_manager, session_manager, frame_pool, rmar);
+									self.render_eye_button(c, input);
+									self.render_login_button(c, login_manager, session_manager, frame_pool, input);
 								},
 							);
 						},
@@ -493,7 +1211,7 @@ impl LoginScreen {
 		login_manager: &LoginManager,
 		session_manager: &'render SessionManager,
 		frame_pool: &FrameAllocator<'render>,
-		rmar: &Rustamarine,
+		input: &dyn Input,
 	) where
 		'clay: 'render,
 	{
@@ -520,14 +1238,14 @@ impl LoginScreen {
 				}
 				if c.hovered() {
 					d.background_color((0x0E + 20, 0x1A + 20, 0x26 + 20, 0x30).into());
-					if rmar.is_mouse_button_down(0) {
+					if input.is_mouse_button_down(0) {
 						d.background_color((0x0E + 30, 0x1A + 30, 0x26 + 30, 0x30).into());
 					}
 				}
 				d
 			},
 			|c| {
-				if self.is_logging(login_manager, session_manager) {
+				if self.is_logging(login_manager, session_manager) && !self.is_answering_prompt() {
 					// Mostra apenas o spinner
 					c.with(
 						Declaration::new()
@@ -553,7 +1271,7 @@ impl LoginScreen {
 					)
 				}
 				if self.is_desktop_environment_popup_open {
-					desktop_environments_popup(session_manager, c, frame_pool, &self.de_icons, rmar);
+					desktop_environments_popup(session_manager, c, frame_pool, &self.de_icons, input);
 				}
 			},
 		);
@@ -562,7 +1280,7 @@ impl LoginScreen {
 	fn render_eye_button<'clay, 'render>(
 		&'render self,
 		c: &mut TibsClayScope<'clay, 'render>,
-		rmar: &Rustamarine,
+		input: &dyn Input,
 	) where
 		'clay: 'render,
 	{
@@ -582,7 +1300,7 @@ impl LoginScreen {
 
 				if c.hovered() {
 					d.background_color((0x0E + 20, 0x1A + 20, 0x26 + 20, 0x30).into());
-					if rmar.is_mouse_button_down(0) {
+					if input.is_mouse_button_down(0) {
 						d.background_color((0x0E + 30, 0x1A + 30, 0x26 + 30, 0x30).into());
 					}
 				}
@@ -617,7 +1335,7 @@ fn desktop_environments_popup<'clay: 'render, 'render>(
 	c: &mut TibsClayScope<'clay, 'render>,
 	frame_pool: &FrameAllocator<'render>,
 	de_icons: &'render HashMap<KnownDEs, SkiaImageAsset>,
-	rmar: &Rustamarine,
+	input: &dyn Input,
 ) {
 	c.with(
 		Declaration::new()
@@ -679,7 +1397,7 @@ fn desktop_environments_popup<'clay: 'render, 'render>(
 									.id(c.id_index("desktop-environment", i as u32));
 								if c.hovered() {
 									d.background_color((0xff, 0xff, 0xff, 0x1f).into());
-									if rmar.is_mouse_button_down(0) {
+									if input.is_mouse_button_down(0) {
 										d.background_color((0xff, 0xff, 0xff, 0x3f).into());
 									}
 								}
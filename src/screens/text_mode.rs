@@ -0,0 +1,316 @@
+//! Text-mode greeter, used when the graphics can't be initialized (e.g. the initrd doesn't have the
+//! driver of the GPU), so there's always a way to log in.
+//!
+//! It runs on the tty tibs was started on and logs in through the same [`LoginManager`] and
+//! [`SessionManager`] as the graphical greeter. `/dev/dri` is checked while waiting for input, and
+//! [`run`] returns as soon as a new DRM device shows up so the graphics can be tried again.
+use std::{
+	collections::HashSet,
+	io::{ErrorKind, Write},
+	mem::MaybeUninit,
+	os::fd::AsRawFd,
+	path::PathBuf,
+	thread::sleep,
+	time::Duration,
+};
+
+use color_eyre::eyre::{bail, Result};
+use linux_raw_sys::ioctl::KDSETMODE;
+use nix::{errno::Errno, libc, unistd};
+
+use crate::{
+	login::{LoginManager, LoginPrompt, LoginState, PromptKind},
+	session_manager::SessionManager,
+};
+
+/// How often `/dev/dri` is checked for new devices while waiting for input
+const DRM_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+fn drm_devices() -> HashSet<PathBuf> {
+	rustamarine::drm::devices()
+		.into_iter()
+		.map(|device| device.path)
+		.collect()
+}
+
+/// Index of the desktop environment picked by the user, an empty answer picks the first one.
+fn parse_choice(answer: &str, count: usize) -> Option<usize> {
+	let answer = answer.trim();
+	if answer.is_empty() {
+		return (count > 0).then_some(0);
+	}
+	let choice = answer.parse::<usize>().ok()?;
+	(1..=count).contains(&choice).then(|| choice - 1)
+}
+
+/// Puts the tty back in text mode, aquamarine (or a session that crashed) might have left it in
+/// graphics mode.
+fn set_text_mode() {
+	let fd = std::io::stdin().as_raw_fd();
+	unsafe { libc::ioctl(fd, KDSETMODE as u64, 0) };
+}
+
+/// Stops the tty from echoing what's typed while it's alive, for reading passwords.
+struct HiddenInput {
+	original: Option<libc::termios>,
+}
+
+impl HiddenInput {
+	fn new() -> Self {
+		let fd = std::io::stdin().as_raw_fd();
+		let mut termios = MaybeUninit::<libc::termios>::uninit();
+		if unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) } != 0 {
+			return Self { original: None };
+		}
+		let original = unsafe { termios.assume_init() };
+		let mut hidden = original;
+		hidden.c_lflag &= !libc::ECHO;
+		unsafe { libc::tcsetattr(fd, libc::TCSANOW, &hidden) };
+		Self {
+			original: Some(original),
+		}
+	}
+}
+
+impl Drop for HiddenInput {
+	fn drop(&mut self) {
+		if let Some(original) = &self.original {
+			unsafe { libc::tcsetattr(std::io::stdin().as_raw_fd(), libc::TCSANOW, original) };
+		}
+	}
+}
+
+struct TextGreeter<'a> {
+	/// Why the graphics couldn't be initialized
+	error: &'a str,
+	login_manager: &'a LoginManager,
+	session_manager: &'a mut SessionManager,
+	drm_devices: HashSet<PathBuf>,
+	/// What was read from the tty but isn't part of a line that was returned yet. The tty is read
+	/// directly rather than through the buffered `Stdin`, which `poll` can't see into
+	typed: Vec<u8>,
+}
+
+impl TextGreeter<'_> {
+	fn print_banner(&self) {
+		// Clear the screen
+		print!("\x1b[2J\x1b[H");
+		println!("TIBS - text mode\n");
+		println!("The graphical login couldn't be started:");
+		println!("  {}\n", self.error);
+		println!("It will be started as soon as a new GPU is detected.\n");
+	}
+
+	/// Returns the DRM device that showed up since the greeter started, if any.
+	fn new_drm_device(&mut self) -> Option<PathBuf> {
+		let current = drm_devices();
+		// Devices that are unplugged and plugged back in also count as new
+		self.drm_devices.retain(|device| current.contains(device));
+		current.difference(&self.drm_devices).next().cloned()
+	}
+
+	/// Reads a line from the tty, or returns `None` if a new DRM device showed up while waiting.
+	fn read_line(&mut self, prompt: &str) -> Result<Option<String>> {
+		print!("{prompt}");
+		std::io::stdout().flush()?;
+		let stdin = std::io::stdin();
+		loop {
+			if let Some(end) = self.typed.iter().position(|&byte| byte == b'\n') {
+				let line = self.typed.drain(..=end).collect::<Vec<_>>();
+				let line = String::from_utf8_lossy(&line);
+				return Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()));
+			}
+			if let Some(device) = self.new_drm_device() {
+				println!();
+				println!("[INFO] Found {}, retrying graphics", device.display());
+				return Ok(None);
+			}
+			let mut pollfd = libc::pollfd {
+				fd: stdin.as_raw_fd(),
+				events: libc::POLLIN,
+				revents: 0,
+			};
+			let ready = unsafe { libc::poll(&mut pollfd, 1, DRM_POLL_INTERVAL.as_millis() as i32) };
+			if ready < 0 {
+				let error = std::io::Error::last_os_error();
+				if error.kind() != ErrorKind::Interrupted {
+					bail!("Failed to wait for input: {error}");
+				}
+			}
+			if ready <= 0 {
+				continue;
+			}
+			let mut buffer = [0; 1024];
+			match unistd::read(&stdin, &mut buffer) {
+				Ok(0) => bail!("The tty was closed"),
+				Ok(n) => self.typed.extend_from_slice(&buffer[..n]),
+				Err(Errno::EINTR | Errno::EAGAIN) => {}
+				Err(e) => bail!("Failed to read from the tty: {e}"),
+			}
+		}
+	}
+
+	/// Shows what PAM asks or tells after the password, like the PIN of a security token of a
+	/// systemd-homed user. Returns `false` if a new DRM device showed up in the meantime.
+	fn answer_prompt(&mut self, username: &str, prompt: &LoginPrompt) -> Result<bool> {
+		let question = format!("{} ", prompt.message);
+		let answer = match prompt.kind {
+			PromptKind::Info => {
+				println!("{}", prompt.message);
+				return Ok(true);
+			}
+			// Shown if the login fails
+			PromptKind::Error => return Ok(true),
+			PromptKind::Visible => self.read_line(&question)?,
+			PromptKind::Secret => {
+				let _hidden = HiddenInput::new();
+				let answer = self.read_line(&question)?;
+				println!();
+				answer
+			}
+		};
+		let Some(answer) = answer else {
+			return Ok(false);
+		};
+		self.login_manager.answer_prompt(username, answer);
+		Ok(true)
+	}
+
+	/// Authenticates `username` and starts the session they pick. Returns `false` if a new DRM
+	/// device showed up in the meantime.
+	fn login(&mut self, username: &str, password: String) -> Result<bool> {
+		self.login_manager.start_login(username, password, true);
+		let mut shown_prompt = None;
+		let state = loop {
+			match self.login_manager.get_current_login_state(username) {
+				Some(LoginState::Logging) => {
+					let prompt = self.login_manager.prompt(username);
+					if prompt != shown_prompt {
+						shown_prompt = prompt.clone();
+						if let Some(prompt) = prompt {
+							if !self.answer_prompt(username, &prompt)? {
+								self.login_manager.reset_login_state(username);
+								return Ok(false);
+							}
+							// The same question is asked again if the answer was wrong
+							if prompt.expects_answer() {
+								shown_prompt = None;
+							}
+						}
+					}
+					sleep(Duration::from_millis(50))
+				}
+				state => break state,
+			}
+		};
+		if !matches!(state, Some(LoginState::Authenticated(_))) {
+			if let Some(prompt) = self.login_manager.prompt(username) {
+				println!("\n{}", prompt.message);
+			}
+			println!("\nLogin incorrect\n");
+			self.login_manager.reset_login_state(username);
+			return Ok(true);
+		}
+
+		let desktop_environments = self
+			.session_manager
+			.get_desktop_environments_list()
+			.to_vec();
+		if desktop_environments.is_empty() {
+			println!("\nNo desktop environments were found\n");
+			self.login_manager.reset_login_state(username);
+			return Ok(true);
+		}
+		let choice = if desktop_environments.len() == 1 {
+			0
+		} else {
+			println!();
+			for (i, desktop_environment) in desktop_environments.iter().enumerate() {
+				println!("  {}) {}", i + 1, desktop_environment.name());
+			}
+			loop {
+				let Some(answer) = self.read_line("session [1]: ")? else {
+					self.login_manager.reset_login_state(username);
+					return Ok(false);
+				};
+				match parse_choice(&answer, desktop_environments.len()) {
+					Some(choice) => break choice,
+					None => println!("Invalid choice"),
+				}
+			}
+		};
+
+		let session = self.session_manager.start_session(
+			self.login_manager,
+			username,
+			&desktop_environments[choice],
+			None,
+			None,
+		);
+		self.login_manager.reset_login_state(username);
+		match session {
+			Ok(_) => {
+				// The session has its own tty, wait until the user comes back to this one
+				self.session_manager.wait_for_tibs_tty();
+				set_text_mode();
+				self.print_banner();
+			}
+			Err(e) => println!("\nFailed to start the session: {e}\n"),
+		}
+		Ok(true)
+	}
+}
+
+/// Shows the text-mode greeter until a new DRM device shows up.
+pub fn run(
+	error: &str,
+	login_manager: &LoginManager,
+	session_manager: &mut SessionManager,
+) -> Result<()> {
+	set_text_mode();
+	let mut greeter = TextGreeter {
+		error,
+		login_manager,
+		session_manager,
+		drm_devices: drm_devices(),
+		typed: Vec::new(),
+	};
+	greeter.print_banner();
+	loop {
+		let Some(username) = greeter.read_line("login: ")? else {
+			return Ok(());
+		};
+		let username = username.trim();
+		if username.is_empty() {
+			continue;
+		}
+		let password = {
+			let _hidden = HiddenInput::new();
+			let password = greeter.read_line("password: ")?;
+			println!();
+			password
+		};
+		let Some(password) = password else {
+			return Ok(());
+		};
+		if !greeter.login(username, password)? {
+			return Ok(());
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn session_choice() {
+		assert_eq!(parse_choice("", 3), Some(0));
+		assert_eq!(parse_choice(" 2 ", 3), Some(1));
+		assert_eq!(parse_choice("3", 3), Some(2));
+		assert_eq!(parse_choice("0", 3), None);
+		assert_eq!(parse_choice("4", 3), None);
+		assert_eq!(parse_choice("plasma", 3), None);
+		assert_eq!(parse_choice("", 0), None);
+	}
+}
@@ -0,0 +1,108 @@
+use skia_safe::{codec, images, AlphaType, Codec, ColorType, Data, Image};
+
+/// An animated image (GIF, WebP, APNG) decoded upfront into one [`Image`] per frame.
+pub struct AnimatedImage {
+	frames: Vec<AnimatedImageFrame>,
+	current_frame: usize,
+	elapsed: f32,
+	/// `None` means loop forever
+	repetitions: Option<usize>,
+	loops_done: usize,
+}
+
+struct AnimatedImageFrame {
+	image: Image,
+	duration: f32,
+}
+
+impl AnimatedImage {
+	/// Decodes every frame of an encoded image.
+	///
+	/// Returns `None` if the data can't be decoded or if the image only has a single frame.
+	pub fn from_data(data: Data) -> Option<Self> {
+		let mut codec = Codec::from_data(data)?;
+		let frame_count = codec.get_frame_count();
+		if frame_count <= 1 {
+			return None;
+		}
+		let info = codec
+			.info()
+			.with_color_type(ColorType::N32)
+			.with_alpha_type(AlphaType::Premul);
+		let row_bytes = info.min_row_bytes();
+		let byte_size = info.compute_min_byte_size();
+
+		// Frames can depend on a previous frame, so we keep the pixels of every decoded frame around
+		// until all of them are decoded.
+		let mut pixels: Vec<Vec<u8>> = Vec::with_capacity(frame_count);
+		let mut frames = Vec::with_capacity(frame_count);
+		for index in 0..frame_count {
+			let frame_info = codec.get_frame_info(index)?;
+			let prior_frame = usize::try_from(frame_info.required_frame)
+				.ok()
+				.filter(|&i| i < pixels.len());
+			let mut frame_pixels = match prior_frame {
+				Some(prior) => pixels[prior].clone(),
+				None => vec![0; byte_size],
+			};
+			let options = codec::Options {
+				zero_initialized: codec::ZeroInitialized::No,
+				subset: None,
+				frame_index: index,
+				prior_frame,
+			};
+			let result =
+				codec.get_pixels_with_options(&info, &mut frame_pixels, row_bytes, Some(&options));
+			if !matches!(
+				result,
+				codec::Result::Success | codec::Result::IncompleteInput
+			) {
+				log::warn!(
+					"Failed to decode frame {index} of animated image: {}",
+					codec::result_to_string(result)
+				);
+				return None;
+			}
+			let image = images::raster_from_data(&info, Data::new_copy(&frame_pixels), row_bytes)?;
+			// Browsers bump very short frame durations up to 100ms, do the same
+			let duration = if frame_info.duration <= 10 {
+				0.1
+			} else {
+				frame_info.duration as f32 / 1000.0
+			};
+			frames.push(AnimatedImageFrame { image, duration });
+			pixels.push(frame_pixels);
+		}
+
+		Some(Self {
+			frames,
+			current_frame: 0,
+			elapsed: 0.0,
+			repetitions: codec.get_repetition_count(),
+			loops_done: 0,
+		})
+	}
+
+	pub fn update(&mut self, delta: f32) {
+		if self.repetitions.is_some_and(|r| self.loops_done > r) {
+			return;
+		}
+		self.elapsed += delta;
+		while self.elapsed >= self.frames[self.current_frame].duration {
+			self.elapsed -= self.frames[self.current_frame].duration;
+			if self.current_frame + 1 < self.frames.len() {
+				self.current_frame += 1;
+				continue;
+			}
+			self.loops_done += 1;
+			if self.repetitions.is_some_and(|r| self.loops_done > r) {
+				return;
+			}
+			self.current_frame = 0;
+		}
+	}
+
+	pub fn current_frame(&self) -> &Image {
+		&self.frames[self.current_frame].image
+	}
+}
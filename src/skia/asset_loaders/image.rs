@@ -1,6 +1,6 @@
 use std::ops::{Deref, DerefMut};
 
-use assets_manager::{loader::Loader, Asset};
+use assets_manager::{loader::Loader, Asset, AssetCache};
 use color_eyre::eyre::OptionExt;
 
 pub struct SkiaImageAsset(pub skia_safe::Image);
@@ -23,6 +23,22 @@ impl Asset for SkiaImageAsset {
 	const EXTENSIONS: &'static [&'static str] = &["png", "jpg", "jpeg", "webp"];
 	type Loader = SkiaImageLoader;
 }
+impl SkiaImageAsset {
+	/// Loads the variant of an image made for `scale`, if there is one.
+	///
+	/// For scale 2, `icons/eye@2x.png` is used over `icons/eye.png`. Variants for smaller scales are
+	/// tried before falling back to the regular image.
+	pub fn load_scaled(
+		assets: &AssetCache,
+		id: &str,
+		scale: f32,
+	) -> Result<Self, assets_manager::Error> {
+		(2..=scale.ceil() as u32)
+			.rev()
+			.find_map(|density| assets.load_owned(&format!("{id}@{density}x")).ok())
+			.map_or_else(|| assets.load_owned(id), Ok)
+	}
+}
 impl From<skia_safe::Image> for SkiaImageAsset {
 	fn from(value: skia_safe::Image) -> Self {
 		Self(value)
@@ -1,10 +1,10 @@
+use super::{fonts::Fonts, text::TextCache};
 use clay_layout::math::{BoundingBox, Dimensions};
 use clay_layout::render_commands::{Custom, RenderCommand, RenderCommandConfig};
 use clay_layout::text::TextConfig;
 use clay_layout::{ClayLayoutScope, Color as ClayColor};
 use skia_safe::{
-	Canvas, ClipOp, Color, Font, Image, Paint, PaintCap, Point, RRect, Rect, SamplingOptions,
-	Typeface,
+	Canvas, ClipOp, Color, Image, Paint, PaintCap, Point, RRect, Rect, SamplingOptions,
 };
 
 pub fn clay_to_skia_color(color: ClayColor) -> Color {
@@ -28,20 +28,21 @@ pub fn clay_skia_render<'a, CustomElementData: 'a>(
 		&Custom<'a, CustomElementData>,
 		&Canvas,
 	),
-	fonts: &[&Typeface],
+	fonts: &Fonts,
 ) {
 	for command in render_commands {
 		match command.config {
 			RenderCommandConfig::Text(text) => {
-				let text_data = text.text;
-				let mut paint = Paint::default();
-				paint.set_color(clay_to_skia_color(text.color));
-				let font = Font::new(fonts[text.font_id as usize].clone(), text.font_size as f32);
-				let pos = Point::new(
-					command.bounding_box.x,
-					command.bounding_box.y + text.font_size as f32,
-				);
-				canvas.draw_str(&text_data, pos, &font, &paint);
+				TextCache::with(fonts, |cache| {
+					cache.draw(
+						canvas,
+						&text.text,
+						(command.bounding_box.x, command.bounding_box.y),
+						text.font_id as usize,
+						text.font_size as f32,
+						clay_to_skia_color(text.color),
+					)
+				});
 			}
 
 			RenderCommandConfig::Image(image) => {
@@ -322,6 +323,21 @@ pub fn clay_skia_render<'a, CustomElementData: 'a>(
 			RenderCommandConfig::None() => {}
 		}
 	}
+	TextCache::with(fonts, TextCache::end_frame);
+}
+
+/// Draws the keyboard focus ring around an element, on top of the rendered layout.
+pub fn draw_focus_ring(canvas: &Canvas, bounding_box: BoundingBox) {
+	const GAP: f32 = 3.0;
+	const WIDTH: f32 = 2.0;
+	let mut paint = Paint::default();
+	paint.set_color(Color::from_argb(0xFF, 0x3D, 0x8B, 0xFF));
+	paint.set_anti_alias(true);
+	paint.set_style(skia_safe::PaintStyle::Stroke);
+	paint.set_stroke_width(WIDTH);
+	let bounds = clay_to_skia_rect(bounding_box).with_outset((GAP, GAP));
+	// Follows the 10px corners of the buttons
+	canvas.draw_rrect(RRect::new_rect_xy(bounds, 10.0 + GAP, 10.0 + GAP), &paint);
 }
 
 pub type SkiaClayScope<'clay, 'render, CustomElements> =
@@ -332,14 +348,17 @@ pub fn get_source_dimensions_from_skia_image(image: &Image) -> Dimensions {
 }
 
 pub fn create_measure_text_function(
-	fonts: &'static [&Typeface],
+	fonts: &'static Fonts,
 ) -> impl Fn(&str, &TextConfig) -> Dimensions + 'static {
 	|text, text_config| {
-		let font = Font::new(
-			fonts[text_config.font_id as usize],
-			text_config.font_size as f32,
-		);
-		let width = font.measure_str(text, None).0;
-		(width, font.metrics().1.bottom - font.metrics().1.top).into()
+		TextCache::with(fonts, |cache| {
+			cache.measure(
+				text,
+				text_config.font_id as usize,
+				text_config.font_size as f32,
+				clay_to_skia_color(text_config.color),
+			)
+		})
+		.into()
 	}
 }
@@ -1,4 +1,4 @@
-use color_eyre::eyre::{bail, Result};
+use color_eyre::eyre::{bail, OptionExt, Result};
 use rustamarine::{screen::Screen, Rustamarine};
 use std::collections::HashMap;
 
@@ -11,7 +11,10 @@ use skia_safe::{
 	},
 	Canvas, ColorSpace, Surface,
 };
+pub mod animated_image;
 pub mod clay_renderer;
+pub mod fonts;
+pub mod text;
 use crate::gl;
 
 pub mod asset_loaders {
@@ -23,49 +26,50 @@ pub mod asset_loaders {
 
 pub struct SkiaContext {
 	context: DirectContext,
+	// Every output has its own framebuffers, so surfaces are keyed by framebuffer and sized after
+	// the screen that owns them
 	surfaces: HashMap<u32, Surface>,
-	width: u32,
-	height: u32,
 	current_fboid: u32,
 }
 
 impl SkiaContext {
-	pub fn init_skia(screen: &mut Screen) -> Self {
+	pub fn init_skia(screen: &mut Screen) -> Result<Self> {
 		let interface =
 			Interface::new_load_with(|fn_name| screen.get_rustamarine().get_opengl_proc_address(fn_name))
-				.expect("Failed to initialize skia (interface)");
-
-		let context =
-			direct_contexts::make_gl(interface, None).expect("Failed to initialize skia (context)");
+				.ok_or_eyre("Failed to initialize skia (interface)")?;
 
-		let (width, height) = (screen.get_width(), screen.get_height());
+		let context = direct_contexts::make_gl(interface, None)
+			.ok_or_eyre("Failed to initialize skia (context)")?;
 
-		Self {
+		Ok(Self {
 			context,
 			surfaces: HashMap::new(),
-			width,
-			height,
 			current_fboid: 0,
-		}
+		})
 	}
 
-	pub fn set_size(&mut self, screen_width: u32, screen_height: u32) -> bool {
-		if self.width != screen_width || self.height != screen_height {
-			self.width = screen_width;
-			self.height = screen_height;
-			self.surfaces.clear(); // forçar recriação
-			true
-		} else {
-			false
-		}
+	/// Drops the surfaces of every framebuffer, they're recreated the next time a screen is used.
+	///
+	/// Framebuffer ids of unplugged screens can be reused by new ones, so this must be called when
+	/// the outputs change.
+	pub fn clear_surfaces(&mut self) {
+		self.surfaces.clear();
 	}
 
 	pub fn use_screen(&mut self, screen: &mut Screen) {
 		let fboid = screen.use_screen(); // <- obtém o framebuffer do sistema
+		let (width, height) = (screen.get_width(), screen.get_height());
 		self.current_fboid = fboid;
 
+		if self
+			.surfaces
+			.get(&fboid)
+			.is_some_and(|s| s.width() as u32 != width || s.height() as u32 != height)
+		{
+			self.surfaces.remove(&fboid); // forçar recriação
+		}
 		self.surfaces.entry(fboid).or_insert_with(|| {
-			create_skia_surface(&mut self.context, self.width, self.height, fboid)
+			create_skia_surface(&mut self.context, width, height, fboid)
 				.expect("Failed to create Skia surface")
 		});
 	}
@@ -81,6 +85,13 @@ impl SkiaContext {
 	pub fn flush(&mut self) {
 		self.context.flush(None);
 	}
+
+	/// Frees the context without touching OpenGL, for when the GL context is already gone (e.g. its
+	/// GPU was unplugged).
+	pub fn abandon(mut self) {
+		self.surfaces.clear();
+		self.context.abandon();
+	}
 }
 
 fn create_skia_surface(
@@ -0,0 +1,210 @@
+//! Font registry.
+//!
+//! Fonts are looked up at startup from the config, each one has a fallback chain of families,
+//! font files and bundled assets, and if none of them can be loaded the default font of the system
+//! is used instead. Clay refers to fonts by id: [`Fonts::REGULAR`], [`Fonts::BOLD`] and
+//! [`Fonts::MEDIUM`].
+use std::{
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+
+use skia_safe::{
+	font_style::{Slant, Weight, Width},
+	FontMgr, FontStyle, Typeface,
+};
+
+/// Somewhere a font can be loaded from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontSource {
+	/// Font family installed on the system, e.g. `UbuntuSans NF`
+	Family(String),
+	/// Path to a font file
+	File(PathBuf),
+	/// Font bundled in the assets folder, e.g. `asset:fonts.Inter` for `assets/fonts/Inter.ttf`
+	Asset(String),
+}
+
+impl FromStr for FontSource {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		if s.is_empty() {
+			return Err(());
+		}
+		if let Some(id) = s.strip_prefix("asset:") {
+			return Ok(Self::Asset(id.to_string()));
+		}
+		if s.contains('/') {
+			return Ok(Self::File(s.into()));
+		}
+		Ok(Self::Family(s.to_string()))
+	}
+}
+
+impl FontSource {
+	fn load(&self, style: FontStyle, assets_folder: &Path) -> Option<Typeface> {
+		let font_mgr = FontMgr::new();
+		match self {
+			Self::Family(family) => font_mgr.match_family_style(family, style),
+			Self::File(path) => font_mgr.new_from_data(&std::fs::read(path).ok()?, None),
+			Self::Asset(id) => {
+				let path = assets_folder.join(id.replace('.', "/"));
+				["ttf", "otf"]
+					.iter()
+					.find_map(|ext| std::fs::read(path.with_extension(ext)).ok())
+					.and_then(|data| font_mgr.new_from_data(&data, None))
+			}
+		}
+	}
+}
+
+/// A font: a fallback chain of sources and a weight, parsed from `Inter,UbuntuSans NF:500`.
+///
+/// The weight is only used for installed families, font files already have one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontSpec {
+	pub sources: Vec<FontSource>,
+	pub weight: i32,
+}
+
+impl FromStr for FontSpec {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (sources, weight) = match s.rsplit_once(':') {
+			Some((sources, weight)) if weight.trim().parse::<i32>().is_ok() => {
+				(sources, weight.trim().parse().unwrap())
+			}
+			_ => (s, *Weight::NORMAL),
+		};
+		let sources = sources
+			.split(',')
+			.map(str::parse)
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(Self { sources, weight })
+	}
+}
+
+/// Fonts available to clay, indexed by font id.
+pub struct Fonts {
+	/// `None` if not even the system default font could be loaded, skparagraph then falls back to
+	/// whatever font has the characters
+	typefaces: Vec<Option<Typeface>>,
+}
+
+impl Fonts {
+	pub const REGULAR: u16 = 0;
+	pub const BOLD: u16 = 1;
+	pub const MEDIUM: u16 = 2;
+
+	/// Loads the built-in fonts using the `family` fallback chain, unless `fonts` replaces them by
+	/// name (`regular`, `bold` or `medium`).
+	pub fn load(family: &[FontSource], fonts: &[(String, FontSpec)], assets_folder: &Path) -> Self {
+		// The fallback chain stops at the first font that loads, so the families after it are
+		// checked here too
+		let font_mgr = FontMgr::new();
+		for source in family {
+			if let FontSource::Family(name) = source {
+				if font_mgr
+					.match_family_style(name, FontStyle::normal())
+					.is_none()
+				{
+					log::warn!("Unknown font family: {name:?}");
+				}
+			}
+		}
+		let mut registry = Self {
+			typefaces: Vec::new(),
+		};
+		let builtin = [
+			("regular", Weight::NORMAL),
+			("bold", Weight::BOLD),
+			("medium", Weight::MEDIUM),
+		];
+		for (name, weight) in builtin {
+			let spec = fonts
+				.iter()
+				.find(|(n, _)| n == name)
+				.map(|(_, spec)| spec.clone())
+				.unwrap_or_else(|| FontSpec {
+					sources: family.to_vec(),
+					weight: *weight,
+				});
+			registry.add(name, &spec, assets_folder);
+		}
+		for (name, _) in fonts {
+			if !builtin.iter().any(|(n, _)| n == name) {
+				log::warn!("Ignoring unknown font {name:?}, only regular, bold and medium can be set");
+			}
+		}
+		registry
+	}
+
+	fn add(&mut self, name: &str, spec: &FontSpec, assets_folder: &Path) {
+		let style = FontStyle::new(Weight::from(spec.weight), Width::NORMAL, Slant::Upright);
+		let typeface = spec
+			.sources
+			.iter()
+			.find_map(|source| {
+				let typeface = source.load(style, assets_folder);
+				if typeface.is_none() {
+					log::warn!("Font {name}: couldn't load {source:?}");
+				}
+				typeface
+			})
+			.or_else(|| {
+				log::warn!("Font {name}: falling back to the default font");
+				FontMgr::new().legacy_make_typeface(None, style)
+			});
+		match &typeface {
+			Some(typeface) => log::debug!("Font {name}: using {}", typeface.family_name()),
+			None => log::error!("Font {name}: no fonts are available"),
+		}
+		self.typefaces.push(typeface);
+	}
+
+	pub fn get(&self, id: u16) -> Option<&Typeface> {
+		self.typefaces.get(id as usize)?.as_ref()
+	}
+
+	pub fn len(&self) -> usize {
+		self.typefaces.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.typefaces.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_font_source() {
+		assert_eq!(
+			"asset:fonts.Inter".parse(),
+			Ok(FontSource::Asset("fonts.Inter".into()))
+		);
+		assert_eq!(
+			"/usr/share/fonts/Inter.ttf".parse(),
+			Ok(FontSource::File("/usr/share/fonts/Inter.ttf".into()))
+		);
+		assert_eq!(
+			" UbuntuSans NF ".parse(),
+			Ok(FontSource::Family("UbuntuSans NF".into()))
+		);
+	}
+
+	#[test]
+	fn parse_font_spec() {
+		let spec: FontSpec = "Inter,UbuntuSans NF:500".parse().unwrap();
+		assert_eq!(spec.weight, 500);
+		assert_eq!(spec.sources.len(), 2);
+		let spec: FontSpec = "Inter".parse().unwrap();
+		assert_eq!(spec.weight, 400);
+		assert!("Inter,,Ubuntu".parse::<FontSpec>().is_err());
+	}
+}
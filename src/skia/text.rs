@@ -0,0 +1,153 @@
+//! Text shaping and layout with skparagraph.
+//!
+//! Clay measures text while computing the layout and the renderer draws it right after, both go
+//! through the same cache of shaped paragraphs so the text is only shaped once and the measured
+//! size always matches what gets drawn. Shaping gives us kerning and ligatures, font fallback for
+//! emoji and CJK usernames and bidirectional text.
+use std::{cell::RefCell, collections::HashMap};
+
+use skia_safe::{
+	textlayout::{
+		FontCollection, Paragraph, ParagraphBuilder, ParagraphStyle, TextStyle, TypefaceFontProvider,
+	},
+	Canvas, Color, FontMgr, Point,
+};
+
+use super::fonts::Fonts;
+
+/// Paragraphs that weren't used for this many frames are dropped from the cache
+const MAX_UNUSED_FRAMES: u64 = 120;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ParagraphKey {
+	text: String,
+	font_id: usize,
+	/// `f32::to_bits` of the font size
+	font_size: u32,
+	color: u32,
+}
+
+struct CachedParagraph {
+	paragraph: Paragraph,
+	last_used: u64,
+}
+
+pub struct TextCache {
+	font_collection: FontCollection,
+	/// Family name of each font id in the font collection
+	families: Vec<String>,
+	paragraphs: HashMap<ParagraphKey, CachedParagraph>,
+	frame: u64,
+}
+
+thread_local! {
+	static TEXT_CACHE: RefCell<Option<TextCache>> = const { RefCell::new(None) };
+}
+
+impl TextCache {
+	fn new(fonts: &Fonts) -> Self {
+		let mut provider = TypefaceFontProvider::new();
+		let families = (0..fonts.len() as u16)
+			.map(|font_id| {
+				// Every font id gets its own family so the weight of the typeface is always respected
+				let family = format!("tibs-font-{font_id}");
+				// Fonts that couldn't be loaded at all are left to the default font manager
+				if let Some(typeface) = fonts.get(font_id) {
+					provider.register_typeface(typeface.clone(), family.as_str());
+				}
+				family
+			})
+			.collect();
+		let font_manager: FontMgr = provider.into();
+		let mut font_collection = FontCollection::new();
+		font_collection.set_asset_font_manager(font_manager);
+		// Fallback for characters that the configured fonts don't have
+		font_collection.set_default_font_manager(FontMgr::new(), None);
+		Self {
+			font_collection,
+			families,
+			paragraphs: HashMap::new(),
+			frame: 0,
+		}
+	}
+
+	/// Runs `f` with the cache of the current thread, creating it with `fonts` if needed.
+	pub fn with<R>(fonts: &Fonts, f: impl FnOnce(&mut TextCache) -> R) -> R {
+		TEXT_CACHE.with_borrow_mut(|cache| f(cache.get_or_insert_with(|| TextCache::new(fonts))))
+	}
+
+	/// Returns the shaped paragraph for `text`, laid out on a single line.
+	pub fn paragraph(
+		&mut self,
+		text: &str,
+		font_id: usize,
+		font_size: f32,
+		color: Color,
+	) -> &Paragraph {
+		let key = ParagraphKey {
+			text: text.to_string(),
+			font_id,
+			font_size: font_size.to_bits(),
+			color: u32::from_be_bytes([color.a(), color.r(), color.g(), color.b()]),
+		};
+		let frame = self.frame;
+		let font_collection = &self.font_collection;
+		let family = self.families.get(font_id).or(self.families.first());
+		let cached = self.paragraphs.entry(key).or_insert_with(|| {
+			let mut text_style = TextStyle::new();
+			text_style.set_font_size(font_size).set_color(color);
+			if let Some(family) = family {
+				text_style.set_font_families(&[family]);
+			}
+			let mut paragraph_style = ParagraphStyle::new();
+			paragraph_style.set_text_style(&text_style);
+			let mut builder = ParagraphBuilder::new(&paragraph_style, font_collection.clone());
+			builder.push_style(&text_style).add_text(text);
+			let mut paragraph = builder.build();
+			// Clay takes care of wrapping, so paragraphs are always laid out on a single line
+			paragraph.layout(f32::INFINITY);
+			CachedParagraph {
+				paragraph,
+				last_used: frame,
+			}
+		});
+		cached.last_used = frame;
+		&cached.paragraph
+	}
+
+	/// Size of `text` once shaped, the height is the height of the line.
+	pub fn measure(
+		&mut self,
+		text: &str,
+		font_id: usize,
+		font_size: f32,
+		color: Color,
+	) -> (f32, f32) {
+		let paragraph = self.paragraph(text, font_id, font_size, color);
+		(paragraph.max_intrinsic_width(), paragraph.height())
+	}
+
+	/// Draws `text` with its top left corner at `position`.
+	pub fn draw(
+		&mut self,
+		canvas: &Canvas,
+		text: &str,
+		position: impl Into<Point>,
+		font_id: usize,
+		font_size: f32,
+		color: Color,
+	) {
+		self
+			.paragraph(text, font_id, font_size, color)
+			.paint(canvas, position);
+	}
+
+	/// Drops the paragraphs that weren't used recently, should be called once per frame.
+	pub fn end_frame(&mut self) {
+		let frame = self.frame;
+		self
+			.paragraphs
+			.retain(|_, cached| frame - cached.last_used < MAX_UNUSED_FRAMES);
+		self.frame += 1;
+	}
+}
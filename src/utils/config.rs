@@ -0,0 +1,185 @@
+//! Runtime configuration.
+//!
+//! tibs is configured through `TIBS_*` environment variables (the NixOS module sets them in the
+//! service script). Everything is read once, the first time [`CONFIG`] is accessed.
+use std::{path::PathBuf, str::FromStr, sync::LazyLock};
+
+use rustamarine::{device_config::DeviceConfig, keymap::KeyboardLayout};
+
+use crate::{
+	background::ImageFit,
+	input_devices::parse_device_config,
+	keybindings::Keybindings,
+	keyboard_layouts::parse_layouts,
+	outputs::{MultiMonitorPolicy, OutputSettings, UiScale},
+	skia::fonts::{FontSource, FontSpec},
+};
+
+pub static CONFIG: LazyLock<Config> = LazyLock::new(Config::from_env);
+
+pub struct Config {
+	/// Folder the images, shaders and bundled fonts are loaded from.
+	pub assets_folder: PathBuf,
+	/// DRM device to display on (e.g. `/dev/dri/card1`), aquamarine picks one by default.
+	pub drm_device: Option<PathBuf>,
+	/// Folder where what tibs remembers between boots is saved, like the layout of each user.
+	pub state_folder: PathBuf,
+	pub fonts: FontsConfig,
+	pub background: BackgroundConfig,
+	pub outputs: OutputsConfig,
+	/// Shortcuts of the UI, the defaults with the ones in `TIBS_KEYBINDINGS` replacing them.
+	pub keybindings: Keybindings,
+	/// Layouts that can be picked on the login screen, as `us,pt,us(intl)` in
+	/// `TIBS_KEYBOARD_LAYOUTS`, at most 4. If it's empty, rustamarine uses `RUSTAMARINE_KB_LAYOUT`.
+	pub keyboard_layouts: Vec<KeyboardLayout>,
+	/// Turns Num Lock on at startup (`TIBS_NUMLOCK`), off by default like the kernel does.
+	pub numlock: bool,
+	/// libinput settings of mice (`TIBS_MOUSE`) and touchpads (`TIBS_TOUCHPAD`), see
+	/// [`crate::input_devices`] for the format.
+	pub mouse: DeviceConfig,
+	pub touchpad: DeviceConfig,
+	/// Use the `~/.config/tibs/input` settings of the user who logged in last
+	/// (`TIBS_USER_INPUT_SETTINGS`), off by default.
+	pub user_input_settings: bool,
+	/// Don't list the users on the login screen, the username has to be typed
+	/// (`TIBS_HIDE_USER_LIST`).
+	pub hide_user_list: bool,
+}
+
+pub struct BackgroundConfig {
+	/// Image, animated image or folder of images to use instead of the mesh gradient shader.
+	pub source: Option<PathBuf>,
+	pub fit: ImageFit,
+	/// Blur radius (sigma) applied to image backgrounds, `0` disables it.
+	pub blur: f32,
+	/// Seconds each image stays on screen when `source` is a folder.
+	pub slideshow_interval: f32,
+	/// Seconds it takes to crossfade between two background images.
+	pub crossfade_duration: f32,
+	/// Show `~/.config/tibs/background.*` of the selected user on the login screen
+	/// (`TIBS_USER_WALLPAPERS`), off by default as they're decoded by tibs, which runs as root.
+	pub user_wallpapers: bool,
+}
+
+pub struct OutputsConfig {
+	pub policy: MultiMonitorPolicy,
+	/// Name of the output (e.g. `DP-1`) the UI is shown on, defaults to the first one.
+	pub primary: Option<String>,
+	/// Scale of the UI on outputs that don't have one set in `settings`, `auto` (the default) guesses
+	/// it from the DPI of the output.
+	pub ui_scale: UiScale,
+	/// Per output mode, transform and scale, separated by `;` in `TIBS_OUTPUTS`.
+	pub settings: Vec<OutputSettings>,
+}
+
+pub struct FontsConfig {
+	/// Fallback chain of the built-in regular, medium and bold fonts, separated by `,` in
+	/// `TIBS_FONT_FAMILY`. The system default font is used if none of them can be loaded.
+	pub family: Vec<FontSource>,
+	/// Replacements for the built-in `regular`, `bold` and `medium` fonts, as
+	/// `bold=Family,asset:fonts.Other:700` separated by `;` in `TIBS_FONTS`.
+	pub fonts: Vec<(String, FontSpec)>,
+}
+
+impl OutputsConfig {
+	pub fn settings_for(&self, name: &str) -> Option<&OutputSettings> {
+		self.settings.iter().find(|s| s.name == name)
+	}
+}
+
+impl Config {
+	pub fn from_env() -> Self {
+		Self {
+			assets_folder: env("TIBS_ASSETS_FOLDER").unwrap_or_else(|| "assets".into()),
+			drm_device: env::<String>("TIBS_DRM_DEVICE")
+				.filter(|s| !s.is_empty())
+				.map(PathBuf::from),
+			state_folder: env("TIBS_STATE_FOLDER").unwrap_or_else(|| "/var/lib/tibs".into()),
+			fonts: FontsConfig {
+				family: env::<String>("TIBS_FONT_FAMILY")
+					.unwrap_or_else(|| "UbuntuSans NF,Ubuntu Sans,sans-serif".into())
+					.split(',')
+					.filter_map(|s| {
+						let source = s.parse().ok();
+						if source.is_none() {
+							log::warn!("Ignoring invalid font family: {s:?}");
+						}
+						source
+					})
+					.collect(),
+				fonts: env::<String>("TIBS_FONTS")
+					.unwrap_or_default()
+					.split(';')
+					.filter(|s| !s.trim().is_empty())
+					.filter_map(|s| {
+						let font = s
+							.split_once('=')
+							.and_then(|(name, spec)| Some((name.trim().to_string(), spec.parse().ok()?)));
+						if font.is_none() {
+							log::warn!("Ignoring invalid font: {s:?}");
+						}
+						font
+					})
+					.collect(),
+			},
+			background: BackgroundConfig {
+				source: env::<String>("TIBS_BACKGROUND")
+					.filter(|s| !s.is_empty() && s != "meshgradient")
+					.map(PathBuf::from),
+				fit: env("TIBS_BACKGROUND_FIT").unwrap_or_default(),
+				blur: env("TIBS_BACKGROUND_BLUR").unwrap_or(0.0),
+				slideshow_interval: env("TIBS_BACKGROUND_SLIDESHOW_INTERVAL").unwrap_or(30.0),
+				crossfade_duration: env("TIBS_BACKGROUND_CROSSFADE").unwrap_or(1.5),
+				user_wallpapers: env_flag("TIBS_USER_WALLPAPERS").unwrap_or(false),
+			},
+			outputs: OutputsConfig {
+				policy: env("TIBS_MULTI_MONITOR").unwrap_or_default(),
+				primary: env("TIBS_PRIMARY_OUTPUT"),
+				ui_scale: env("TIBS_UI_SCALE").unwrap_or_default(),
+				settings: env::<String>("TIBS_OUTPUTS")
+					.unwrap_or_default()
+					.split(';')
+					.filter(|s| !s.trim().is_empty())
+					.filter_map(|s| {
+						let settings = s.parse().ok();
+						if settings.is_none() {
+							log::warn!("Ignoring invalid output settings: {s:?}");
+						}
+						settings
+					})
+					.collect(),
+			},
+			keybindings: Keybindings::with_overrides(
+				&env::<String>("TIBS_KEYBINDINGS").unwrap_or_default(),
+			),
+			keyboard_layouts: parse_layouts(&env::<String>("TIBS_KEYBOARD_LAYOUTS").unwrap_or_default()),
+			numlock: env_flag("TIBS_NUMLOCK").unwrap_or(false),
+			mouse: parse_device_config(&env::<String>("TIBS_MOUSE").unwrap_or_default()),
+			touchpad: parse_device_config(&env::<String>("TIBS_TOUCHPAD").unwrap_or_default()),
+			user_input_settings: env_flag("TIBS_USER_INPUT_SETTINGS").unwrap_or(false),
+			hide_user_list: env_flag("TIBS_HIDE_USER_LIST").unwrap_or(false),
+		}
+	}
+}
+
+/// Reads and parses an environment variable, logging a warning if it's set but invalid.
+pub fn env<T: FromStr>(name: &str) -> Option<T> {
+	let value = std::env::var(name).ok()?;
+	let parsed = value.parse().ok();
+	if parsed.is_none() {
+		log::warn!("Ignoring invalid value for {name}: {value:?}");
+	}
+	parsed
+}
+
+/// Reads a boolean environment variable (`1`/`0`, `true`/`false`).
+pub fn env_flag(name: &str) -> Option<bool> {
+	match std::env::var(name).ok()?.as_str() {
+		"1" | "true" => Some(true),
+		"0" | "false" => Some(false),
+		value => {
+			log::warn!("Ignoring invalid value for {name}: {value:?}");
+			None
+		}
+	}
+}
@@ -0,0 +1,173 @@
+//! Keyboard focus of the UI.
+//!
+//! Screens list the elements that can be focused every frame, in Tab order. Tab and Shift+Tab move
+//! through that list, Up and Down move between the elements of the same list (the ones with the same
+//! name, like the items of a popup), and Enter or Space activate the focused element. The focus ring
+//! is only shown after the focus was moved with the keyboard, a mouse click hides it.
+use clay_layout::{math::BoundingBox, Clay};
+use rustamarine::{events::Modifiers, keys};
+
+use crate::input::Input;
+
+/// A clay element that can be focused, `c.id(name)` or `c.id_index(name, index)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FocusId {
+	pub name: &'static str,
+	pub index: Option<u32>,
+}
+
+impl FocusId {
+	pub const fn new(name: &'static str) -> Self {
+		Self { name, index: None }
+	}
+
+	pub const fn indexed(name: &'static str, index: u32) -> Self {
+		Self {
+			name,
+			index: Some(index),
+		}
+	}
+
+	/// Where the element was laid out in the last frame.
+	pub fn bounding_box(self, c: &Clay) -> Option<BoundingBox> {
+		match self.index {
+			Some(index) => c.bounding_box(c.id_index(self.name, index)),
+			None => c.bounding_box(c.id(self.name)),
+		}
+	}
+}
+
+pub struct FocusManager {
+	focused: Option<FocusId>,
+	ring_visible: bool,
+}
+
+impl FocusManager {
+	pub fn new(focused: FocusId) -> Self {
+		Self {
+			focused: Some(focused),
+			ring_visible: false,
+		}
+	}
+
+	pub fn focused(&self) -> Option<FocusId> {
+		self.focused
+	}
+
+	pub fn is_focused(&self, id: FocusId) -> bool {
+		self.focused == Some(id)
+	}
+
+	/// Moves the focus to `id`, e.g. into a popup that was just opened. The focus ring stays visible
+	/// if it was.
+	pub fn focus(&mut self, id: FocusId) {
+		self.focused = Some(id);
+	}
+
+	/// Handles Tab, Shift+Tab, Up and Down. `order` is the elements that can be focused this frame,
+	/// if the focused one isn't one of them the focus goes to the first one.
+	pub fn update(&mut self, input: &dyn Input, order: &[FocusId]) {
+		if input.is_mouse_button_pressed(0) {
+			self.ring_visible = false;
+		}
+		let Some(current) = self
+			.focused
+			.and_then(|focused| order.iter().position(|&id| id == focused))
+		else {
+			self.focused = order.first().copied();
+			return;
+		};
+		// xkb turns Shift+Tab into ISO_Left_Tab
+		let shift_tab = input.is_key_pressed(keys::KEY_ISO_Left_Tab)
+			|| (input.is_key_pressed(keys::KEY_Tab) && input.modifiers().contains(Modifiers::SHIFT));
+		let next = if shift_tab {
+			Some((current + order.len() - 1) % order.len())
+		} else if input.is_key_pressed(keys::KEY_Tab) {
+			Some((current + 1) % order.len())
+		} else if input.is_key_pressed(keys::KEY_Up) {
+			current
+				.checked_sub(1)
+				.filter(|&i| order[i].name == order[current].name)
+		} else if input.is_key_pressed(keys::KEY_Down) {
+			Some(current + 1).filter(|&i| {
+				order
+					.get(i)
+					.is_some_and(|id| id.name == order[current].name)
+			})
+		} else {
+			None
+		};
+		if let Some(next) = next {
+			self.focused = Some(order[next]);
+			self.ring_visible = true;
+		}
+	}
+
+	/// `true` if `id` is focused and Enter or Space was pressed.
+	pub fn activated(&self, id: FocusId, input: &dyn Input) -> bool {
+		self.is_focused(id)
+			&& [keys::KEY_Return, keys::KEY_KP_Enter, keys::KEY_space]
+				.into_iter()
+				.any(|key| input.is_key_pressed(key))
+	}
+
+	/// Where to draw the focus ring, `None` while it's hidden.
+	pub fn ring(&self, c: &Clay) -> Option<BoundingBox> {
+		self.focused.filter(|_| self.ring_visible)?.bounding_box(c)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::input::FakeInput;
+
+	const ORDER: [FocusId; 4] = [
+		FocusId::new("input"),
+		FocusId::new("button"),
+		FocusId::indexed("item", 0),
+		FocusId::indexed("item", 1),
+	];
+
+	fn press(key: u32) -> FakeInput {
+		FakeInput {
+			keys_pressed: [key].into(),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn tab_order() {
+		let mut focus = FocusManager::new(ORDER[0]);
+		focus.update(&press(keys::KEY_Tab), &ORDER);
+		assert_eq!(focus.focused(), Some(ORDER[1]));
+		assert!(focus.ring_visible);
+		focus.update(&press(keys::KEY_ISO_Left_Tab), &ORDER);
+		focus.update(&press(keys::KEY_ISO_Left_Tab), &ORDER);
+		assert_eq!(focus.focused(), Some(ORDER[3]));
+		focus.update(&press(keys::KEY_Tab), &ORDER);
+		assert_eq!(focus.focused(), Some(ORDER[0]));
+		// The focused element went away, e.g. a popup was closed
+		focus.update(&FakeInput::default(), &ORDER[1..]);
+		assert_eq!(focus.focused(), Some(ORDER[1]));
+		focus.update(&FakeInput::default(), &[]);
+		assert_eq!(focus.focused(), None);
+	}
+
+	#[test]
+	fn arrows_stay_in_the_list() {
+		let mut focus = FocusManager::new(ORDER[2]);
+		focus.update(&press(keys::KEY_Up), &ORDER);
+		assert_eq!(focus.focused(), Some(ORDER[2]));
+		focus.update(&press(keys::KEY_Down), &ORDER);
+		assert_eq!(focus.focused(), Some(ORDER[3]));
+		focus.update(&press(keys::KEY_Down), &ORDER);
+		assert_eq!(focus.focused(), Some(ORDER[3]));
+		assert!(focus.activated(ORDER[3], &press(keys::KEY_space)));
+		assert!(!focus.activated(ORDER[2], &press(keys::KEY_Return)));
+		let mut click = FakeInput::default();
+		click.click();
+		focus.update(&click, &ORDER);
+		assert!(!focus.ring_visible);
+	}
+}
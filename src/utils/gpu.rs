@@ -0,0 +1,84 @@
+//! Moving to another GPU at runtime.
+//!
+//! tibs starts before the driver of the GPU is loaded on some machines, so it might start on the
+//! boot framebuffer (simpledrm). When a real GPU shows up (or the one in use is unplugged) the
+//! backend is recreated on the new device, see `run_graphics` in `main.rs`.
+use std::path::Path;
+
+use rustamarine::drm::DrmDevice;
+
+/// Device to move to, given the one in use and the devices that are available now.
+///
+/// tibs stays on a real GPU as long as it's there, but leaves boot framebuffers as soon as there's
+/// something better.
+pub fn migration_target<'a>(current: &Path, devices: &'a [DrmDevice]) -> Option<&'a DrmDevice> {
+	let current_device = devices.iter().find(|device| device.path == current);
+	if current_device.is_some_and(|device| !device.boot_framebuffer) {
+		return None;
+	}
+	devices
+		.iter()
+		.filter(|device| device.path != current)
+		// Another boot framebuffer is only better than nothing
+		.filter(|device| current_device.is_none() || !device.boot_framebuffer)
+		.min_by_key(|device| device.boot_framebuffer)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn device(path: &str, boot_framebuffer: bool) -> DrmDevice {
+		DrmDevice {
+			path: path.into(),
+			boot_framebuffer,
+		}
+	}
+
+	#[test]
+	fn leaves_boot_framebuffer() {
+		let simpledrm = device("/dev/dri/card0", true);
+		let i915 = device("/dev/dri/card1", false);
+		assert_eq!(
+			migration_target(
+				Path::new("/dev/dri/card0"),
+				&[simpledrm.clone(), i915.clone()]
+			),
+			Some(&i915)
+		);
+		// simpledrm is usually removed before the new device shows up
+		assert_eq!(
+			migration_target(Path::new("/dev/dri/card0"), &[i915.clone()]),
+			Some(&i915)
+		);
+		assert_eq!(
+			migration_target(Path::new("/dev/dri/card0"), &[simpledrm]),
+			None
+		);
+	}
+
+	#[test]
+	fn stays_on_real_gpu() {
+		let i915 = device("/dev/dri/card1", false);
+		let nvidia = device("/dev/dri/card2", false);
+		assert_eq!(
+			migration_target(Path::new("/dev/dri/card1"), &[i915, nvidia]),
+			None
+		);
+	}
+
+	#[test]
+	fn replaces_unplugged_gpu() {
+		let simpledrm = device("/dev/dri/card0", true);
+		let nvidia = device("/dev/dri/card2", false);
+		assert_eq!(
+			migration_target(Path::new("/dev/dri/card1"), &[simpledrm.clone()]),
+			Some(&simpledrm)
+		);
+		assert_eq!(
+			migration_target(Path::new("/dev/dri/card1"), &[simpledrm, nvidia.clone()]),
+			Some(&nvidia)
+		);
+		assert_eq!(migration_target(Path::new("/dev/dri/card1"), &[]), None);
+	}
+}
@@ -0,0 +1,109 @@
+//! Input state read by the UI.
+//!
+//! The screens only read the keyboard and mouse through [`Input`] so they can be driven by
+//! something other than rustamarine, like the scripted frames of the headless mode.
+use std::collections::HashSet;
+
+use rustamarine::{events::Modifiers, shortcut::Shortcut, Rustamarine};
+
+/// Keyboard and mouse state of the current frame, see the methods of [`Rustamarine`] with the
+/// same names.
+pub trait Input {
+	fn is_key_down(&self, key: u32) -> bool;
+	fn is_key_pressed(&self, key: u32) -> bool;
+	fn is_mouse_button_down(&self, button: u32) -> bool;
+	fn is_mouse_button_pressed(&self, button: u32) -> bool;
+	fn is_mouse_button_released(&self, button: u32) -> bool;
+	fn get_mouse_scroll_x(&self) -> f64;
+	fn get_mouse_scroll_y(&self) -> f64;
+	fn get_typed_characters(&self) -> String;
+	fn modifiers(&self) -> Modifiers;
+
+	/// `true` if the key of `shortcut` was just pressed with exactly its modifiers held.
+	fn is_shortcut_pressed(&self, shortcut: &Shortcut) -> bool {
+		shortcut.modifiers == self.modifiers().without_locks()
+			&& shortcut.keysyms().any(|keysym| self.is_key_pressed(keysym))
+	}
+}
+
+impl Input for Rustamarine {
+	fn is_key_down(&self, key: u32) -> bool {
+		Rustamarine::is_key_down(self, key)
+	}
+	fn is_key_pressed(&self, key: u32) -> bool {
+		Rustamarine::is_key_pressed(self, key)
+	}
+	fn is_mouse_button_down(&self, button: u32) -> bool {
+		Rustamarine::is_mouse_button_down(self, button)
+	}
+	fn is_mouse_button_pressed(&self, button: u32) -> bool {
+		Rustamarine::is_mouse_button_pressed(self, button)
+	}
+	fn is_mouse_button_released(&self, button: u32) -> bool {
+		Rustamarine::is_mouse_button_released(self, button)
+	}
+	fn get_mouse_scroll_x(&self) -> f64 {
+		Rustamarine::get_mouse_scroll_x(self)
+	}
+	fn get_mouse_scroll_y(&self) -> f64 {
+		Rustamarine::get_mouse_scroll_y(self)
+	}
+	fn get_typed_characters(&self) -> String {
+		Rustamarine::get_typed_characters(self)
+	}
+	fn modifiers(&self) -> Modifiers {
+		Rustamarine::modifiers(self)
+	}
+}
+
+/// Input of a single frame set by hand, keys and buttons are only "pressed" and "released" on the
+/// frame they are listed in.
+#[derive(Default, Clone, Debug)]
+pub struct FakeInput {
+	pub keys_down: HashSet<u32>,
+	pub keys_pressed: HashSet<u32>,
+	pub buttons_down: HashSet<u32>,
+	pub buttons_pressed: HashSet<u32>,
+	pub buttons_released: HashSet<u32>,
+	pub scroll: (f64, f64),
+	pub typed_characters: String,
+	pub modifiers: Modifiers,
+}
+
+impl FakeInput {
+	/// A left click: the button is pressed and released in the same frame.
+	pub fn click(&mut self) {
+		self.buttons_pressed.insert(0);
+		self.buttons_released.insert(0);
+	}
+}
+
+impl Input for FakeInput {
+	fn is_key_down(&self, key: u32) -> bool {
+		self.keys_down.contains(&key)
+	}
+	fn is_key_pressed(&self, key: u32) -> bool {
+		self.keys_pressed.contains(&key)
+	}
+	fn is_mouse_button_down(&self, button: u32) -> bool {
+		self.buttons_down.contains(&button)
+	}
+	fn is_mouse_button_pressed(&self, button: u32) -> bool {
+		self.buttons_pressed.contains(&button)
+	}
+	fn is_mouse_button_released(&self, button: u32) -> bool {
+		self.buttons_released.contains(&button)
+	}
+	fn get_mouse_scroll_x(&self) -> f64 {
+		self.scroll.0
+	}
+	fn get_mouse_scroll_y(&self) -> f64 {
+		self.scroll.1
+	}
+	fn get_typed_characters(&self) -> String {
+		self.typed_characters.clone()
+	}
+	fn modifiers(&self) -> Modifiers {
+		self.modifiers
+	}
+}
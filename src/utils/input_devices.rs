@@ -0,0 +1,273 @@
+//! libinput settings of mice and touchpads.
+//!
+//! They're set with `TIBS_MOUSE` and `TIBS_TOUCHPAD`, as comma separated `setting=value` pairs,
+//! e.g. `tap=1,natural-scroll=1,accel-profile=flat,accel-speed=0.3,left-handed=0,disable-while-typing=1`.
+//! With `TIBS_USER_INPUT_SETTINGS`, the settings the user who logged in last wrote in
+//! `~/.config/tibs/input` replace them, as `mouse=...` and `touchpad=...` lines in the same format.
+//! That user is saved in `<state folder>/last-user`.
+use std::{
+	fs::OpenOptions,
+	io::{self, Read},
+	os::unix::fs::{MetadataExt, OpenOptionsExt},
+	path::{Path, PathBuf},
+};
+
+use nix::libc;
+use rustamarine::{
+	device_config::{AccelProfile, DeviceConfig},
+	events::DeviceKind,
+	Rustamarine,
+};
+use uzers::{os::unix::UserExt, User};
+
+use crate::config::CONFIG;
+
+/// Parses `setting=value` pairs, invalid ones are logged and skipped.
+pub fn parse_device_config(s: &str) -> DeviceConfig {
+	let (config, invalid) = parse_settings(s);
+	for n in invalid {
+		log::warn!("Ignoring invalid input device setting number {n}");
+	}
+	config
+}
+
+/// Parses `setting=value` pairs, returning the config along with the positions (starting at 1)
+/// of the invalid ones. Only their position is reported as the string may come from a file of
+/// the user.
+fn parse_settings(s: &str) -> (DeviceConfig, Vec<usize>) {
+	// A setting is only changed if its value is valid, so an invalid value doesn't reset one set
+	// earlier
+	fn set<T>(field: &mut Option<T>, value: Option<T>) -> bool {
+		value.map(|value| *field = Some(value)).is_some()
+	}
+	let mut config = DeviceConfig::default();
+	let mut invalid = Vec::new();
+	for (i, entry) in s.split(',').filter(|s| !s.trim().is_empty()).enumerate() {
+		let valid = entry.split_once('=').is_some_and(|(setting, value)| {
+			let value = value.trim();
+			let flag = || match value {
+				"1" | "true" => Some(true),
+				"0" | "false" => Some(false),
+				_ => None,
+			};
+			match setting.trim() {
+				"tap" => set(&mut config.tap_to_click, flag()),
+				"natural-scroll" => set(&mut config.natural_scroll, flag()),
+				"left-handed" => set(&mut config.left_handed, flag()),
+				"disable-while-typing" => set(&mut config.disable_while_typing, flag()),
+				"accel-profile" => set(
+					&mut config.accel_profile,
+					match value {
+						"flat" => Some(AccelProfile::Flat),
+						"adaptive" => Some(AccelProfile::Adaptive),
+						_ => None,
+					},
+				),
+				"accel-speed" => set(
+					&mut config.accel_speed,
+					value
+						.parse::<f64>()
+						.ok()
+						.filter(|speed| (-1.0..=1.0).contains(speed)),
+				),
+				_ => false,
+			}
+		});
+		if !valid {
+			invalid.push(i + 1);
+		}
+	}
+	(config, invalid)
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct InputSettings {
+	pub mouse: DeviceConfig,
+	pub touchpad: DeviceConfig,
+}
+
+impl InputSettings {
+	/// Parses the `mouse=...` and `touchpad=...` lines of `~/.config/tibs/input`. Only the number
+	/// of the invalid lines is logged, never their content.
+	fn parse_user_settings(s: &str) -> Self {
+		let mut settings = Self::default();
+		for (i, line) in s.lines().map(str::trim).enumerate() {
+			let (device, config) = if let Some(config) = line.strip_prefix("mouse=") {
+				(&mut settings.mouse, config)
+			} else if let Some(config) = line.strip_prefix("touchpad=") {
+				(&mut settings.touchpad, config)
+			} else {
+				if !line.is_empty() && !line.starts_with('#') {
+					log::warn!("Ignoring invalid line {} of the user input settings", i + 1);
+				}
+				continue;
+			};
+			let invalid;
+			(*device, invalid) = parse_settings(config);
+			for n in invalid {
+				log::warn!(
+					"Ignoring invalid setting number {n} on line {} of the user input settings",
+					i + 1
+				);
+			}
+		}
+		settings
+	}
+
+	/// The settings of the config, with the ones of the user who logged in last replacing them if
+	/// `TIBS_USER_INPUT_SETTINGS` is enabled.
+	pub fn load() -> Self {
+		let config = Self {
+			mouse: CONFIG.mouse,
+			touchpad: CONFIG.touchpad,
+		};
+		if !CONFIG.user_input_settings {
+			return config;
+		}
+		let Some(user) = std::fs::read_to_string(last_user_path())
+			.ok()
+			.and_then(|username| uzers::get_user_by_name(username.trim()))
+		else {
+			return config;
+		};
+		let Some(user_settings) = read_user_settings(&user) else {
+			return config;
+		};
+		let user_settings = Self::parse_user_settings(&user_settings);
+		Self {
+			mouse: user_settings.mouse.or(config.mouse),
+			touchpad: user_settings.touchpad.or(config.touchpad),
+		}
+	}
+
+	pub fn apply(&self, rmar: &mut Rustamarine) {
+		rmar.set_device_config(DeviceKind::Pointer, self.mouse);
+		rmar.set_device_config(DeviceKind::Touchpad, self.touchpad);
+	}
+}
+
+/// Reads `~/.config/tibs/input` of `user`.
+fn read_user_settings(user: &User) -> Option<String> {
+	/// The settings are a few lines, anything bigger isn't a settings file
+	const MAX_SIZE: u64 = 64 * 1024;
+	let path = user.home_dir().join(".config/tibs/input");
+	let settings = read_user_file(&path, user.uid(), MAX_SIZE)?;
+	match String::from_utf8(settings) {
+		Ok(settings) => Some(settings),
+		Err(e) => {
+			log::warn!("Failed to read {}: {e}", path.display());
+			None
+		}
+	}
+}
+
+/// Reads at most `max_size` bytes of a file in the home of the user with `uid`. tibs runs as root,
+/// so the file is only read if it's a regular file owned by the user and symlinks aren't followed,
+/// otherwise the user could make tibs read any file. Missing files are `None` without a warning.
+pub fn read_user_file(path: &Path, uid: u32, max_size: u64) -> Option<Vec<u8>> {
+	// O_NONBLOCK so opening a FIFO doesn't block, it's rejected below
+	let file = match OpenOptions::new()
+		.read(true)
+		.custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+		.open(path)
+	{
+		Ok(file) => file,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+		Err(e) => {
+			log::warn!("Failed to open {}: {e}", path.display());
+			return None;
+		}
+	};
+	let metadata = file.metadata().ok()?;
+	if !metadata.is_file() || metadata.uid() != uid {
+		log::warn!(
+			"Ignoring {}, it isn't a regular file owned by its user",
+			path.display()
+		);
+		return None;
+	}
+	if metadata.len() > max_size {
+		log::warn!(
+			"Ignoring {}, it's bigger than {max_size} bytes",
+			path.display()
+		);
+		return None;
+	}
+	let mut data = Vec::new();
+	if let Err(e) = file.take(max_size).read_to_end(&mut data) {
+		log::warn!("Failed to read {}: {e}", path.display());
+		return None;
+	}
+	Some(data)
+}
+
+fn last_user_path() -> PathBuf {
+	CONFIG.state_folder.join("last-user")
+}
+
+/// Saves the user that logged in, whose input settings are used the next time tibs starts.
+pub fn remember_last_user(username: &str) {
+	let path = last_user_path();
+	let result = path
+		.parent()
+		.map_or(Ok(()), std::fs::create_dir_all)
+		.and_then(|_| std::fs::write(&path, username));
+	if let Err(e) = result {
+		log::warn!("Failed to save the last user to {}: {e}", path.display());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn device_config() {
+		let config = parse_device_config(
+			"tap=1, natural-scroll=false,accel-profile=flat,accel-speed=-0.5,swipe=1,left-handed=maybe",
+		);
+		assert_eq!(
+			config,
+			DeviceConfig {
+				tap_to_click: Some(true),
+				natural_scroll: Some(false),
+				accel_profile: Some(AccelProfile::Flat),
+				accel_speed: Some(-0.5),
+				..Default::default()
+			}
+		);
+		assert_eq!(parse_device_config("accel-speed=2").accel_speed, None);
+	}
+
+	#[test]
+	fn invalid_settings() {
+		let (config, invalid) = parse_settings(
+			"left-handed=1,left-handed=maybe,accel-profile=flat,accel-profile=foo,accel-speed=0.5,accel-speed=2,tap",
+		);
+		assert_eq!(invalid, [2, 4, 6, 7]);
+		assert_eq!(
+			config,
+			DeviceConfig {
+				left_handed: Some(true),
+				accel_profile: Some(AccelProfile::Flat),
+				accel_speed: Some(0.5),
+				..Default::default()
+			}
+		);
+	}
+
+	#[test]
+	fn user_settings_replace_the_config() {
+		let user = InputSettings::parse_user_settings("# touchpad only\ntouchpad=tap=0\n");
+		assert_eq!(user.mouse, DeviceConfig::default());
+		let config = parse_device_config("tap=1,natural-scroll=1");
+		assert_eq!(
+			user.touchpad.or(config),
+			DeviceConfig {
+				tap_to_click: Some(false),
+				natural_scroll: Some(true),
+				..Default::default()
+			}
+		);
+	}
+}
@@ -0,0 +1,195 @@
+//! Keyboard shortcuts of the UI.
+//!
+//! Every [`Action`] has a list of shortcuts, the defaults can be replaced with
+//! `TIBS_KEYBINDINGS`, e.g. `delete-word-backward=Ctrl+BackSpace,Alt+BackSpace;select-all=`
+//! (an empty list disables the action).
+use std::str::FromStr;
+
+use rustamarine::{events::Modifiers, keys, shortcut::Shortcut};
+
+use crate::input::Input;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+	SelectAll,
+	DeleteWordBackward,
+	DeleteWordForward,
+	/// Deletes everything before the cursor
+	DeleteToStart,
+	WordLeft,
+	WordRight,
+	NextKeyboardLayout,
+	/// Only in dev mode
+	ToggleDevtools,
+}
+
+impl Action {
+	pub const ALL: [Self; 8] = [
+		Self::SelectAll,
+		Self::DeleteWordBackward,
+		Self::DeleteWordForward,
+		Self::DeleteToStart,
+		Self::WordLeft,
+		Self::WordRight,
+		Self::NextKeyboardLayout,
+		Self::ToggleDevtools,
+	];
+
+	/// Name used in `TIBS_KEYBINDINGS`
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::SelectAll => "select-all",
+			Self::DeleteWordBackward => "delete-word-backward",
+			Self::DeleteWordForward => "delete-word-forward",
+			Self::DeleteToStart => "delete-to-start",
+			Self::WordLeft => "word-left",
+			Self::WordRight => "word-right",
+			Self::NextKeyboardLayout => "next-keyboard-layout",
+			Self::ToggleDevtools => "toggle-devtools",
+		}
+	}
+
+	fn default_shortcuts(self) -> Vec<Shortcut> {
+		let ctrl = |keysym| Shortcut::new(Modifiers::CTRL, keysym);
+		match self {
+			Self::SelectAll => vec![ctrl(keys::KEY_a)],
+			Self::DeleteWordBackward => vec![
+				ctrl(keys::KEY_BackSpace),
+				Shortcut::new(Modifiers::ALT, keys::KEY_BackSpace),
+			],
+			Self::DeleteWordForward => vec![ctrl(keys::KEY_Delete)],
+			Self::DeleteToStart => vec![ctrl(keys::KEY_u)],
+			Self::WordLeft => vec![ctrl(keys::KEY_Left)],
+			Self::WordRight => vec![ctrl(keys::KEY_Right)],
+			Self::NextKeyboardLayout => vec![Shortcut::new(Modifiers::SUPER, keys::KEY_space)],
+			Self::ToggleDevtools => vec![Shortcut::new(Modifiers::NONE, keys::KEY_F12)],
+		}
+	}
+}
+
+impl FromStr for Action {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::ALL
+			.into_iter()
+			.find(|action| action.name() == s.trim())
+			.ok_or(())
+	}
+}
+
+pub struct Keybindings {
+	bindings: Vec<(Action, Vec<Shortcut>)>,
+}
+
+impl Default for Keybindings {
+	fn default() -> Self {
+		Self {
+			bindings: Action::ALL
+				.into_iter()
+				.map(|action| (action, action.default_shortcuts()))
+				.collect(),
+		}
+	}
+}
+
+impl Keybindings {
+	/// The default keybindings with the ones in `overrides` (`action=Shortcut,Shortcut;...`)
+	/// replacing them, invalid entries are logged and skipped.
+	pub fn with_overrides(overrides: &str) -> Self {
+		let mut keybindings = Self::default();
+		for entry in overrides.split(';').filter(|s| !s.trim().is_empty()) {
+			let Some((action, shortcuts)) = entry.split_once('=') else {
+				log::warn!("Ignoring invalid keybinding: {entry:?}");
+				continue;
+			};
+			let Ok(action) = action.parse() else {
+				log::warn!("Ignoring keybinding for unknown action: {entry:?}");
+				continue;
+			};
+			let shortcuts = shortcuts
+				.split(',')
+				.filter(|s| !s.trim().is_empty())
+				.map(str::parse)
+				.collect::<Result<Vec<Shortcut>, _>>();
+			match shortcuts {
+				Ok(shortcuts) => keybindings.set(action, shortcuts),
+				Err(e) => log::warn!("Ignoring keybinding {entry:?}: {e}"),
+			}
+		}
+		keybindings
+	}
+
+	pub fn set(&mut self, action: Action, shortcuts: Vec<Shortcut>) {
+		if let Some((_, current)) = self.bindings.iter_mut().find(|(a, _)| *a == action) {
+			*current = shortcuts;
+		}
+	}
+
+	pub fn shortcuts(&self, action: Action) -> &[Shortcut] {
+		self
+			.bindings
+			.iter()
+			.find(|(a, _)| *a == action)
+			.map_or(&[], |(_, shortcuts)| shortcuts)
+	}
+
+	/// Action whose shortcut was pressed this frame, if any.
+	pub fn pressed(&self, input: &dyn Input) -> Option<Action> {
+		self
+			.bindings
+			.iter()
+			.find(|(_, shortcuts)| {
+				shortcuts
+					.iter()
+					.any(|shortcut| input.is_shortcut_pressed(shortcut))
+			})
+			.map(|(action, _)| *action)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::input::FakeInput;
+
+	#[test]
+	fn overrides() {
+		let keybindings = Keybindings::with_overrides(
+			"select-all=;word-left=Alt+Left, Ctrl+b;jump=Ctrl+J;word-right=Hyper+Right",
+		);
+		assert!(keybindings.shortcuts(Action::SelectAll).is_empty());
+		assert_eq!(
+			keybindings.shortcuts(Action::WordLeft),
+			[
+				Shortcut::new(Modifiers::ALT, keys::KEY_Left),
+				Shortcut::new(Modifiers::CTRL, keys::KEY_b)
+			]
+		);
+		// Invalid entries keep the defaults
+		assert_eq!(
+			keybindings.shortcuts(Action::WordRight),
+			Action::WordRight.default_shortcuts()
+		);
+	}
+
+	#[test]
+	fn pressed() {
+		let keybindings = Keybindings::default();
+		let mut input = FakeInput {
+			modifiers: Modifiers::CTRL | Modifiers::CAPS_LOCK,
+			..Default::default()
+		};
+		input.keys_pressed.insert(keys::KEY_BackSpace);
+		assert_eq!(
+			keybindings.pressed(&input),
+			Some(Action::DeleteWordBackward)
+		);
+		input.modifiers = Modifiers::CTRL | Modifiers::SHIFT;
+		assert_eq!(keybindings.pressed(&input), None);
+		// Caps Lock makes the keysym uppercase
+		input.modifiers = Modifiers::CTRL | Modifiers::CAPS_LOCK;
+		input.keys_pressed = [keys::KEY_A].into();
+		assert_eq!(keybindings.pressed(&input), Some(Action::SelectAll));
+	}
+}
@@ -0,0 +1,223 @@
+//! Keyboard layouts that can be picked on the login screen.
+//!
+//! The layouts come from `TIBS_KEYBOARD_LAYOUTS`. The one each user logged in with is remembered
+//! in `<state folder>/keyboard-layouts` and selected again the next time they're selected, and it's
+//! passed to their session as `XKB_DEFAULT_LAYOUT` and `XKB_DEFAULT_VARIANT`.
+use std::{collections::HashMap, path::PathBuf};
+
+use rustamarine::{keymap::KeyboardLayout, Rustamarine};
+
+pub struct KeyboardLayouts {
+	layouts: Vec<KeyboardLayout>,
+	/// Descriptions from xkb (e.g. `English (US)`), the layout codes until there's a keyboard
+	names: Vec<String>,
+	active: usize,
+	/// Picked in the UI, applied to rustamarine on the next [`KeyboardLayouts::sync`]
+	requested: Option<usize>,
+	/// Layout each user logged in with last time, by username
+	remembered: HashMap<String, KeyboardLayout>,
+	remembered_path: Option<PathBuf>,
+}
+
+/// xkb keymaps have at most 4 groups, so that's as many layouts as can be switched between.
+const MAX_LAYOUTS: usize = 4;
+
+/// Parses the comma separated layouts of `TIBS_KEYBOARD_LAYOUTS`, warning about invalid ones and
+/// the ones past [`MAX_LAYOUTS`].
+pub fn parse_layouts(s: &str) -> Vec<KeyboardLayout> {
+	let mut layouts = s
+		.split(',')
+		.filter(|s| !s.trim().is_empty())
+		.filter_map(|s| {
+			let layout = s.parse().ok();
+			if layout.is_none() {
+				log::warn!("Ignoring invalid keyboard layout: {s:?}");
+			}
+			layout
+		})
+		.collect::<Vec<_>>();
+	if layouts.len() > MAX_LAYOUTS {
+		log::warn!(
+			"Ignoring keyboard layouts after the first {MAX_LAYOUTS}, xkb doesn't support more: {:?}",
+			layouts[MAX_LAYOUTS..]
+				.iter()
+				.map(|l| l.to_string())
+				.collect::<Vec<_>>()
+		);
+		layouts.truncate(MAX_LAYOUTS);
+	}
+	layouts
+}
+
+/// Parses the `username=layout` lines of the remembered layouts file.
+fn parse_remembered(s: &str) -> HashMap<String, KeyboardLayout> {
+	s.lines()
+		.filter_map(|line| {
+			let (username, layout) = line.split_once('=')?;
+			Some((username.trim().to_string(), layout.parse().ok()?))
+		})
+		.collect()
+}
+
+fn serialize_remembered(remembered: &HashMap<String, KeyboardLayout>) -> String {
+	let mut lines = remembered
+		.iter()
+		.map(|(username, layout)| format!("{username}={layout}\n"))
+		.collect::<Vec<_>>();
+	lines.sort();
+	lines.concat()
+}
+
+impl KeyboardLayouts {
+	/// `remembered_path` is where the layout of each user is saved, nothing is saved if it's `None`.
+	pub fn new(layouts: Vec<KeyboardLayout>, remembered_path: Option<PathBuf>) -> Self {
+		let remembered = remembered_path
+			.as_ref()
+			.and_then(|path| std::fs::read_to_string(path).ok())
+			.map(|s| parse_remembered(&s))
+			.unwrap_or_default();
+		Self {
+			names: layouts.iter().map(ToString::to_string).collect(),
+			layouts,
+			active: 0,
+			requested: None,
+			remembered,
+			remembered_path,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.layouts.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.layouts.is_empty()
+	}
+
+	pub fn names(&self) -> &[String] {
+		&self.names
+	}
+
+	pub fn active_index(&self) -> usize {
+		self.requested.unwrap_or(self.active)
+	}
+
+	pub fn active(&self) -> Option<&KeyboardLayout> {
+		self.layouts.get(self.active_index())
+	}
+
+	/// Short name of the active layout for the indicator, e.g. `US`.
+	pub fn active_short_name(&self) -> Option<String> {
+		self.active().map(|layout| layout.layout.to_uppercase())
+	}
+
+	pub fn select(&mut self, index: usize) {
+		if index < self.layouts.len() {
+			self.requested = Some(index);
+		}
+	}
+
+	pub fn select_next(&mut self) {
+		if !self.layouts.is_empty() {
+			self.select((self.active_index() + 1) % self.layouts.len());
+		}
+	}
+
+	/// Applies the layout picked in the UI and reads back the active one, which can also be changed
+	/// with the keyboard.
+	pub fn sync(&mut self, rmar: &mut Rustamarine) {
+		if let Some(index) = self.requested.take() {
+			rmar.set_active_keyboard_layout(index);
+			self.active = index;
+		}
+		let names = rmar.keyboard_layout_names();
+		// Otherwise there's no keyboard yet, or the layouts couldn't be set
+		if names.len() == self.layouts.len() {
+			self.names = names;
+			self.active = rmar.active_keyboard_layout();
+		}
+	}
+
+	/// Selects the layout `username` logged in with last time, if it's still available.
+	pub fn restore(&mut self, username: &str) {
+		let Some(layout) = self.remembered.get(username) else {
+			return;
+		};
+		if let Some(index) = self.layouts.iter().position(|l| l == layout) {
+			self.select(index);
+		}
+	}
+
+	/// Saves the active layout as the one of `username`.
+	pub fn remember(&mut self, username: &str) {
+		let Some(layout) = self.active().cloned() else {
+			return;
+		};
+		if self.remembered.get(username) == Some(&layout) {
+			return;
+		}
+		self.remembered.insert(username.to_string(), layout);
+		let Some(path) = &self.remembered_path else {
+			return;
+		};
+		let result = path
+			.parent()
+			.map_or(Ok(()), std::fs::create_dir_all)
+			.and_then(|_| std::fs::write(path, serialize_remembered(&self.remembered)));
+		if let Err(e) = result {
+			log::warn!(
+				"Failed to save the keyboard layouts to {}: {e}",
+				path.display()
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn layouts(s: &str) -> Vec<KeyboardLayout> {
+		s.split(',').map(|l| l.parse().unwrap()).collect()
+	}
+
+	#[test]
+	fn at_most_4_layouts() {
+		assert_eq!(
+			parse_layouts("us,pt,invalid(,de,fr,es"),
+			layouts("us,pt,de,fr")
+		);
+		assert!(parse_layouts("").is_empty());
+	}
+
+	#[test]
+	fn remembered_file() {
+		let remembered = parse_remembered("bob=pt\nalice = us(intl)\ninvalid\neve=us(\n");
+		assert_eq!(remembered.len(), 2);
+		assert_eq!(remembered["alice"], "us(intl)".parse().unwrap());
+		assert_eq!(
+			serialize_remembered(&remembered),
+			"alice=us(intl)\nbob=pt\n"
+		);
+	}
+
+	#[test]
+	fn restore_and_cycle() {
+		let mut keyboard_layouts = KeyboardLayouts::new(layouts("us,pt,us(intl)"), None);
+		assert_eq!(keyboard_layouts.active_short_name().as_deref(), Some("US"));
+		keyboard_layouts.select(2);
+		keyboard_layouts.remember("alice");
+		keyboard_layouts.select_next();
+		assert_eq!(keyboard_layouts.active_index(), 0);
+		keyboard_layouts.restore("alice");
+		assert_eq!(
+			keyboard_layouts.active(),
+			Some(&"us(intl)".parse().unwrap())
+		);
+		// Users that never logged in keep the current layout
+		keyboard_layouts.restore("bob");
+		assert_eq!(keyboard_layouts.active_index(), 2);
+		keyboard_layouts.select(3);
+		assert_eq!(keyboard_layouts.active_index(), 2);
+	}
+}
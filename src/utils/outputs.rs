@@ -0,0 +1,459 @@
+use std::str::FromStr;
+
+use rustamarine::screen::{Mode, Screen, Transform};
+use skia_safe::Matrix;
+
+/// What to show on each output when more than one screen is connected.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MultiMonitorPolicy {
+	/// Show the UI on every output
+	Mirror,
+	/// Show the UI on the primary output and only the background on the others
+	#[default]
+	Primary,
+	/// Show the UI on whichever output the cursor is on
+	FollowCursor,
+}
+
+impl FromStr for MultiMonitorPolicy {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"mirror" => Ok(Self::Mirror),
+			"primary" => Ok(Self::Primary),
+			"follow-cursor" => Ok(Self::FollowCursor),
+			_ => Err(()),
+		}
+	}
+}
+
+/// Scale of the UI on outputs that don't have one configured.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum UiScale {
+	/// Guess the scale from the DPI of the output
+	#[default]
+	Auto,
+	Fixed(f32),
+}
+
+impl FromStr for UiScale {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if s == "auto" {
+			return Ok(Self::Auto);
+		}
+		match s.parse() {
+			Ok(scale) if scale > 0.0 => Ok(Self::Fixed(scale)),
+			_ => Err(()),
+		}
+	}
+}
+
+impl UiScale {
+	pub fn for_screen(self, screen: &Screen) -> f32 {
+		match self {
+			Self::Auto => scale_from_dpi(
+				(screen.get_width(), screen.get_height()),
+				screen.get_physical_size(),
+			),
+			Self::Fixed(scale) => scale,
+		}
+	}
+}
+
+/// Guesses a scale from the size of an output in pixels and in millimeters, rounded to multiples of
+/// `0.25` so the UI stays sharp.
+///
+/// 96 DPI is scale 1. Outputs with an unknown or bogus physical size (projectors report things like
+/// 16x9 mm) get scale 1.
+pub fn scale_from_dpi(size: (u32, u32), physical_size: (u32, u32)) -> f32 {
+	const MM_PER_INCH: f32 = 25.4;
+	if physical_size.0 < 100 || physical_size.1 < 50 {
+		return 1.0;
+	}
+	let dpi = size.0 as f32 / (physical_size.0 as f32 / MM_PER_INCH);
+	((dpi / 96.0 * 4.0).round() / 4.0).clamp(1.0, 3.0)
+}
+
+/// Mode, transform and scale to use for an output, parsed from `NAME,mode=WxH@HZ,transform=90,scale=1.5`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OutputSettings {
+	pub name: String,
+	pub mode: Option<Mode>,
+	pub transform: Option<Transform>,
+	pub scale: Option<f32>,
+}
+
+impl FromStr for OutputSettings {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parts = s.split(',').map(str::trim);
+		let name = parts.next().filter(|n| !n.is_empty()).ok_or(())?;
+		let mut settings = Self {
+			name: name.to_string(),
+			..Default::default()
+		};
+		for part in parts {
+			match part.split_once('=').ok_or(())? {
+				("mode", mode) => {
+					let (size, refresh_rate) = mode.split_once('@').unwrap_or((mode, "0"));
+					let (width, height) = size.split_once('x').ok_or(())?;
+					settings.mode = Some(Mode {
+						width: width.parse().map_err(|_| ())?,
+						height: height.parse().map_err(|_| ())?,
+						refresh_rate: refresh_rate.parse().map_err(|_| ())?,
+						preferred: false,
+					});
+				}
+				("transform", transform) => settings.transform = Some(transform.parse()?),
+				("scale", scale) => {
+					settings.scale = Some(scale.parse().map_err(|_| ())?).filter(|&s| s > 0.0)
+				}
+				_ => return Err(()),
+			}
+		}
+		Ok(settings)
+	}
+}
+
+/// Applies the configured mode, transform and scale to a screen.
+///
+/// `default_scale` is used when the settings don't have a scale.
+pub fn configure_screen(
+	screen: &mut Screen,
+	settings: Option<&OutputSettings>,
+	default_scale: f32,
+) {
+	if let Some(mode) = settings.and_then(|s| s.mode.as_ref()) {
+		let same_size = screen.get_width() == mode.width && screen.get_height() == mode.height;
+		let same_refresh_rate =
+			mode.refresh_rate <= 0.0 || (screen.get_refresh_rate() - mode.refresh_rate).abs() < 0.5;
+		if !(same_size && same_refresh_rate) && !screen.set_mode(mode) {
+			log::warn!(
+				"Output {} rejected mode {}x{}@{}",
+				screen.get_name(),
+				mode.width,
+				mode.height,
+				mode.refresh_rate
+			);
+		}
+	}
+	screen.set_transform(settings.and_then(|s| s.transform).unwrap_or_default());
+	let scale = settings
+		.and_then(|s| s.scale)
+		.unwrap_or_else(|| default_scale.for_screen(screen));
+	screen.set_scale(scale);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+	pub name: String,
+	/// Horizontal position of the output in the global (cursor) coordinate space
+	pub x: i32,
+	/// Logical size, after applying the transform and the scale
+	pub width: u32,
+	pub height: u32,
+	/// Size of the framebuffer in pixels
+	pub buffer_size: (u32, u32),
+	pub transform: Transform,
+	pub scale: f32,
+}
+
+impl Output {
+	pub fn new(
+		name: impl Into<String>,
+		buffer_size: (u32, u32),
+		transform: Transform,
+		scale: f32,
+	) -> Self {
+		let (width, height) = if transform.is_sideways() {
+			(buffer_size.1, buffer_size.0)
+		} else {
+			buffer_size
+		};
+		Self {
+			name: name.into(),
+			x: 0,
+			width: (width as f32 / scale).round() as u32,
+			height: (height as f32 / scale).round() as u32,
+			buffer_size,
+			transform,
+			scale,
+		}
+	}
+	pub fn contains(&self, (x, y): (i32, i32)) -> bool {
+		x >= self.x && x < self.x + self.width as i32 && y >= 0 && y < self.height as i32
+	}
+	/// Converts a position in global coordinates to coordinates relative to this output.
+	pub fn to_local(&self, (x, y): (i32, i32)) -> (f32, f32) {
+		((x - self.x) as f32, y as f32)
+	}
+	/// Logical size of the output, this is what the UI is laid out with.
+	pub fn size(&self) -> (f32, f32) {
+		(self.width as f32, self.height as f32)
+	}
+	/// Maps logical coordinates to framebuffer pixels, applying the scale and the transform.
+	pub fn matrix(&self) -> Matrix {
+		let (buffer_width, buffer_height) = (self.buffer_size.0 as f32, self.buffer_size.1 as f32);
+		let mut matrix = Matrix::new_identity();
+		// Skia rotates clockwise, the transforms are counter-clockwise
+		match self.transform.rotation() {
+			90 => matrix
+				.pre_translate((0.0, buffer_height))
+				.pre_rotate(-90.0, None),
+			180 => matrix
+				.pre_translate((buffer_width, buffer_height))
+				.pre_rotate(180.0, None),
+			270 => matrix
+				.pre_translate((buffer_width, 0.0))
+				.pre_rotate(90.0, None),
+			_ => &mut matrix,
+		};
+		if self.transform.is_flipped() {
+			let width = if self.transform.is_sideways() {
+				buffer_height
+			} else {
+				buffer_width
+			};
+			matrix
+				.pre_translate((width, 0.0))
+				.pre_scale((-1.0, 1.0), None);
+		}
+		matrix.pre_scale((self.scale, self.scale), None);
+		matrix
+	}
+	/// Converts a position from 0 to 1 on the framebuffer (how touchscreens and tablets report
+	/// them) to global coordinates, undoing the transform and the scale.
+	pub fn from_normalized(&self, (x, y): (f64, f64)) -> (f32, f32) {
+		let position = self.matrix().invert().unwrap_or_default().map_xy(
+			x as f32 * self.buffer_size.0 as f32,
+			y as f32 * self.buffer_size.1 as f32,
+		);
+		(position.x + self.x as f32, position.y)
+	}
+}
+
+/// Arrangement of the enabled outputs, placed side by side from left to right.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OutputLayout {
+	outputs: Vec<Output>,
+	primary: Option<String>,
+}
+
+impl OutputLayout {
+	pub fn new(outputs: impl IntoIterator<Item = Output>, primary: Option<String>) -> Self {
+		let mut x = 0;
+		let outputs = outputs
+			.into_iter()
+			.map(|output| {
+				let output = Output { x, ..output };
+				x += output.width as i32;
+				output
+			})
+			.collect();
+		Self { outputs, primary }
+	}
+	pub fn from_screens(screens: &[Screen], primary: Option<String>) -> Self {
+		Self::new(
+			screens.iter().filter(|s| s.is_enabled()).map(|s| {
+				Output::new(
+					s.get_name(),
+					(s.get_width(), s.get_height()),
+					s.get_transform(),
+					s.get_scale(),
+				)
+			}),
+			primary,
+		)
+	}
+	pub fn is_empty(&self) -> bool {
+		self.outputs.is_empty()
+	}
+	pub fn get(&self, name: &str) -> Option<&Output> {
+		self.outputs.iter().find(|o| o.name == name)
+	}
+	/// The configured primary output, or the first one if it isn't connected.
+	pub fn primary(&self) -> Option<&Output> {
+		self
+			.primary
+			.as_deref()
+			.and_then(|name| self.get(name))
+			.or_else(|| self.outputs.first())
+	}
+	/// Biggest scale of all the outputs, images and the cursor are rasterized for this scale.
+	pub fn max_scale(&self) -> f32 {
+		self.outputs.iter().map(|o| o.scale).fold(1.0, f32::max)
+	}
+	pub fn output_at(&self, position: (i32, i32)) -> Option<&Output> {
+		self.outputs.iter().find(|o| o.contains(position))
+	}
+	/// Moves a position to the closest point that is inside of an output.
+	pub fn clamp(&self, (x, y): (i32, i32)) -> (i32, i32) {
+		let Some(last) = self.outputs.last() else {
+			return (x, y);
+		};
+		let x = x.clamp(0, last.x + last.width as i32 - 1);
+		let output = self
+			.outputs
+			.iter()
+			.find(|o| x < o.x + o.width as i32)
+			.unwrap_or(last);
+		(x, y.clamp(0, output.height as i32 - 1))
+	}
+	/// The output where the interactive UI lives.
+	///
+	/// In mirror mode, this is the output the cursor is on, the UI drawn there is then copied to the
+	/// other outputs.
+	pub fn ui_output(&self, policy: MultiMonitorPolicy, cursor: (i32, i32)) -> Option<&Output> {
+		match policy {
+			MultiMonitorPolicy::Primary => self.primary(),
+			MultiMonitorPolicy::Mirror | MultiMonitorPolicy::FollowCursor => {
+				self.output_at(cursor).or_else(|| self.primary())
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn dual_head() -> OutputLayout {
+		OutputLayout::new(
+			[
+				Output::new("DP-1", (1920, 1080), Transform::Normal, 1.0),
+				Output::new("HDMI-A-1", (1280, 1024), Transform::Normal, 1.0),
+			],
+			Some("HDMI-A-1".to_string()),
+		)
+	}
+
+	#[test]
+	fn outputs_are_placed_side_by_side() {
+		let layout = dual_head();
+		assert_eq!(layout.get("HDMI-A-1").unwrap().x, 1920);
+		assert_eq!(layout.output_at((1919, 0)).unwrap().name, "DP-1");
+		assert_eq!(layout.output_at((1920, 0)).unwrap().name, "HDMI-A-1");
+		assert_eq!(layout.output_at((1920, 1050)), None);
+	}
+
+	#[test]
+	fn clamp_keeps_the_cursor_inside_an_output() {
+		let layout = dual_head();
+		assert_eq!(layout.clamp((-5, -5)), (0, 0));
+		assert_eq!(layout.clamp((5000, 1070)), (3199, 1023));
+		assert_eq!(layout.clamp((100, 1070)), (100, 1070));
+	}
+
+	#[test]
+	fn ui_output_follows_policy() {
+		let layout = dual_head();
+		let cursor = (10, 10);
+		assert_eq!(
+			layout
+				.ui_output(MultiMonitorPolicy::Primary, cursor)
+				.unwrap()
+				.name,
+			"HDMI-A-1"
+		);
+		assert_eq!(
+			layout
+				.ui_output(MultiMonitorPolicy::FollowCursor, cursor)
+				.unwrap()
+				.name,
+			"DP-1"
+		);
+	}
+
+	#[test]
+	fn missing_primary_falls_back_to_first_output() {
+		let layout = OutputLayout::new(
+			[Output::new("eDP-1", (800, 600), Transform::Normal, 1.0)],
+			Some("DP-2".to_string()),
+		);
+		assert_eq!(layout.primary().unwrap().name, "eDP-1");
+	}
+
+	#[test]
+	fn transform_and_scale_change_the_logical_size() {
+		let output = Output::new("eDP-1", (2560, 1600), Transform::Rotate90, 2.0);
+		assert_eq!(output.size(), (800.0, 1280.0));
+	}
+
+	#[test]
+	fn matrix_maps_the_output_onto_the_framebuffer() {
+		let transforms = [
+			Transform::Normal,
+			Transform::Rotate90,
+			Transform::Rotate180,
+			Transform::Rotate270,
+			Transform::Flipped,
+			Transform::Flipped90,
+			Transform::Flipped180,
+			Transform::Flipped270,
+		];
+		for transform in transforms {
+			let output = Output::new("DP-1", (1920, 1080), transform, 1.5);
+			let matrix = output.matrix();
+			let (width, height) = output.size();
+			let bounds = matrix.map_rect(skia_safe::Rect::from_wh(width, height)).0;
+			assert_eq!(
+				bounds.round(),
+				skia_safe::IRect::from_wh(1920, 1080),
+				"{transform:?}"
+			);
+		}
+		// The top left corner of a panel rotated clockwise ends up on the bottom left of the buffer
+		let output = Output::new("DP-1", (1920, 1080), Transform::Rotate90, 1.0);
+		assert_eq!(output.matrix().map_xy(0.0, 0.0), (0.0, 1080.0).into());
+	}
+
+	#[test]
+	fn normalized_positions_follow_the_transform() {
+		let output = Output {
+			x: 1920,
+			..Output::new("eDP-1", (1920, 1080), Transform::Rotate90, 2.0)
+		};
+		let round = |(x, y): (f32, f32)| (x.round(), y.round());
+		// Bottom left of the panel is the top left of the rotated UI
+		assert_eq!(round(output.from_normalized((0.0, 1.0))), (1920.0, 0.0));
+		assert_eq!(round(output.from_normalized((1.0, 0.0))), (2460.0, 960.0));
+	}
+
+	#[test]
+	fn scale_from_dpi_rounds_to_quarters() {
+		// 14" 4K laptop
+		assert_eq!(scale_from_dpi((3840, 2160), (310, 174)), 3.0);
+		// 13" 2560x1600 laptop
+		assert_eq!(scale_from_dpi((2560, 1600), (286, 179)), 2.25);
+		// 24" 1080p monitor
+		assert_eq!(scale_from_dpi((1920, 1080), (527, 296)), 1.0);
+		// Unknown physical size
+		assert_eq!(scale_from_dpi((3840, 2160), (0, 0)), 1.0);
+		assert_eq!(scale_from_dpi((3840, 2160), (16, 9)), 1.0);
+	}
+
+	#[test]
+	fn parse_ui_scale() {
+		assert_eq!("auto".parse(), Ok(UiScale::Auto));
+		assert_eq!("1.5".parse(), Ok(UiScale::Fixed(1.5)));
+		assert!("0".parse::<UiScale>().is_err());
+	}
+
+	#[test]
+	fn parse_output_settings() {
+		let settings: OutputSettings = "eDP-1,mode=2560x1600@60,transform=90,scale=1.5"
+			.parse()
+			.unwrap();
+		assert_eq!(settings.name, "eDP-1");
+		assert_eq!(settings.mode.unwrap().width, 2560);
+		assert_eq!(settings.mode.unwrap().refresh_rate, 60.0);
+		assert_eq!(settings.transform, Some(Transform::Rotate90));
+		assert_eq!(settings.scale, Some(1.5));
+		assert!("DP-1,mode=big".parse::<OutputSettings>().is_err());
+		assert!("DP-1,rotation=90".parse::<OutputSettings>().is_err());
+	}
+}
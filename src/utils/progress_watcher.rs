@@ -56,6 +56,13 @@ pub struct ProgressWatcher {
 
 impl ProgressWatcher {
 	pub fn new() -> Self {
+		Self::spawn(matches!(std::env::var("TIBS_DEBUG_FAKE_PROGRESS_BAR"), Ok(s) if s == "1"))
+	}
+	/// Watcher that shows a fake boot instead of the progress of systemd.
+	pub fn fake_progress_bar() -> Self {
+		Self::spawn(true)
+	}
+	fn spawn(fake_progress_bar: bool) -> Self {
 		let (tx, rx) = channel::unbounded::<ProgressData>();
 		let shutdown = Arc::new(AtomicBool::new(false));
 		let shutdown_clone = Arc::clone(&shutdown);
@@ -63,7 +70,7 @@ impl ProgressWatcher {
 		let handle = thread::spawn(move || {
 			smol::block_on(async {
 				let mut progress_data = ProgressData::default();
-				if matches!(std::env::var("TIBS_DEBUG_FAKE_PROGRESS_BAR"), Ok(s) if s == "1") {
+				if fake_progress_bar {
 					fake_progress_bar(&tx, &shutdown_clone, &mut progress_data).await;
 					return Ok(());
 				}
@@ -152,6 +159,19 @@ impl ProgressWatcher {
 			handle: Some(handle),
 		}
 	}
+	/// Watcher that doesn't talk to systemd, the progress only changes with [`Self::set_progress`].
+	pub fn fake(progress_data: ProgressData) -> Self {
+		let (_, rx) = channel::unbounded::<ProgressData>();
+		ProgressWatcher {
+			progress_rx: rx,
+			progress_data,
+			shutdown: Arc::new(AtomicBool::new(true)),
+			handle: None,
+		}
+	}
+	pub fn set_progress(&mut self, progress_data: ProgressData) {
+		self.progress_data = progress_data;
+	}
 	pub fn poll_progress(&mut self) -> &ProgressData {
 		if let Some(new_progress) = self.progress_rx.try_recv().ok() {
 			self.progress_data = new_progress;
@@ -0,0 +1,291 @@
+//! Touchscreens and drawing tablets, turned into the mouse the UI already understands.
+//!
+//! The first finger on the screen moves the pointer: a tap is a left click and dragging scrolls
+//! whatever is under it, the other fingers are ignored. A pen works like a mouse, touching the
+//! tablet with its tip is a left click. Touchscreens are mapped to the primary output, since that's
+//! the one they're built into.
+use rustamarine::events::{DeviceKind, Event, Modifiers};
+
+use crate::{input::Input, outputs::Output};
+
+/// How far a finger can move, in logical pixels, before a tap becomes a drag
+const DRAG_THRESHOLD: f32 = 10.0;
+
+#[derive(Default)]
+pub struct TouchPointer {
+	/// The finger that moves the pointer
+	finger: Option<i32>,
+	/// Where the finger touched the screen, to tell taps from drags
+	start: (f32, f32),
+	last: (f32, f32),
+	dragging: bool,
+	/// Where the pointer was moved to this frame
+	position: Option<(f32, f32)>,
+	/// How much the dragging finger moved this frame
+	drag_delta: Option<(f32, f32)>,
+	button: TouchButton,
+}
+
+/// Left button state of the current frame, as reported by [`TouchInput`].
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct TouchButton {
+	pub down: bool,
+	pub pressed: bool,
+	pub released: bool,
+}
+
+impl TouchPointer {
+	/// Updates the pointer with a touch or tablet event, the other events are ignored. `output` is
+	/// where the positions are mapped to.
+	pub fn handle_event(&mut self, event: &Event, output: &Output) {
+		match *event {
+			Event::TouchDown { id, x, y } if self.finger.is_none() => {
+				let position = output.from_normalized((x, y));
+				self.finger = Some(id);
+				self.start = position;
+				self.last = position;
+				self.dragging = false;
+				self.position = Some(position);
+				self.press();
+			}
+			Event::TouchMotion { id, x, y } if self.finger == Some(id) => {
+				let position = output.from_normalized((x, y));
+				let distance = (position.0 - self.start.0).hypot(position.1 - self.start.1);
+				if !self.dragging && distance > DRAG_THRESHOLD {
+					// Lift the button without releasing it, so the drag doesn't click what's under it
+					self.dragging = true;
+					self.button.down = false;
+				}
+				if self.dragging {
+					let (dx, dy) = self.drag_delta.unwrap_or_default();
+					self.drag_delta = Some((dx + position.0 - self.last.0, dy + position.1 - self.last.1));
+				}
+				self.last = position;
+				self.position = Some(position);
+			}
+			Event::TouchUp { id } if self.finger == Some(id) => {
+				self.finger = None;
+				if !self.dragging {
+					self.release();
+				}
+			}
+			Event::TouchCancel { id } if self.finger == Some(id) => {
+				self.finger = None;
+				self.button.down = false;
+			}
+			// Its TouchUp or TabletToolTip won't come anymore
+			Event::DeviceRemoved {
+				kind: DeviceKind::Touch | DeviceKind::Tablet,
+				..
+			} => self.reset(),
+			Event::TabletToolMotion { x, y, .. } | Event::TabletToolProximity { x, y, .. } => {
+				self.position = Some(output.from_normalized((x, y)));
+			}
+			Event::TabletToolTip { x, y, down } => {
+				self.position = Some(output.from_normalized((x, y)));
+				if down {
+					self.press();
+				} else {
+					self.release();
+				}
+			}
+			_ => {}
+		}
+	}
+
+	fn press(&mut self) {
+		self.button.down = true;
+		self.button.pressed = true;
+	}
+
+	fn release(&mut self) {
+		if self.button.down {
+			self.button.down = false;
+			self.button.released = true;
+		}
+	}
+
+	/// Lifts the finger and the button without clicking, for when their events are lost (e.g. the
+	/// device was unplugged, or they came while tibs wasn't on screen). Otherwise the next touches
+	/// would be ignored and the button would stay down.
+	pub fn reset(&mut self) {
+		self.finger = None;
+		self.dragging = false;
+		self.button.down = false;
+	}
+
+	/// Where the pointer should be moved to, in global coordinates, if a finger or a pen moved it
+	/// this frame.
+	pub fn position(&self) -> Option<(i32, i32)> {
+		self
+			.position
+			.map(|(x, y)| (x.round() as i32, y.round() as i32))
+	}
+
+	/// How much the finger that is dragging moved this frame.
+	pub fn drag_delta(&self) -> Option<(f32, f32)> {
+		self.drag_delta
+	}
+
+	pub fn button(&self) -> TouchButton {
+		self.button
+	}
+
+	/// Forgets what happened this frame, called after the UI was updated.
+	pub fn end_frame(&mut self) {
+		self.position = None;
+		self.drag_delta = None;
+		self.button.pressed = false;
+		self.button.released = false;
+	}
+}
+
+/// Input with the left button also pressed by touches and pens.
+pub struct TouchInput<'a> {
+	pub input: &'a dyn Input,
+	pub button: TouchButton,
+}
+
+impl Input for TouchInput<'_> {
+	fn is_key_down(&self, key: u32) -> bool {
+		self.input.is_key_down(key)
+	}
+	fn is_key_pressed(&self, key: u32) -> bool {
+		self.input.is_key_pressed(key)
+	}
+	fn is_mouse_button_down(&self, button: u32) -> bool {
+		self.input.is_mouse_button_down(button) || (button == 0 && self.button.down)
+	}
+	fn is_mouse_button_pressed(&self, button: u32) -> bool {
+		self.input.is_mouse_button_pressed(button) || (button == 0 && self.button.pressed)
+	}
+	fn is_mouse_button_released(&self, button: u32) -> bool {
+		self.input.is_mouse_button_released(button) || (button == 0 && self.button.released)
+	}
+	fn get_mouse_scroll_x(&self) -> f64 {
+		self.input.get_mouse_scroll_x()
+	}
+	fn get_mouse_scroll_y(&self) -> f64 {
+		self.input.get_mouse_scroll_y()
+	}
+	fn get_typed_characters(&self) -> String {
+		self.input.get_typed_characters()
+	}
+	fn modifiers(&self) -> Modifiers {
+		self.input.modifiers()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rustamarine::screen::Transform;
+
+	fn output() -> Output {
+		Output::new("eDP-1", (1000, 1000), Transform::Normal, 1.0)
+	}
+
+	#[test]
+	fn tap_is_a_click() {
+		let mut touch = TouchPointer::default();
+		touch.handle_event(
+			&Event::TouchDown {
+				id: 3,
+				x: 0.5,
+				y: 0.25,
+			},
+			&output(),
+		);
+		assert_eq!(touch.position(), Some((500, 250)));
+		// Other fingers are ignored
+		touch.handle_event(
+			&Event::TouchDown {
+				id: 4,
+				x: 0.0,
+				y: 0.0,
+			},
+			&output(),
+		);
+		touch.handle_event(
+			&Event::TouchMotion {
+				id: 3,
+				x: 0.505,
+				y: 0.25,
+			},
+			&output(),
+		);
+		touch.handle_event(&Event::TouchUp { id: 3 }, &output());
+		assert_eq!(
+			touch.button(),
+			TouchButton {
+				down: false,
+				pressed: true,
+				released: true
+			}
+		);
+		assert_eq!(touch.drag_delta(), None);
+		touch.end_frame();
+		assert_eq!(touch.button(), TouchButton::default());
+		assert_eq!(touch.position(), None);
+	}
+
+	#[test]
+	fn drag_scrolls_without_clicking() {
+		let mut touch = TouchPointer::default();
+		touch.handle_event(
+			&Event::TouchDown {
+				id: 0,
+				x: 0.5,
+				y: 0.5,
+			},
+			&output(),
+		);
+		touch.end_frame();
+		touch.handle_event(
+			&Event::TouchMotion {
+				id: 0,
+				x: 0.5,
+				y: 0.4375,
+			},
+			&output(),
+		);
+		touch.handle_event(
+			&Event::TouchMotion {
+				id: 0,
+				x: 0.5,
+				y: 0.375,
+			},
+			&output(),
+		);
+		assert_eq!(touch.drag_delta(), Some((0.0, -125.0)));
+		assert!(!touch.button().down);
+		touch.end_frame();
+		touch.handle_event(&Event::TouchUp { id: 0 }, &output());
+		assert_eq!(touch.button(), TouchButton::default());
+	}
+
+	#[test]
+	fn lost_touches_are_reset() {
+		let touch_down = |id| Event::TouchDown { id, x: 0.5, y: 0.5 };
+		let mut touch = TouchPointer::default();
+		touch.handle_event(&touch_down(0), &output());
+		touch.end_frame();
+		touch.handle_event(
+			&Event::DeviceRemoved {
+				kind: DeviceKind::Touch,
+				name: "touchscreen".into(),
+			},
+			&output(),
+		);
+		assert_eq!(touch.button(), TouchButton::default());
+		// The next touch isn't ignored
+		touch.handle_event(&touch_down(1), &output());
+		assert!(touch.button().pressed);
+		touch.end_frame();
+		// Its TouchUp was drained while tibs wasn't on screen
+		touch.reset();
+		assert_eq!(touch.button(), TouchButton::default());
+		touch.handle_event(&touch_down(2), &output());
+		assert!(touch.button().down);
+	}
+}
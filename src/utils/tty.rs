@@ -46,6 +46,21 @@ impl TTYInfo {
 
 		Ok(())
 	}
+	/// Blocks until tty `number` is the active one.
+	pub fn wait_active(number: u16) -> color_eyre::Result<()> {
+		let console = File::open("/dev/console")?;
+		let ret = unsafe {
+			libc::ioctl(
+				console.as_raw_fd(),
+				VT_WAITACTIVE as u64,
+				number as libc::c_int,
+			)
+		};
+		if ret != 0 {
+			bail!("VT_WAITACTIVE failed: {}", std::io::Error::last_os_error());
+		}
+		Ok(())
+	}
 	pub fn get_active_tty_number() -> u16 {
 		#[repr(C)]
 		#[derive(Debug)]
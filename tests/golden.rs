@@ -0,0 +1,181 @@
+//! Golden image tests: the screens are rendered with `tibs --headless` using the scripts in
+//! `tests/golden` and compared with the reference images in `tests/golden/reference`.
+//!
+//! Font rendering and the shader can be slightly different between machines, so images are
+//! compared perceptually and a few different pixels are allowed.
+//!
+//! After an intentional change to the UI, run the tests with `TIBS_BLESS=1` to update the
+//! references, a missing reference is a failure otherwise.
+use std::{
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+use skia_safe::{image::CachingHint, AlphaType, ColorType, Data, Image, ImageInfo};
+
+/// Maximum perceptual difference between two pixels that are considered equal, from 0 to 1
+const PIXEL_THRESHOLD: f32 = 0.1;
+/// Fraction of the pixels that can be different
+const MAX_DIFFERENT_PIXELS: f32 = 0.005;
+
+fn golden_folder() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Renders `script` and returns the rendered frames.
+fn render(script: &str) -> Vec<PathBuf> {
+	let golden = golden_folder();
+	let output = Path::new(env!("CARGO_TARGET_TMPDIR"))
+		.join("golden")
+		.join(script);
+	let _ = std::fs::remove_dir_all(&output);
+	let status = Command::new(env!("CARGO_BIN_EXE_tibs"))
+		.arg("--headless")
+		.arg(golden.join(format!("{script}.tibs")))
+		.arg(&output)
+		.env(
+			"TIBS_ASSETS_FOLDER",
+			Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"),
+		)
+		.env("XDG_SESSION_DIRS", golden.join("sessions"))
+		.env("TIBS_USER_WALLPAPERS", "0")
+		// Bundled fonts, so the text looks the same whatever fonts the machine has
+		.env("TIBS_FONT_FAMILY", "asset:fonts.DejaVuSans")
+		.env(
+			"TIBS_FONTS",
+			"bold=asset:fonts.DejaVuSans-Bold:700;medium=asset:fonts.DejaVuSans:500",
+		)
+		.env_remove("TIBS_BACKGROUND")
+		.env_remove("TIBS_DEV_MODE")
+		.status()
+		.expect("Failed to run tibs");
+	assert!(status.success(), "tibs --headless failed for {script}");
+	let mut frames = std::fs::read_dir(&output)
+		.unwrap()
+		.map(|entry| entry.unwrap().path())
+		.filter(|path| path.extension().is_some_and(|e| e == "png"))
+		.collect::<Vec<_>>();
+	frames.sort();
+	frames
+}
+
+/// Size and RGBA pixels of a PNG.
+fn load_pixels(path: &Path) -> ((i32, i32), Vec<u8>) {
+	let data = Data::new_copy(&std::fs::read(path).unwrap());
+	let image =
+		Image::from_encoded(data).unwrap_or_else(|| panic!("Failed to decode {}", path.display()));
+	let size = (image.width(), image.height());
+	let info = ImageInfo::new(size, ColorType::RGBA8888, AlphaType::Unpremul, None);
+	let mut pixels = vec![0u8; size.0 as usize * size.1 as usize * 4];
+	assert!(image.read_pixels(
+		&info,
+		&mut pixels,
+		size.0 as usize * 4,
+		(0, 0),
+		CachingHint::Allow
+	));
+	(size, pixels)
+}
+
+/// YIQ of a pixel blended over white, Y is the perceived brightness.
+fn yiq(pixel: &[u8]) -> (f32, f32, f32) {
+	let alpha = pixel[3] as f32 / 255.0;
+	let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| 255.0 + (c as f32 - 255.0) * alpha);
+	(
+		r * 0.29889531 + g * 0.58662247 + b * 0.11448223,
+		r * 0.59597799 - g * 0.2741761 - b * 0.32180189,
+		r * 0.21147017 - g * 0.52261711 + b * 0.31114694,
+	)
+}
+
+/// Perceptual difference between two pixels, from 0 (equal) to 1, weighted like in "Measuring
+/// perceived color difference using YIQ NTSC transmission color space".
+fn pixel_difference(a: &[u8], b: &[u8]) -> f32 {
+	const MAX_DELTA: f32 = 35215.0;
+	let (ay, ai, aq) = yiq(a);
+	let (by, bi, bq) = yiq(b);
+	let delta = 0.5053 * (ay - by).powi(2) + 0.299 * (ai - bi).powi(2) + 0.1957 * (aq - bq).powi(2);
+	(delta / MAX_DELTA).sqrt()
+}
+
+/// Fraction of the pixels that look different.
+fn image_difference(a: &[u8], b: &[u8]) -> f32 {
+	let different = a
+		.chunks_exact(4)
+		.zip(b.chunks_exact(4))
+		.filter(|(a, b)| pixel_difference(a, b) > PIXEL_THRESHOLD)
+		.count();
+	different as f32 / (a.len() / 4) as f32
+}
+
+fn check(script: &str) {
+	let reference_folder = golden_folder().join("reference");
+	let bless = std::env::var("TIBS_BLESS").is_ok_and(|v| v == "1");
+	if bless {
+		std::fs::create_dir_all(&reference_folder).unwrap();
+	}
+	let mut failures = Vec::new();
+	for frame in render(script) {
+		let reference = reference_folder.join(frame.file_name().unwrap());
+		if bless {
+			std::fs::copy(&frame, &reference).unwrap();
+			println!("Updated {}", reference.display());
+			continue;
+		}
+		if !reference.exists() {
+			failures.push(format!(
+				"{} has no reference image, run the tests with TIBS_BLESS=1 to create it",
+				frame.display()
+			));
+			continue;
+		}
+		let (actual_size, actual) = load_pixels(&frame);
+		let (reference_size, expected) = load_pixels(&reference);
+		if actual_size != reference_size {
+			failures.push(format!(
+				"{} is {actual_size:?} but the reference is {reference_size:?}",
+				frame.display()
+			));
+			continue;
+		}
+		let difference = image_difference(&actual, &expected);
+		if difference > MAX_DIFFERENT_PIXELS {
+			failures.push(format!(
+				"{} differs from {} in {:.2}% of the pixels",
+				frame.display(),
+				reference.display(),
+				difference * 100.0
+			));
+		}
+	}
+	assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn loading_screen() {
+	check("loading");
+}
+
+#[test]
+fn login_screen() {
+	check("login");
+}
+
+#[test]
+fn background() {
+	check("background");
+}
+
+#[test]
+fn perceptual_difference() {
+	let white = [255, 255, 255, 255];
+	let black = [0, 0, 0, 255];
+	assert_eq!(pixel_difference(&white, &white), 0.0);
+	assert!(pixel_difference(&white, &black) > 0.9);
+	// Transparent pixels are blended over white
+	assert!(pixel_difference(&white, &[0, 0, 0, 0]) < 0.001);
+	assert!(pixel_difference(&[100, 100, 100, 255], &[101, 100, 99, 255]) < PIXEL_THRESHOLD);
+	let a = [white, white, white, white].concat();
+	let b = [white, white, white, black].concat();
+	assert_eq!(image_difference(&a, &b), 0.25);
+}